@@ -4,31 +4,145 @@ use anchor_lang::prelude::*;
 pub enum RaffleError {
     // create_raffle errors
     RaffleEndTimeInPast,
+    LeadTimeTooShort,
     MaxRaffleLengthExceeded,
     MaxTicketsIsZero,
     RaffleTooLarge,
+    PrizeCapExceeded,
     TicketPriceTooLow,
+    LoyaltyBonusTooHigh,
+    InvalidVestingSchedule,
+    KeeperBountyTooHigh,
+    OperatorWithdrawalCapTooHigh,
+    SecondChancePrizeTooHigh,
+    SecondChancePrizeNeedsClaimWindow,
+    InsufficientRentBalance,
+    MinTicketsExceedsMax,
+    UnsupportedForTokenRaffle,
+    MetadataTooLong,
+    InvalidFreeEntry,
+    InvalidFixedPrize,
+    FeeTooHigh,
+    CreateCooldownActive,
+    InvalidAcceptedMints,
+    UnsupportedForAcceptedMints,
 
     // buy_tickets errors
     RaffleHasEnded,
+    NumberOfTicketsIsZero,
+    SoldOut,
     InsufficientTickets,
+    WalletTicketLimitExceeded,
+    NotOnAllowlist,
+    SalesCutoffReached,
+    PurchaseLogMissing,
+    FreeEntryNotGiftable,
+    InsufficientFunds,
+    BatchTooLarge,
+    ManagerCannotEnter,
+    PriceOverflow,
+    InvalidMintIndex,
+
+    // withdraw_operator_funds errors
+    OnlyRaffleManagerCanWithdraw,
+    OperatorWithdrawalCapExceeded,
+
+    // withdraw_revenue errors
+    RevenueNotWithdrawable,
+    RevenueAlreadyWithdrawn,
+
+    // cancel_raffle errors
+    OnlyRaffleManagerCanCancel,
+    RaffleCancelled,
+
+    // extend_end_time errors
+    OnlyRaffleManagerCanExtend,
+    EndTimeNotExtended,
+
+    // pause_raffle / unpause_raffle errors
+    OnlyRaffleManagerCanPause,
+    RafflePaused,
+    RaffleNotPaused,
+
+    // deposit_prize_nft errors
+    OnlyRaffleManagerCanDeposit,
+    PrizeNftAlreadyDeposited,
+    TicketsAlreadySold,
+    InvalidPrizeNft,
+    UnsupportedForNftPrize,
+
+    // deposit_prize_tokens errors
+    PrizeTokensAlreadyDeposited,
+    PrizeTokenAmountIsZero,
+    InvalidPrizeTokenMint,
+    UnsupportedForTokenPrize,
+
+    // transfer_manager errors
+    OnlyRaffleManagerCanTransfer,
+
+    // accept_manager errors
+    NotPendingManager,
 
     // draw_winner errors
     WinnerAlreadyDrawn,
     RaffleNotOver,
     NoEntrants,
+    RefundsOpen,
+    MinimumNotReached,
+    WrongRandomnessSource,
 
     // draw_winner_callback errors
     DrawWinnerNotStarted,
     CallbackAlreadyInvoked,
     CallbackNotInvokedByVRF,
 
+    // draw_winner_from_slot_hashes errors
+    CommitSlotNotReached,
+
+    // redraw_winner errors
+    NoOtherEntrants,
+
     // claim_prize errors
     WinnerNotYetDrawn,
     NotWinner,
+    ClaimWindowNotOver,
+    ClaimWindowExpired,
     PrizeAlreadyClaimed,
+    LoyaltyHoldersMismatch,
+    NoTrancheUnlocked,
+    PrizeOverflow,
+    RaffleManagerMissing,
+    InvalidRaffleManager,
+
+    // claim_consolation errors
+    NotConsolationWinner,
+    ConsolationAlreadyClaimed,
+    ConsolationClaimWindowExpired,
+
+    // repair_winner_index errors
+    OnlyProgramOwnerCanRepair,
+    WinnerIndexNotCorrupted,
+
+    // open_refunds_after_expiry errors
+    RefundGracePeriodNotOver,
+    DrawAlreadyStarted,
+
+    // claim_refund errors
+    RefundsNotOpen,
+    NoTicketsToRefund,
+
+    // pool errors (any instruction moving the pool)
+    InsufficientPoolBalance,
+    TokenAccountsMissing,
+    InvalidEscrow,
+    InvalidTokenAccount,
+    AcceptedMintAccountsMismatch,
+
+    // entrants errors (any instruction storing an entrant count or index)
+    EntrantCountOverflow,
 
     // close_raffle errors
     OnlyRaffleManagerOrProgramOwnerCanClose,
     CanNotCloseActiveRaffle,
+    ManagerIndexMissing,
 }
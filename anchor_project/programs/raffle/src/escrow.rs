@@ -0,0 +1,369 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address, AssociatedToken, Create},
+    token::{self, CloseAccount, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+/// Where a token raffle keeps its pool: the raffle state's associated token
+/// account for the payment mint. The default address for SOL raffles, which
+/// no token account can have.
+pub fn escrow_address(raffle_state: &Account<RaffleState>) -> Pubkey {
+    payment_token_address(raffle_state, &raffle_state.key())
+}
+
+/// The associated token account of `owner` for the raffle's payment mint, or
+/// the default address for SOL raffles.
+pub fn payment_token_address(raffle_state: &RaffleState, owner: &Pubkey) -> Pubkey {
+    raffle_state
+        .payment_mint
+        .map(|mint| get_associated_token_address(owner, &mint))
+        .unwrap_or_default()
+}
+
+/// Where a token raffle keeps the tickets paid in `mint`, one of its
+/// accepted mints: the raffle state's associated token account for it.
+pub fn accepted_escrow_address(raffle_state: &Account<RaffleState>, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(&raffle_state.key(), mint)
+}
+
+/// Where tickets paid with `mint_index` go: the escrow of the accepted mint
+/// at that index, or the raffle's own pool for `None`. The default address
+/// for an index past the accepted mints.
+pub fn purchase_escrow_address(
+    raffle_state: &Account<RaffleState>,
+    mint_index: Option<u8>,
+) -> Pubkey {
+    let Some(mint_index) = mint_index else {
+        return escrow_address(raffle_state);
+    };
+    raffle_state
+        .accepted_mints
+        .get(usize::from(mint_index))
+        .map(|accepted_mint| accepted_escrow_address(raffle_state, &accepted_mint.mint))
+        .unwrap_or_default()
+}
+
+/// Where the raffle keeps its prize NFT: the raffle state's associated token
+/// account for the prize mint. The default address without a prize NFT.
+pub fn prize_nft_escrow_address(raffle_state: &Account<RaffleState>) -> Pubkey {
+    prize_nft_address(raffle_state, &raffle_state.key())
+}
+
+/// The associated token account of `owner` for the raffle's prize NFT, or the
+/// default address without a prize NFT.
+pub fn prize_nft_address(raffle_state: &RaffleState, owner: &Pubkey) -> Pubkey {
+    raffle_state
+        .prize_nft_mint
+        .map(|mint| get_associated_token_address(owner, &mint))
+        .unwrap_or_default()
+}
+
+/// Where the raffle keeps its prize tokens: the raffle state's associated
+/// token account for the prize token mint. The default address without a
+/// token prize.
+pub fn prize_token_escrow_address(raffle_state: &Account<RaffleState>) -> Pubkey {
+    prize_token_address(raffle_state, &raffle_state.key())
+}
+
+/// The associated token account of `owner` for the raffle's prize tokens, or
+/// the default address without a token prize.
+pub fn prize_token_address(raffle_state: &RaffleState, owner: &Pubkey) -> Pubkey {
+    raffle_state
+        .prize_token_mint
+        .map(|mint| get_associated_token_address(owner, &mint))
+        .unwrap_or_default()
+}
+
+/// The accounts that hold and move a token raffle's pool, or a raffle's
+/// prize NFT or prize tokens.
+pub struct TokenEscrow<'a, 'info> {
+    pub escrow: &'a Account<'info, TokenAccount>,
+    pub token_program: &'a Program<'info, Token>,
+}
+
+impl<'a, 'info> TokenEscrow<'a, 'info> {
+    /// Collects an instruction's optional escrow accounts. `None` for SOL
+    /// raffles; token raffles must provide them.
+    pub fn for_raffle(
+        raffle_state: &RaffleState,
+        escrow: Option<&'a Account<'info, TokenAccount>>,
+        token_program: Option<&'a Program<'info, Token>>,
+    ) -> Result<Option<Self>> {
+        Self::required_if(raffle_state.payment_mint.is_some(), escrow, token_program)
+    }
+
+    /// Collects an instruction's optional prize NFT escrow accounts. `None`
+    /// without a prize NFT; raffles with one must provide them.
+    pub fn for_prize_nft(
+        raffle_state: &RaffleState,
+        escrow: Option<&'a Account<'info, TokenAccount>>,
+        token_program: Option<&'a Program<'info, Token>>,
+    ) -> Result<Option<Self>> {
+        Self::required_if(raffle_state.prize_nft_mint.is_some(), escrow, token_program)
+    }
+
+    /// Collects an instruction's optional prize token escrow accounts. `None`
+    /// without a token prize; raffles with one must provide them.
+    pub fn for_prize_tokens(
+        raffle_state: &RaffleState,
+        escrow: Option<&'a Account<'info, TokenAccount>>,
+        token_program: Option<&'a Program<'info, Token>>,
+    ) -> Result<Option<Self>> {
+        Self::required_if(
+            raffle_state.prize_token_mint.is_some(),
+            escrow,
+            token_program,
+        )
+    }
+
+    fn required_if(
+        required: bool,
+        escrow: Option<&'a Account<'info, TokenAccount>>,
+        token_program: Option<&'a Program<'info, Token>>,
+    ) -> Result<Option<Self>> {
+        if !required {
+            return Ok(None);
+        }
+        match (escrow, token_program) {
+            (Some(escrow), Some(token_program)) => Ok(Some(Self {
+                escrow,
+                token_program,
+            })),
+            _ => err!(RaffleError::TokenAccountsMissing),
+        }
+    }
+}
+
+/// An accepted mint and its escrow, passed as remaining accounts, along with
+/// the token account on the other side of the instruction's transfer.
+pub struct AcceptedMintAccounts<'info> {
+    pub mint: &'info AccountInfo<'info>,
+    pub escrow: Account<'info, TokenAccount>,
+    pub counterparty: &'info AccountInfo<'info>,
+}
+
+/// What creating a missing associated token account of `owner` takes: who
+/// pays its rent and the programs creating it.
+pub struct TokenAccountCreator<'a, 'info> {
+    pub payer: &'a AccountInfo<'info>,
+    pub owner: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a Program<'info, AssociatedToken>,
+    pub system_program: &'a Program<'info, System>,
+}
+
+impl<'info> TokenAccountCreator<'_, 'info> {
+    /// Creates `owner`'s associated `token_account` for `mint`, so an owner
+    /// who never held the token can still be paid in it.
+    fn create(
+        &self,
+        token_account: &AccountInfo<'info>,
+        mint: &AccountInfo<'info>,
+        token_program: &Program<'info, Token>,
+    ) -> Result<()> {
+        associated_token::create(CpiContext::new(
+            self.associated_token_program.to_account_info(),
+            Create {
+                payer: self.payer.clone(),
+                associated_token: token_account.clone(),
+                authority: self.owner.clone(),
+                mint: mint.clone(),
+                system_program: self.system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))
+    }
+}
+
+/// Reads the accounts of each of the raffle's accepted mints from
+/// `remaining_accounts`, in the order of `accepted_mints`: the mint, its
+/// escrow, then the token account paid from it. With an `owner`, that token
+/// account must be the owner's associated token account for the mint.
+/// Raffles without accepted mints read none, leaving the remaining accounts
+/// to other uses.
+pub fn accepted_mint_accounts<'info>(
+    raffle_state: &Account<RaffleState>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    owner: Option<&Pubkey>,
+) -> Result<Vec<AcceptedMintAccounts<'info>>> {
+    if raffle_state.accepted_mints.is_empty() {
+        return Ok(vec![]);
+    }
+    require_eq!(
+        remaining_accounts.len(),
+        3 * raffle_state.accepted_mints.len(),
+        RaffleError::AcceptedMintAccountsMismatch
+    );
+    raffle_state
+        .accepted_mints
+        .iter()
+        .zip(remaining_accounts.chunks_exact(3))
+        .map(|(accepted_mint, accounts)| {
+            let (mint, escrow, counterparty) = (&accounts[0], &accounts[1], &accounts[2]);
+            require_keys_eq!(
+                *mint.key,
+                accepted_mint.mint,
+                RaffleError::AcceptedMintAccountsMismatch
+            );
+            require_keys_eq!(
+                *escrow.key,
+                accepted_escrow_address(raffle_state, &accepted_mint.mint),
+                RaffleError::InvalidEscrow
+            );
+            if let Some(owner) = owner {
+                require_keys_eq!(
+                    *counterparty.key,
+                    get_associated_token_address(owner, &accepted_mint.mint),
+                    RaffleError::InvalidTokenAccount
+                );
+            }
+            Ok(AcceptedMintAccounts {
+                mint,
+                escrow: Account::try_from(escrow)?,
+                counterparty,
+            })
+        })
+        .collect()
+}
+
+/// Pays out of each accepted mint's escrow to its counterparty the amount
+/// `amount` gives for the mint's index and the escrow's balance. A
+/// counterparty that doesn't exist yet is created with `creator`, which is
+/// then required.
+pub fn pay_from_accepted_mints<'info>(
+    raffle_state: &Account<'info, RaffleState>,
+    raffle_bump: u8,
+    token_program: Option<&Program<'info, Token>>,
+    accepted_mints: &[AcceptedMintAccounts<'info>],
+    creator: Option<&TokenAccountCreator<'_, 'info>>,
+    amount: impl Fn(usize, u64) -> Result<u64>,
+) -> Result<()> {
+    if accepted_mints.is_empty() {
+        return Ok(());
+    }
+    let token_program = token_program.ok_or(RaffleError::TokenAccountsMissing)?;
+    for (index, accounts) in accepted_mints.iter().enumerate() {
+        let amount = amount(index, accounts.escrow.amount)?;
+        if amount == 0 {
+            continue;
+        }
+        if accounts.counterparty.data_is_empty() {
+            creator.ok_or(RaffleError::TokenAccountsMissing)?.create(
+                accounts.counterparty,
+                accounts.mint,
+                token_program,
+            )?;
+        }
+        let escrow = TokenEscrow {
+            escrow: &accounts.escrow,
+            token_program,
+        };
+        pay_from_pool(
+            raffle_state,
+            raffle_bump,
+            Some(&escrow),
+            accounts.counterparty,
+            amount,
+        )?;
+    }
+    Ok(())
+}
+
+/// Pays `amount` out of the raffle's pool: lamports from the raffle account
+/// for SOL raffles, or tokens from the escrow for token raffles, in which
+/// case `recipient` must be a token account for the payment mint.
+pub fn pay_from_pool<'info>(
+    raffle_state: &Account<'info, RaffleState>,
+    raffle_bump: u8,
+    escrow: Option<&TokenEscrow<'_, 'info>>,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let Some(escrow) = escrow else {
+        // The raffle account itself must stay rent exempt
+        let rent_exempt_minimum =
+            Rent::get()?.minimum_balance(raffle_state.to_account_info().data_len());
+        require_gte!(
+            raffle_state
+                .get_lamports()
+                .saturating_sub(rent_exempt_minimum),
+            amount,
+            RaffleError::InsufficientPoolBalance
+        );
+        raffle_state.sub_lamports(amount)?;
+        recipient.add_lamports(amount)?;
+        return Ok(());
+    };
+
+    with_raffle_signer(raffle_state, raffle_bump, |signer_seeds| {
+        token::transfer(
+            CpiContext::new_with_signer(
+                escrow.token_program.to_account_info(),
+                Transfer {
+                    from: escrow.escrow.to_account_info(),
+                    to: recipient.clone(),
+                    authority: raffle_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    })
+}
+
+/// Empties the escrow into `leftover_recipient` and closes it, returning its
+/// rent to `rent_recipient`. A pool escrow only holds anything by now if tokens
+/// were sent to it outside of ticket sales, and a prize NFT or prize token
+/// escrow if the prize was never claimed, so `leftover_recipient` is only
+/// required then.
+pub fn close_escrow<'info>(
+    raffle_state: &Account<'info, RaffleState>,
+    raffle_bump: u8,
+    escrow: &TokenEscrow<'_, 'info>,
+    leftover_recipient: Option<&AccountInfo<'info>>,
+    rent_recipient: &AccountInfo<'info>,
+) -> Result<()> {
+    if escrow.escrow.amount > 0 {
+        let leftover_recipient = leftover_recipient.ok_or(RaffleError::TokenAccountsMissing)?;
+        pay_from_pool(
+            raffle_state,
+            raffle_bump,
+            Some(escrow),
+            leftover_recipient,
+            escrow.escrow.amount,
+        )?;
+    }
+
+    with_raffle_signer(raffle_state, raffle_bump, |signer_seeds| {
+        token::close_account(CpiContext::new_with_signer(
+            escrow.token_program.to_account_info(),
+            CloseAccount {
+                account: escrow.escrow.to_account_info(),
+                destination: rent_recipient.clone(),
+                authority: raffle_state.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    })
+}
+
+/// Runs `f` with the signer seeds of the raffle state PDA, which is the
+/// escrow's authority.
+fn with_raffle_signer<R>(
+    raffle_state: &RaffleState,
+    raffle_bump: u8,
+    f: impl FnOnce(&[&[&[u8]]]) -> R,
+) -> R {
+    f(&[&[
+        RAFFLE_SEED.as_bytes(),
+        raffle_state.creator.as_ref(),
+        &raffle_state.ticket_price.to_le_bytes(),
+        &raffle_state.max_tickets.to_le_bytes(),
+        &raffle_state.original_end_time.to_le_bytes(),
+        &[raffle_bump],
+    ]])
+}
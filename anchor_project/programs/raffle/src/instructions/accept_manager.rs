@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn accept_manager_impl(ctx: Context<AcceptManager>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let old_manager = raffle_state.raffle_manager;
+    raffle_state.raffle_manager = ctx.accounts.new_manager.key();
+    raffle_state.pending_manager = None;
+
+    emit!(ManagerTransferredEvent {
+        raffle_state: raffle_state.key(),
+        old_manager,
+        new_manager: raffle_state.raffle_manager,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptManager<'info> {
+    /// Pending manager taking over the raffle; must sign.
+    pub new_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `raffle_manager` set to `new_manager`.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.pending_manager == Some(new_manager.key())
+            @ RaffleError::NotPendingManager
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
+
+#[event]
+/// Emitted when a raffle is handed to a new manager.
+pub struct ManagerTransferredEvent {
+    /// Raffle state PDA that changed hands.
+    pub raffle_state: Pubkey,
+    /// Manager before the transfer.
+    pub old_manager: Pubkey,
+    /// Manager from now on.
+    pub new_manager: Pubkey,
+}
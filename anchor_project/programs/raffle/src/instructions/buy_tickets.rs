@@ -1,72 +1,304 @@
-use core::iter;
-
 use anchor_lang::{
     prelude::*,
     solana_program::{program::invoke, system_instruction::transfer},
 };
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    escrow::{pay_from_pool, purchase_escrow_address, TokenEscrow},
+    instructions::commit_to_slot,
+    state::{Purchase, PurchaseLog, RaffleState, RandomnessSource, PURCHASE_LOG_SEED, RAFFLE_SEED},
 };
 
-pub(crate) fn buy_tickets_impl(ctx: Context<BuyTickets>, number_of_tickets: u32) -> Result<()> {
+/// Most tickets one `buy_tickets` call may buy; larger purchases must be
+/// split into batches of at most this many tickets.
+pub const MAX_TICKETS_PER_PURCHASE: u32 = 256;
+
+pub(crate) fn buy_tickets_impl(
+    ctx: Context<BuyTickets>,
+    number_of_tickets: u32,
+    recipient: Option<Pubkey>,
+    _proof: Vec<[u8; 32]>,
+    mint_index: Option<u8>,
+) -> Result<()> {
     let raffle_state = &mut ctx.accounts.raffle_state;
     let buyer = &ctx.accounts.buyer;
+    let entrant = recipient.unwrap_or_else(|| buyer.key());
 
-    // Compute total price (overflow prevented by create_raffle checks)
-    let total_price = raffle_state
-        .ticket_price
-        .checked_mul(number_of_tickets as u64)
-        .unwrap();
-
-    // Transfer ticket price from buyer to the raffle account
-    invoke(
-        &transfer(
-            &buyer.key(),        // Source
-            &raffle_state.key(), // Destination
-            total_price,         // Amount in lamports
-        ),
-        &[buyer.to_account_info(), raffle_state.to_account_info()],
+    // Can't overflow given the create_raffle checks, but fails cleanly if
+    // that invariant is ever broken
+    let total_price = raffle_state.purchase_price_in(mint_index, number_of_tickets)?;
+
+    let escrow = TokenEscrow::for_raffle(
+        raffle_state,
+        ctx.accounts.escrow_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
     )?;
+    if let Some(escrow) = escrow {
+        // Transfer ticket price from the buyer's token account to the escrow
+        let buyer_token_account = ctx
+            .accounts
+            .buyer_token_account
+            .as_ref()
+            .ok_or(RaffleError::TokenAccountsMissing)?;
+        token::transfer(
+            CpiContext::new(
+                escrow.token_program.to_account_info(),
+                Transfer {
+                    from: buyer_token_account.to_account_info(),
+                    to: escrow.escrow.to_account_info(),
+                    authority: buyer.to_account_info(),
+                },
+            ),
+            total_price,
+        )?;
+    } else if !raffle_state.free_entry {
+        // Transfer ticket price from buyer to the raffle account
+        invoke(
+            &transfer(
+                &buyer.key(),        // Source
+                &raffle_state.key(), // Destination
+                total_price,         // Amount in lamports
+            ),
+            &[buyer.to_account_info(), raffle_state.to_account_info()],
+        )?;
+    }
 
-    // Reserve tickets for the buyer
-    raffle_state
-        .entrants
-        .extend(iter::repeat(buyer.key()).take(number_of_tickets as usize));
+    if let Some(purchase_log) = &mut ctx.accounts.purchase_log {
+        purchase_log.purchases.push(Purchase {
+            first_index: raffle_state.tickets_sold,
+            slot: ctx.accounts.clock.slot,
+        });
+    }
+
+    // Only the payment mint's pool is counted; tickets paid in an accepted
+    // mint stay in that mint's escrow
+    if mint_index.is_none() {
+        raffle_state.total_collected = raffle_state
+            .total_collected
+            .checked_add(total_price)
+            .ok_or(RaffleError::PrizeOverflow)?;
+    }
+
+    // Reserve tickets for the buyer, or whoever they are gifted to, noting
+    // the mint they were paid in for refunds
+    raffle_state.add_tickets(entrant, number_of_tickets);
+    if let Some(mint_index) = mint_index {
+        raffle_state.add_accepted_mint_tickets(entrant, mint_index, number_of_tickets);
+    }
+
+    if raffle_state.auto_draw && raffle_state.tickets_sold == raffle_state.max_tickets {
+        start_auto_draw(
+            raffle_state,
+            ctx.bumps.raffle_state,
+            buyer,
+            &ctx.accounts.clock,
+        )?;
+    }
 
     Ok(())
 }
 
+/// Starts the draw of an auto-draw raffle the last purchase sold out. A
+/// slot-hashes raffle commits to a slot, the buyer earning the keeper bounty
+/// for it, unless a draw delay still holds the draw back. A VRF raffle needs
+/// the oracle accounts, so `draw_winner` is left to a keeper.
+fn start_auto_draw<'info>(
+    raffle_state: &mut Account<'info, RaffleState>,
+    raffle_bump: u8,
+    buyer: &Signer<'info>,
+    clock: &Clock,
+) -> Result<()> {
+    if raffle_state.randomness_source == RandomnessSource::SlotHashes
+        && raffle_state.is_draw_open(clock.unix_timestamp)
+    {
+        if raffle_state.keeper_bounty > 0 {
+            pay_from_pool(
+                raffle_state,
+                raffle_bump,
+                None,
+                &buyer.to_account_info(),
+                raffle_state.keeper_bounty,
+            )?;
+        }
+        commit_to_slot(raffle_state, clock.slot);
+    }
+
+    emit!(RaffleReadyToDrawEvent {
+        raffle_state: raffle_state.key(),
+        commit_slot: raffle_state.commit_slot,
+    });
+
+    Ok(())
+}
+
+/// Whether `buyer` can pay for `number_of_tickets` tickets for `entrant`, paid
+/// with `mint_index`: the price of a SOL raffle's tickets plus the rent for
+/// growing the raffle account to fit a new entrant, as its realloc charges it,
+/// while keeping its own account rent exempt if anything is left. Checked
+/// before the raffle account grows, so an underfunded buyer gets a typed error
+/// rather than a failed realloc or system transfer.
+fn can_afford_purchase(
+    buyer: &AccountInfo,
+    raffle_state: &Account<RaffleState>,
+    entrant: &Pubkey,
+    number_of_tickets: u32,
+    mint_index: Option<u8>,
+) -> Result<bool> {
+    let rent = Rent::get()?;
+    let grown_space = RaffleState::grown_space(
+        raffle_state.to_account_info().data_len(),
+        raffle_state.entries_after_purchase(entrant),
+        raffle_state.accepted_entries_after_purchase(entrant, mint_index),
+    );
+    let growth_rent = rent
+        .minimum_balance(grown_space)
+        .saturating_sub(raffle_state.get_lamports());
+    let price = if raffle_state.payment_mint.is_none() && !raffle_state.free_entry {
+        raffle_state.purchase_price(number_of_tickets)?
+    } else {
+        0
+    };
+    let needed = price
+        .checked_add(growth_rent)
+        .ok_or(RaffleError::PriceOverflow)?;
+    Ok(buyer
+        .lamports()
+        .checked_sub(needed)
+        .is_some_and(|remaining| remaining == 0 || remaining >= rent.minimum_balance(0)))
+}
+
+#[event]
+/// Emitted when the purchase that sells out an auto-draw raffle starts its
+/// draw.
+pub struct RaffleReadyToDrawEvent {
+    /// Raffle state PDA ready to be drawn.
+    pub raffle_state: Pubkey,
+    /// Slot whose hash will pick the winner, for slot-hashes raffles that
+    /// committed to it; `None` when a keeper still has to start the draw.
+    pub commit_slot: Option<u64>,
+}
+
 #[derive(Accounts)]
-#[instruction(number_of_tickets: u32)]
+#[instruction(
+    number_of_tickets: u32,
+    recipient: Option<Pubkey>,
+    proof: Vec<[u8; 32]>,
+    mint_index: Option<u8>,
+)]
 pub struct BuyTickets<'info> {
-    /// Buyer paying for tickets; must sign.
-    #[account(mut)]
+    /// Buyer paying for tickets; must sign. Entered in the raffle unless the
+    /// tickets are gifted to a `recipient`.
+    #[account(
+        mut,
+        // Checked here, before `raffle_state` is grown at the buyer's expense
+        constraint = can_afford_purchase(
+            &buyer,
+            &raffle_state,
+            &recipient.unwrap_or_else(|| buyer.key()),
+            number_of_tickets,
+            mint_index,
+        )?
+            @ RaffleError::InsufficientFunds
+    )]
     pub buyer: Signer<'info>,
-    /// Raffle state PDA [RAFFLE_SEED, raffle_manager, ticket_price, max_tickets, end_time].
-    /// Receives ticket lamports and is debited later when prize claimed.
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Receives ticket lamports (SOL raffles) and is debited later when prize claimed.
+    /// Grown to fit a new entrant, with the extra rent paid by `buyer`.
     #[account(
         mut,
         seeds = [
             RAFFLE_SEED.as_bytes(),
-            raffle_state.raffle_manager.key().as_ref(),
+            raffle_state.creator.as_ref(),
             raffle_state.ticket_price.to_le_bytes().as_ref(),
             raffle_state.max_tickets.to_le_bytes().as_ref(),
-            raffle_state.end_time.to_le_bytes().as_ref()
+            raffle_state.original_end_time.to_le_bytes().as_ref()
         ],
         bump,
-        // Ensure raffle hasn't ended yet
-        constraint = raffle_state.entrants.len() < raffle_state.max_tickets as usize
-            && clock.unix_timestamp < raffle_state.end_time
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // An empty purchase would still log a purchase and move no tickets
+        constraint = number_of_tickets > 0
+            @ RaffleError::NumberOfTicketsIsZero,
+        // Bounds the compute spent on one purchase
+        constraint = number_of_tickets <= MAX_TICKETS_PER_PURCHASE
+            @ RaffleError::BatchTooLarge,
+        constraint = !raffle_state.paused
+            @ RaffleError::RafflePaused,
+        // Sales close exactly when drawing opens (see `is_raffle_over`), but a
+        // sold-out raffle is told apart from one past its end time
+        constraint = raffle_state.tickets_sold < raffle_state.max_tickets
+            @ RaffleError::SoldOut,
+        constraint = clock.unix_timestamp < raffle_state.end_time
             @ RaffleError::RaffleHasEnded,
+        // Reject purchases in the final stretch before end_time
+        constraint = clock.unix_timestamp < raffle_state.sales_cutoff_time
+            @ RaffleError::SalesCutoffReached,
         // Check if there are enough tickets available
-        // (overflow impossible: entrants.len() bounded by max_tickets which is u32)
-        constraint = raffle_state.entrants.len() + number_of_tickets as usize
-            <= raffle_state.max_tickets as usize
-            @ RaffleError::InsufficientTickets
+        // (overflow impossible: both bounded by u32 and summed as u64)
+        constraint = u64::from(raffle_state.tickets_sold) + u64::from(number_of_tickets)
+            <= u64::from(raffle_state.max_tickets)
+            @ RaffleError::InsufficientTickets,
+        constraint = !raffle_state
+            .exceeds_wallet_limit(&recipient.unwrap_or_else(|| buyer.key()), number_of_tickets)
+            @ RaffleError::WalletTicketLimitExceeded,
+        // Free entrants sign for themselves, so each entry costs a wallet
+        constraint = !raffle_state.free_entry
+            || recipient.is_none()
+            || recipient == Some(buyer.key())
+            @ RaffleError::FreeEntryNotGiftable,
+        constraint = raffle_state.is_allowed(&recipient.unwrap_or_else(|| buyer.key()), &proof)
+            @ RaffleError::NotOnAllowlist,
+        // A barred manager can't buy tickets, even as gifts, nor be gifted any
+        constraint = !raffle_state.manager_cannot_enter
+            || (buyer.key() != raffle_state.raffle_manager
+                && recipient != Some(raffle_state.raffle_manager))
+            @ RaffleError::ManagerCannotEnter,
+        constraint = !raffle_state.records_purchases || purchase_log.is_some()
+            @ RaffleError::PurchaseLogMissing,
+        constraint = mint_index
+            .is_none_or(|mint_index| usize::from(mint_index) < raffle_state.accepted_mints.len())
+            @ RaffleError::InvalidMintIndex,
+        // Grown by one entry for a new entrant, and one more for its first
+        // tickets paid in an accepted mint, never past one each per ticket
+        realloc = RaffleState::grown_space(
+            raffle_state.to_account_info().data_len(),
+            raffle_state.entries_after_purchase(&recipient.unwrap_or_else(|| buyer.key())),
+            raffle_state.accepted_entries_after_purchase(
+                &recipient.unwrap_or_else(|| buyer.key()),
+                mint_index,
+            ),
+        ),
+        realloc::payer = buyer,
+        realloc::zero = false,
     )]
     pub raffle_state: Account<'info, RaffleState>,
+    /// Purchase log PDA [PURCHASE_LOG_SEED, raffle_state]; required when the
+    /// raffle records purchases. Grown by one purchase, with the extra rent
+    /// paid by `buyer`.
+    #[account(
+        mut,
+        seeds = [PURCHASE_LOG_SEED.as_bytes(), raffle_state.key().as_ref()],
+        bump,
+        realloc = 8 + PurchaseLog::space(purchase_log.purchases.len() + 1),
+        realloc::payer = buyer,
+        realloc::zero = false,
+    )]
+    pub purchase_log: Option<Account<'info, PurchaseLog>>,
+    /// Buyer's token account paying for the tickets, in the mint `mint_index`
+    /// picks; token raffles only.
+    #[account(mut)]
+    pub buyer_token_account: Option<Account<'info, TokenAccount>>,
+    /// The raffle's escrow for the mint `mint_index` picks, receiving the
+    /// payment; token raffles only.
+    #[account(
+        mut,
+        address = purchase_escrow_address(&raffle_state, mint_index) @ RaffleError::InvalidEscrow
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Token program; token raffles only.
+    pub token_program: Option<Program<'info, Token>>,
     /// System program (lamport transfers).
     pub system_program: Program<'info, System>,
     /// Clock sysvar for timestamp validation.
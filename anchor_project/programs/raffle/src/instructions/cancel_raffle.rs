@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn cancel_raffle_impl(ctx: Context<CancelRaffle>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    raffle_state.cancelled = true;
+
+    emit!(RaffleCancelledEvent {
+        raffle_state: raffle_state.key(),
+        raffle_manager: raffle_state.raffle_manager,
+        tickets_sold: raffle_state.tickets_sold,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRaffle<'info> {
+    /// Raffle manager cancelling the raffle; must sign.
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `cancelled` flipped to true.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanCancel,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // The pending VRF request would still select a winner
+        constraint = !raffle_state.draw_winner_started
+            @ RaffleError::DrawAlreadyStarted
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
+
+#[event]
+/// Emitted when the manager cancels a raffle.
+pub struct RaffleCancelledEvent {
+    /// Raffle state PDA that was cancelled.
+    pub raffle_state: Pubkey,
+    /// Raffle manager who cancelled it.
+    pub raffle_manager: Pubkey,
+    /// Tickets sold before the cancellation, now refundable.
+    pub tickets_sold: u32,
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ClaimEligibility, RaffleState, RAFFLE_SEED};
+
+pub(crate) fn check_claim_eligibility_impl(
+    ctx: Context<CheckClaimEligibility>,
+) -> Result<ClaimEligibility> {
+    let raffle_state = &ctx.accounts.raffle_state;
+    Ok(ClaimEligibility {
+        drawn: raffle_state.winner_index.is_some(),
+        is_winner: raffle_state.is_winner(ctx.accounts.claimant.key),
+        already_claimed: raffle_state.claimed,
+        prize_amount: raffle_state.prize_amount()?,
+    })
+}
+
+#[derive(Accounts)]
+pub struct CheckClaimEligibility<'info> {
+    /// CHECK: Only its key is compared against the winning ticket; it need
+    /// not sign.
+    pub claimant: UncheckedAccount<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    escrow::pay_from_pool,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn claim_consolation_impl(ctx: Context<ClaimConsolation>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let consolation_winner = &ctx.accounts.consolation_winner;
+
+    // Second-chance prizes are lamports only, so there is no escrow
+    let amount = raffle_state.consolation_amount()?;
+    pay_from_pool(
+        raffle_state,
+        ctx.bumps.raffle_state,
+        None,
+        &consolation_winner.to_account_info(),
+        amount,
+    )?;
+    raffle_state.consolation_claimed = true;
+
+    emit!(ConsolationClaimedEvent {
+        raffle_state: raffle_state.key(),
+        consolation_winner: consolation_winner.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimConsolation<'info> {
+    /// CHECK: Validated against stored `consolation_winner_index` in
+    /// raffle_state.
+    #[account(mut)]
+    pub consolation_winner: UncheckedAccount<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited to pay the second-chance prize; `consolation_claimed` flipped
+    /// to true.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.winner_index.is_some()
+            @ RaffleError::WinnerNotYetDrawn,
+        constraint = raffle_state.consolation_winner() == Some(consolation_winner.key())
+            @ RaffleError::NotConsolationWinner,
+        constraint = !raffle_state.consolation_claimed
+            @ RaffleError::ConsolationAlreadyClaimed,
+        // Shares the winner's claim window, so an absent consolation winner
+        // can't keep the raffle from being closed
+        constraint = raffle_state.is_consolation_pending(clock.unix_timestamp)
+            @ RaffleError::ConsolationClaimWindowExpired
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Clock sysvar, for the claim window.
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[event]
+/// Emitted when the consolation winner is paid the second-chance prize.
+pub struct ConsolationClaimedEvent {
+    /// Raffle state PDA the second-chance prize was claimed from.
+    pub raffle_state: Pubkey,
+    /// Consolation winner who was paid.
+    pub consolation_winner: Pubkey,
+    /// Lamports paid.
+    pub amount: u64,
+}
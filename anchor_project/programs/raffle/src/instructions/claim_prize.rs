@@ -1,48 +1,446 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::clock::UnixTimestamp};
+use anchor_spl::{
+    associated_token::{self, AssociatedToken, Create},
+    token::{Mint, Token, TokenAccount},
+};
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    escrow::{
+        accepted_mint_accounts, escrow_address, pay_from_accepted_mints, pay_from_pool,
+        payment_token_address, prize_nft_address, prize_nft_escrow_address, prize_token_address,
+        prize_token_escrow_address, TokenAccountCreator, TokenEscrow,
+    },
+    math::{bps_of, mul_div, FULL_BPS},
+    state::{RaffleState, LOYALTY_BONUS_HOLDERS, RAFFLE_SEED},
 };
 
-pub(crate) fn claim_prize_impl(ctx: Context<ClaimPrize>) -> Result<()> {
-    let raffle_state = &mut ctx.accounts.raffle_state;
-    let winner = &mut ctx.accounts.winner;
+pub(crate) fn claim_prize_impl<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>,
+) -> Result<()> {
+    if ctx.accounts.raffle_state.prize_nft_mint.is_some() {
+        return claim_prize_nft(ctx.accounts, ctx.bumps.raffle_state);
+    }
+    // The prize tokens go out in the same instruction as the lamport prize,
+    // so the winner can't end up with one and not the other
+    if ctx.accounts.raffle_state.prize_token_mint.is_some() {
+        claim_prize_tokens(ctx.accounts, ctx.bumps.raffle_state)?;
+    }
+
+    let escrow = TokenEscrow::for_raffle(
+        &ctx.accounts.raffle_state,
+        ctx.accounts.escrow_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    // Token raffles pay the winner's token account rather than the winner
+    let recipient = match (&escrow, &ctx.accounts.winner_token_account) {
+        (None, _) => ctx.accounts.winner.to_account_info(),
+        (Some(escrow), Some(winner_token_account)) => {
+            if winner_token_account.data_is_empty() {
+                create_winner_token_account(
+                    ctx.accounts,
+                    winner_token_account,
+                    ctx.accounts.payment_mint.as_ref(),
+                    escrow,
+                )?;
+            }
+            winner_token_account.to_account_info()
+        }
+        (Some(_), None) => return err!(RaffleError::TokenAccountsMissing),
+    };
+    let accepted_mints = accepted_mint_accounts(
+        &ctx.accounts.raffle_state,
+        ctx.remaining_accounts,
+        Some(ctx.accounts.winner.key),
+    )?;
+
+    pay_prize(
+        &mut ctx.accounts.raffle_state,
+        ctx.bumps.raffle_state,
+        ctx.accounts.winner.key,
+        escrow.as_ref(),
+        &recipient,
+        ctx.accounts.raffle_manager.as_ref().map(AsRef::as_ref),
+        ctx.remaining_accounts,
+        ctx.accounts.clock.unix_timestamp,
+    )?;
+    // Tickets paid in accepted mints are entries like any other, so their
+    // escrows are part of the prize, paid to the winner's token accounts
+    // created as for the payment mint
+    let creator = match (
+        &ctx.accounts.payer,
+        &ctx.accounts.associated_token_program,
+        &ctx.accounts.system_program,
+    ) {
+        (Some(payer), Some(associated_token_program), Some(system_program)) => {
+            Some(TokenAccountCreator {
+                payer,
+                owner: &ctx.accounts.winner,
+                associated_token_program,
+                system_program,
+            })
+        }
+        _ => None,
+    };
+    pay_from_accepted_mints(
+        &ctx.accounts.raffle_state,
+        ctx.bumps.raffle_state,
+        ctx.accounts.token_program.as_ref(),
+        &accepted_mints,
+        creator.as_ref(),
+        |_, balance| Ok(balance),
+    )
+}
+
+/// Sends the prize NFT to the winner's associated token account, creating it
+/// if needed, and marks the raffle claimed. The pool stays in the raffle
+/// account, which goes to the manager on close.
+fn claim_prize_nft(accounts: &mut ClaimPrize<'_>, raffle_bump: u8) -> Result<()> {
+    let escrow = TokenEscrow::for_prize_nft(
+        &accounts.raffle_state,
+        accounts.prize_nft_escrow.as_ref(),
+        accounts.token_program.as_ref(),
+    )?
+    .ok_or(RaffleError::TokenAccountsMissing)?;
+    let winner_nft_account = accounts
+        .winner_nft_account
+        .as_ref()
+        .ok_or(RaffleError::TokenAccountsMissing)?;
+    if winner_nft_account.data_is_empty() {
+        create_winner_token_account(
+            accounts,
+            winner_nft_account,
+            accounts.prize_nft_mint.as_ref(),
+            &escrow,
+        )?;
+    }
 
-    let prize_amount = raffle_state.ticket_price * raffle_state.entrants.len() as u64;
+    let raffle_state = &accounts.raffle_state;
+    pay_from_pool(
+        raffle_state,
+        raffle_bump,
+        Some(&escrow),
+        &winner_nft_account.to_account_info(),
+        1,
+    )?;
 
-    raffle_state.sub_lamports(prize_amount)?;
-    winner.add_lamports(prize_amount)?;
+    let raffle_state = &mut accounts.raffle_state;
+    if raffle_state.runner_up() == Some(accounts.winner.key()) {
+        raffle_state.promote_runner_up();
+    }
+    raffle_state.claimed_bps = FULL_BPS;
     raffle_state.claimed = true;
 
+    emit!(PrizeClaimedEvent {
+        raffle_state: raffle_state.key(),
+        winner: accounts.winner.key(),
+        prize_amount: 1,
+        prize_token_amount: 0,
+        fee_amount: 0,
+    });
+
+    Ok(())
+}
+
+/// Sends the prize tokens to the claimant's associated token account,
+/// creating it if needed. The lamport prize is paid separately.
+fn claim_prize_tokens(accounts: &ClaimPrize<'_>, raffle_bump: u8) -> Result<()> {
+    let escrow = TokenEscrow::for_prize_tokens(
+        &accounts.raffle_state,
+        accounts.prize_token_escrow.as_ref(),
+        accounts.token_program.as_ref(),
+    )?
+    .ok_or(RaffleError::TokenAccountsMissing)?;
+    let winner_prize_token_account = accounts
+        .winner_prize_token_account
+        .as_ref()
+        .ok_or(RaffleError::TokenAccountsMissing)?;
+    if winner_prize_token_account.data_is_empty() {
+        create_winner_token_account(
+            accounts,
+            winner_prize_token_account,
+            accounts.prize_token_mint.as_ref(),
+            &escrow,
+        )?;
+    }
+
+    pay_from_pool(
+        &accounts.raffle_state,
+        raffle_bump,
+        Some(&escrow),
+        &winner_prize_token_account.to_account_info(),
+        accounts.raffle_state.prize_token_amount,
+    )
+}
+
+/// Creates the winner's associated `token_account` for `mint`, paid for by
+/// `payer`, so a winner who never held the token can still be paid.
+fn create_winner_token_account<'info>(
+    accounts: &ClaimPrize<'info>,
+    token_account: &UncheckedAccount<'info>,
+    mint: Option<&Account<'info, Mint>>,
+    escrow: &TokenEscrow<'_, 'info>,
+) -> Result<()> {
+    let (Some(payer), Some(mint), Some(associated_token_program), Some(system_program)) = (
+        &accounts.payer,
+        mint,
+        &accounts.associated_token_program,
+        &accounts.system_program,
+    ) else {
+        return err!(RaffleError::TokenAccountsMissing);
+    };
+
+    associated_token::create(CpiContext::new(
+        associated_token_program.to_account_info(),
+        Create {
+            payer: payer.to_account_info(),
+            associated_token: token_account.to_account_info(),
+            authority: accounts.winner.to_account_info(),
+            mint: mint.to_account_info(),
+            system_program: system_program.to_account_info(),
+            token_program: escrow.token_program.to_account_info(),
+        },
+    ))
+}
+
+/// Pays out the part of a drawn raffle's prize pool that has vested by `now`,
+/// marking the raffle claimed once all of it is paid. The loyalty bonus, if
+/// any, is paid in full with the first payout to `loyalty_holders`, which must
+/// be the top ticket holders in rank order, and so is the manager's fee, to
+/// `fee_recipient`. Everything else goes to `recipient`, which for token
+/// raffles is a token account paid from `escrow`. A runner-up `claimant`
+/// becomes the winner from then on.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "the pool is split across several payees"
+)]
+pub(crate) fn pay_prize<'info>(
+    raffle_state: &mut Account<'info, RaffleState>,
+    raffle_bump: u8,
+    claimant: &Pubkey,
+    escrow: Option<&TokenEscrow<'_, 'info>>,
+    recipient: &AccountInfo<'info>,
+    fee_recipient: Option<&AccountInfo<'info>>,
+    loyalty_holders: &[AccountInfo<'info>],
+    now: UnixTimestamp,
+) -> Result<()> {
+    if raffle_state.runner_up() == Some(*claimant) {
+        raffle_state.promote_runner_up();
+    }
+
+    let prize_amount = raffle_state.prize_amount()?;
+    let bonus_pool = bps_of(prize_amount, raffle_state.loyalty_bonus_bps)?;
+    let fee = raffle_state.fee_amount()?;
+    // create_raffle caps the three shares at the whole pool, so this can't
+    // underflow
+    let winner_share = prize_amount - bonus_pool - fee - raffle_state.consolation_amount()?;
+
+    let claimed_bps = raffle_state.claimed_bps;
+    // A free raffle's prize is zero, with nothing to vest
+    let unlocked_bps = if prize_amount == 0 {
+        FULL_BPS
+    } else {
+        raffle_state.unlocked_bps(now)
+    };
+    require_gt!(unlocked_bps, claimed_bps, RaffleError::NoTrancheUnlocked);
+
+    // Rounding dust from the bonus split stays with the recipient
+    let bonus_dust = if claimed_bps == 0 && bonus_pool > 0 {
+        bonus_pool - pay_loyalty_bonus(raffle_state, bonus_pool, loyalty_holders)?
+    } else {
+        0
+    };
+    // Fees are lamports only, as token raffles can't take one
+    let fee_paid = if claimed_bps == 0 && fee > 0 {
+        let fee_recipient = fee_recipient.ok_or(RaffleError::RaffleManagerMissing)?;
+        raffle_state.sub_lamports(fee)?;
+        fee_recipient.add_lamports(fee)?;
+        fee
+    } else {
+        0
+    };
+    // Computed from the cumulative vested amounts so tranches never leave dust
+    let payout =
+        bonus_dust + bps_of(winner_share, unlocked_bps)? - bps_of(winner_share, claimed_bps)?;
+
+    if payout > 0 {
+        pay_from_pool(raffle_state, raffle_bump, escrow, recipient, payout)?;
+    }
+    raffle_state.claimed_bps = unlocked_bps;
+    raffle_state.claimed = unlocked_bps == FULL_BPS;
+
+    emit!(PrizeClaimedEvent {
+        raffle_state: raffle_state.key(),
+        winner: *claimant,
+        prize_amount: payout,
+        // Token prizes can't vest, so they are all paid with the first claim
+        prize_token_amount: raffle_state.prize_token_amount,
+        fee_amount: fee_paid,
+    });
+
     Ok(())
 }
 
+/// Splits `bonus_pool` among the top ticket holders in proportion to their
+/// ticket counts and returns the total paid, which rounding can leave short of
+/// `bonus_pool`. Lamports only, as token raffles have no loyalty bonus.
+fn pay_loyalty_bonus<'info>(
+    raffle_state: &Account<'info, RaffleState>,
+    bonus_pool: u64,
+    loyalty_holders: &[AccountInfo<'info>],
+) -> Result<u64> {
+    let holders = raffle_state.top_ticket_holders(LOYALTY_BONUS_HOLDERS);
+    require_eq!(
+        holders.len(),
+        loyalty_holders.len(),
+        RaffleError::LoyaltyHoldersMismatch
+    );
+    let holder_tickets = holders.iter().map(|&(_, count)| u64::from(count)).sum();
+
+    let mut bonus_paid = 0;
+    for ((holder, count), account) in holders.iter().zip(loyalty_holders) {
+        require_keys_eq!(*holder, *account.key, RaffleError::LoyaltyHoldersMismatch);
+        let share = mul_div(bonus_pool, u64::from(*count), holder_tickets)?;
+        raffle_state.sub_lamports(share)?;
+        account.add_lamports(share)?;
+        bonus_paid += share;
+    }
+
+    Ok(bonus_paid)
+}
+
+#[event]
+/// Emitted each time a prize, or a vested part of it, is paid out.
+pub struct PrizeClaimedEvent {
+    /// Raffle state PDA the prize was claimed from.
+    pub raffle_state: Pubkey,
+    /// Winner, or runner-up, who claimed the prize.
+    pub winner: Pubkey,
+    /// Lamports, or base units of the payment mint, paid to the winner in
+    /// this claim, excluding the loyalty bonus; 1 for a prize NFT.
+    pub prize_amount: u64,
+    /// Base units of the prize token mint paid to the winner in this claim;
+    /// zero without a token prize.
+    pub prize_token_amount: u64,
+    /// Lamports paid to the raffle manager as its fee in this claim.
+    pub fee_amount: u64,
+}
+
 #[derive(Accounts)]
 pub struct ClaimPrize<'info> {
-    /// Winner receives prize lamports (any signer may facilitate claim).
-    /// CHECK: Validated against stored `winner_index` in raffle_state.
+    /// Winner receives prize lamports, or tokens via `winner_token_account`
+    /// (any signer may facilitate claim).
+    /// CHECK: Validated against stored `winner`, or `runner_up_index`
+    /// once the winner's claim window lapsed, in raffle_state.
     #[account(mut)]
     pub winner: UncheckedAccount<'info>,
-    /// Raffle state PDA [RAFFLE_SEED, raffle_manager, ticket_price, max_tickets, end_time].
-    /// Debited to pay the prize; `claimed` flipped to true.
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited to pay the prize; `claimed` flipped to true once fully paid.
     #[account(
         mut,
         seeds = [
             RAFFLE_SEED.as_bytes(),
-            raffle_state.raffle_manager.key().as_ref(),
+            raffle_state.creator.as_ref(),
             raffle_state.ticket_price.to_le_bytes().as_ref(),
             raffle_state.max_tickets.to_le_bytes().as_ref(),
-            raffle_state.end_time.to_le_bytes().as_ref()
+            raffle_state.original_end_time.to_le_bytes().as_ref()
         ],
         bump,
         constraint = raffle_state.winner_index.is_some()
             @ RaffleError::WinnerNotYetDrawn,
-        constraint = raffle_state.entrants[raffle_state.winner_index.unwrap() as usize]
-            .eq(winner.key)
+        constraint = raffle_state.is_winner(winner.key)
+            || raffle_state.runner_up() == Some(winner.key())
             @ RaffleError::NotWinner,
+        // The runner-up only gets a turn once the winner's claim window lapsed
+        constraint = raffle_state.is_winner(winner.key)
+            || raffle_state.is_runner_up_turn(clock.unix_timestamp)
+            @ RaffleError::ClaimWindowNotOver,
+        // Once it lapses with nothing claimed the winner has forfeited the
+        // prize to the runner-up or a redraw
+        constraint = !(raffle_state.is_winner(winner.key)
+            && raffle_state.is_claim_window_expired(clock.unix_timestamp))
+            @ RaffleError::ClaimWindowExpired,
         constraint = !raffle_state.claimed
             @ RaffleError::PrizeAlreadyClaimed
     )]
     pub raffle_state: Account<'info, RaffleState>,
+    /// Winner's associated token account receiving the prize; token raffles
+    /// only. Created if it doesn't exist yet.
+    /// CHECK: Address checked to be the winner's associated token account for
+    /// the payment mint; it may not exist yet.
+    #[account(
+        mut,
+        address = payment_token_address(&raffle_state, winner.key)
+            @ RaffleError::InvalidTokenAccount
+    )]
+    pub winner_token_account: Option<UncheckedAccount<'info>>,
+    /// The raffle's escrow, paying the prize; token raffles only.
+    #[account(mut, address = escrow_address(&raffle_state) @ RaffleError::InvalidEscrow)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Winner's associated token account receiving the prize NFT; raffles
+    /// with a prize NFT only. Created if it doesn't exist yet.
+    /// CHECK: Address checked to be the winner's associated token account for
+    /// the prize NFT; it may not exist yet.
+    #[account(
+        mut,
+        address = prize_nft_address(&raffle_state, winner.key)
+            @ RaffleError::InvalidTokenAccount
+    )]
+    pub winner_nft_account: Option<UncheckedAccount<'info>>,
+    /// The raffle's escrow holding the prize NFT; raffles with a prize NFT only.
+    #[account(
+        mut,
+        address = prize_nft_escrow_address(&raffle_state) @ RaffleError::InvalidEscrow
+    )]
+    pub prize_nft_escrow: Option<Account<'info, TokenAccount>>,
+    /// Winner's associated token account receiving the prize tokens; raffles
+    /// with a token prize only. Created if it doesn't exist yet.
+    /// CHECK: Address checked to be the winner's associated token account for
+    /// the prize token mint; it may not exist yet.
+    #[account(
+        mut,
+        address = prize_token_address(&raffle_state, winner.key)
+            @ RaffleError::InvalidTokenAccount
+    )]
+    pub winner_prize_token_account: Option<UncheckedAccount<'info>>,
+    /// The raffle's escrow holding the prize tokens; raffles with a token
+    /// prize only.
+    #[account(
+        mut,
+        address = prize_token_escrow_address(&raffle_state) @ RaffleError::InvalidEscrow
+    )]
+    pub prize_token_escrow: Option<Account<'info, TokenAccount>>,
+    /// Raffle manager receiving the fee; raffles with a fee only.
+    /// CHECK: Address checked to be the raffle's manager.
+    #[account(
+        mut,
+        address = raffle_state.raffle_manager @ RaffleError::InvalidRaffleManager
+    )]
+    pub raffle_manager: Option<UncheckedAccount<'info>>,
+    /// Token program; token raffles and raffles with a prize NFT or token
+    /// prize only.
+    pub token_program: Option<Program<'info, Token>>,
+    /// Pays the rent of `winner_token_account`, `winner_nft_account`,
+    /// `winner_prize_token_account` or a token account for an accepted mint
+    /// if it has to be created.
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    /// The raffle's payment mint, to create `winner_token_account`.
+    pub payment_mint: Option<Account<'info, Mint>>,
+    /// The raffle's prize NFT mint, to create `winner_nft_account`.
+    #[account(address = raffle_state.prize_nft_mint.unwrap_or_default() @ RaffleError::InvalidPrizeNft)]
+    pub prize_nft_mint: Option<Account<'info, Mint>>,
+    /// The raffle's prize token mint, to create `winner_prize_token_account`.
+    #[account(
+        address = raffle_state.prize_token_mint.unwrap_or_default()
+            @ RaffleError::InvalidPrizeTokenMint
+    )]
+    pub prize_token_mint: Option<Account<'info, Mint>>,
+    /// Associated token program, to create the winner's token account.
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    /// System program, to create the winner's token account.
+    pub system_program: Option<Program<'info, System>>,
+    /// Clock sysvar, to tell which vesting tranches have unlocked and whether
+    /// the winner's claim window lapsed.
+    pub clock: Sysvar<'info, Clock>,
 }
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::{
+    errors::RaffleError,
+    escrow::{accepted_mint_accounts, escrow_address, pay_from_accepted_mints, TokenEscrow},
+    instructions::claim_prize::pay_prize,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn claim_prize_to_impl<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimPrizeTo<'info>>,
+    _destination: Pubkey,
+) -> Result<()> {
+    let escrow = TokenEscrow::for_raffle(
+        &ctx.accounts.raffle_state,
+        ctx.accounts.escrow_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    // The winner picks where each accepted mint's escrow goes too
+    let accepted_mints =
+        accepted_mint_accounts(&ctx.accounts.raffle_state, ctx.remaining_accounts, None)?;
+
+    pay_prize(
+        &mut ctx.accounts.raffle_state,
+        ctx.bumps.raffle_state,
+        ctx.accounts.winner.key,
+        escrow.as_ref(),
+        &ctx.accounts.recipient,
+        ctx.accounts.raffle_manager.as_ref().map(AsRef::as_ref),
+        ctx.remaining_accounts,
+        ctx.accounts.clock.unix_timestamp,
+    )?;
+    pay_from_accepted_mints(
+        &ctx.accounts.raffle_state,
+        ctx.bumps.raffle_state,
+        ctx.accounts.token_program.as_ref(),
+        &accepted_mints,
+        None,
+        |_, balance| Ok(balance),
+    )
+}
+
+#[derive(Accounts)]
+#[instruction(destination: Pubkey)]
+pub struct ClaimPrizeTo<'info> {
+    /// Winner authorizing the prize to be sent elsewhere; must sign.
+    pub winner: Signer<'info>,
+    /// Receives the prize lamports in place of the winner; for token raffles,
+    /// a token account for the payment mint.
+    /// CHECK: Any account the winner chooses; must match `destination`.
+    #[account(mut, address = destination)]
+    pub recipient: UncheckedAccount<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited to pay the prize; `claimed` flipped to true once fully paid.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.winner_index.is_some()
+            @ RaffleError::WinnerNotYetDrawn,
+        constraint = raffle_state.is_winner(winner.key)
+            || raffle_state.runner_up() == Some(winner.key())
+            @ RaffleError::NotWinner,
+        // The runner-up only gets a turn once the winner's claim window lapsed
+        constraint = raffle_state.is_winner(winner.key)
+            || raffle_state.is_runner_up_turn(clock.unix_timestamp)
+            @ RaffleError::ClaimWindowNotOver,
+        // Once it lapses with nothing claimed the winner has forfeited the
+        // prize to the runner-up or a redraw
+        constraint = !(raffle_state.is_winner(winner.key)
+            && raffle_state.is_claim_window_expired(clock.unix_timestamp))
+            @ RaffleError::ClaimWindowExpired,
+        constraint = !raffle_state.claimed
+            @ RaffleError::PrizeAlreadyClaimed,
+        // The NFT goes to the winner's own account with claim_prize
+        constraint = raffle_state.prize_nft_mint.is_none()
+            @ RaffleError::UnsupportedForNftPrize,
+        // So do the prize tokens
+        constraint = raffle_state.prize_token_mint.is_none()
+            @ RaffleError::UnsupportedForTokenPrize
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// The raffle's escrow, paying the prize; token raffles only.
+    #[account(mut, address = escrow_address(&raffle_state) @ RaffleError::InvalidEscrow)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Raffle manager receiving the fee; raffles with a fee only.
+    /// CHECK: Address checked to be the raffle's manager.
+    #[account(
+        mut,
+        address = raffle_state.raffle_manager @ RaffleError::InvalidRaffleManager
+    )]
+    pub raffle_manager: Option<UncheckedAccount<'info>>,
+    /// Token program; token raffles only.
+    pub token_program: Option<Program<'info, Token>>,
+    /// Clock sysvar, to tell which vesting tranches have unlocked and whether
+    /// the winner's claim window lapsed.
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Token, TokenAccount},
+};
+
+use crate::{
+    errors::RaffleError,
+    escrow::{
+        accepted_mint_accounts, escrow_address, pay_from_accepted_mints, pay_from_pool,
+        TokenAccountCreator, TokenEscrow,
+    },
+    math::mul_div,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn claim_refund_impl<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimRefund<'info>>,
+) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let entrant = &ctx.accounts.entrant;
+
+    let tickets_before = u64::from(raffle_state.tickets_sold);
+    let payment_tickets_before =
+        tickets_before - u64::from(raffle_state.accepted_mint_tickets_sold());
+    let tickets = u64::from(raffle_state.remove_tickets(entrant.key));
+    require_gt!(tickets, 0, RaffleError::NoTicketsToRefund);
+    // Tickets paid in an accepted mint are refunded from that mint's escrow,
+    // at its fixed price
+    let accepted_tickets = raffle_state.remove_accepted_mint_tickets(entrant.key);
+    let payment_tickets = tickets - accepted_tickets.iter().copied().map(u64::from).sum::<u64>();
+
+    // Entrants share whatever the manager withdrew in proportion to their
+    // tickets; the last one refunded bears the rounding
+    let withdrawn_share = mul_div(raffle_state.operator_withdrawn, tickets, tickets_before)?;
+    raffle_state.operator_withdrawn -= withdrawn_share;
+    // The manager's refund bond makes up for the withdrawn share while it lasts
+    let bond_share = withdrawn_share.min(raffle_state.refund_bond);
+    raffle_state.refund_bond -= bond_share;
+
+    // The entrant's share of the payment mint's payments; exactly what they
+    // paid at a flat price, the average price paid under a price curve
+    let paid = if payment_tickets == 0 {
+        0
+    } else {
+        mul_div(
+            raffle_state.total_collected,
+            payment_tickets,
+            payment_tickets_before,
+        )?
+    };
+    raffle_state.total_collected -= paid;
+    let refund = paid - withdrawn_share + bond_share;
+    // An entrant who paid only in accepted mints needs no payment mint account
+    if refund > 0 {
+        let escrow = TokenEscrow::for_raffle(
+            raffle_state,
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        // Token raffles refund the entrant's token account rather than the entrant
+        let recipient = match (&escrow, &ctx.accounts.entrant_token_account) {
+            (None, _) => entrant.to_account_info(),
+            (Some(_), Some(entrant_token_account)) => entrant_token_account.to_account_info(),
+            (Some(_), None) => return err!(RaffleError::TokenAccountsMissing),
+        };
+        pay_from_pool(
+            raffle_state,
+            ctx.bumps.raffle_state,
+            escrow.as_ref(),
+            &recipient,
+            refund,
+        )?;
+    }
+
+    let accepted_refunds = raffle_state
+        .accepted_mints
+        .iter()
+        .zip(accepted_tickets)
+        .map(|(accepted_mint, count)| {
+            accepted_mint
+                .ticket_price
+                .checked_mul(u64::from(count))
+                .ok_or_else(|| RaffleError::PriceOverflow.into())
+        })
+        .collect::<Result<Vec<u64>>>()?;
+    let accepted_mints =
+        accepted_mint_accounts(raffle_state, ctx.remaining_accounts, Some(entrant.key))?;
+    // The entrant pays for any of its token accounts that don't exist yet
+    let creator = match (
+        &ctx.accounts.associated_token_program,
+        &ctx.accounts.system_program,
+    ) {
+        (Some(associated_token_program), Some(system_program)) => Some(TokenAccountCreator {
+            payer: entrant,
+            owner: entrant,
+            associated_token_program,
+            system_program,
+        }),
+        _ => None,
+    };
+    pay_from_accepted_mints(
+        raffle_state,
+        ctx.bumps.raffle_state,
+        ctx.accounts.token_program.as_ref(),
+        &accepted_mints,
+        creator.as_ref(),
+        |index, _| Ok(accepted_refunds[index]),
+    )
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    /// Entrant reclaiming their ticket payments; must sign.
+    #[account(mut)]
+    pub entrant: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited for the refund; the entrant's entry is removed from `entrants`.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.is_refunding(clock.unix_timestamp)
+            @ RaffleError::RefundsNotOpen
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Entrant's token account receiving the refund; token raffles only.
+    #[account(mut, token::authority = entrant)]
+    pub entrant_token_account: Option<Account<'info, TokenAccount>>,
+    /// The raffle's escrow, paying the refund; token raffles only.
+    #[account(mut, address = escrow_address(&raffle_state) @ RaffleError::InvalidEscrow)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Token program; token raffles only.
+    pub token_program: Option<Program<'info, Token>>,
+    /// Associated token program, to create the entrant's token account for an
+    /// accepted mint it paid in; raffles with accepted mints only.
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    /// System program, to create the entrant's token account for an accepted
+    /// mint it paid in; raffles with accepted mints only.
+    pub system_program: Option<Program<'info, System>>,
+    /// Clock sysvar, to tell whether the raffle missed its minimum.
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -1,17 +1,105 @@
 use anchor_lang::{prelude::*, solana_program::bpf_loader_upgradeable};
+use anchor_spl::token::{Token, TokenAccount};
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    escrow::{
+        accepted_mint_accounts, close_escrow, escrow_address, prize_nft_escrow_address,
+        prize_token_escrow_address, TokenEscrow,
+    },
+    state::{
+        ManagerRaffleIndex, PurchaseLog, RaffleState, MANAGER_INDEX_SEED, PURCHASE_LOG_SEED,
+        RAFFLE_SEED,
+    },
 };
 
-pub(crate) fn close_raffle_impl(_ctx: Context<CloseRaffle>) -> Result<()> {
+pub(crate) fn close_raffle_impl<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CloseRaffle<'info>>,
+) -> Result<()> {
+    let escrow = TokenEscrow::for_raffle(
+        &ctx.accounts.raffle_state,
+        ctx.accounts.escrow_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    // The raffle account's lamports go to the manager once the instruction
+    // completes, and so does a token raffle's escrow rent
+    let mut refunded_lamports = ctx.accounts.raffle_state.get_lamports();
+    if let Some(purchase_log) = &ctx.accounts.purchase_log {
+        refunded_lamports += purchase_log.get_lamports();
+    }
+    let prize_nft_escrow = TokenEscrow::for_prize_nft(
+        &ctx.accounts.raffle_state,
+        ctx.accounts.prize_nft_escrow.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    let prize_token_escrow = TokenEscrow::for_prize_tokens(
+        &ctx.accounts.raffle_state,
+        ctx.accounts.prize_token_escrow.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    // An unclaimed prize NFT or unclaimed prize tokens go back to the manager
+    for escrow in [escrow, prize_nft_escrow, prize_token_escrow]
+        .into_iter()
+        .flatten()
+    {
+        refunded_lamports += escrow.escrow.get_lamports();
+        close_escrow(
+            &ctx.accounts.raffle_state,
+            ctx.bumps.raffle_state,
+            &escrow,
+            ctx.accounts
+                .manager_token_account
+                .as_ref()
+                .map(AsRef::as_ref),
+            &ctx.accounts.raffle_manager,
+        )?;
+    }
+    // Accepted mints' escrows are closed the same way, anything left in one
+    // going to the manager's associated token account for its mint
+    let accepted_mints = accepted_mint_accounts(
+        &ctx.accounts.raffle_state,
+        ctx.remaining_accounts,
+        Some(ctx.accounts.raffle_manager.key),
+    )?;
+    if !accepted_mints.is_empty() {
+        let token_program = ctx
+            .accounts
+            .token_program
+            .as_ref()
+            .ok_or(RaffleError::TokenAccountsMissing)?;
+        for accounts in &accepted_mints {
+            refunded_lamports += accounts.escrow.get_lamports();
+            close_escrow(
+                &ctx.accounts.raffle_state,
+                ctx.bumps.raffle_state,
+                &TokenEscrow {
+                    escrow: &accounts.escrow,
+                    token_program,
+                },
+                Some(accounts.counterparty),
+                &ctx.accounts.raffle_manager,
+            )?;
+        }
+    }
+
+    let raffle_key = ctx.accounts.raffle_state.key();
+    if let Some(manager_index) = &mut ctx.accounts.manager_index {
+        manager_index.raffles.retain(|raffle| *raffle != raffle_key);
+    }
+
+    emit!(RaffleClosedEvent {
+        raffle_state: ctx.accounts.raffle_state.key(),
+        raffle_manager: ctx.accounts.raffle_manager.key(),
+        refunded_lamports,
+    });
+
     Ok(())
 }
 
 #[derive(Accounts)]
 pub struct CloseRaffle<'info> {
-    /// Either the raffle manager or the program upgrade authority; must sign.
+    /// The raffle manager, the program upgrade authority or the raffle's
+    /// `close_authority`; must sign.
     #[account(mut)]
     pub signer: Signer<'info>,
 
@@ -19,28 +107,99 @@ pub struct CloseRaffle<'info> {
     #[account(mut)]
     pub raffle_manager: UncheckedAccount<'info>,
 
-    /// Raffle state PDA [RAFFLE_SEED, raffle_manager, ticket_price, max_tickets, end_time].
-    /// Closed to `raffle_manager` when empty or prize claimed.
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Closed to `raffle_manager` when empty, or once the prize is claimed
+    /// and any second-chance prize is claimed or its claim window lapsed.
     #[account(
         mut,
         close = raffle_manager,
         has_one = raffle_manager @ RaffleError::OnlyRaffleManagerOrProgramOwnerCanClose,
         seeds = [
             RAFFLE_SEED.as_bytes(),
-            raffle_state.raffle_manager.key().as_ref(),
+            raffle_state.creator.as_ref(),
             raffle_state.ticket_price.to_le_bytes().as_ref(),
             raffle_state.max_tickets.to_le_bytes().as_ref(),
-            raffle_state.end_time.to_le_bytes().as_ref()
+            raffle_state.original_end_time.to_le_bytes().as_ref()
         ],
         bump,
-        constraint = raffle_state.claimed || raffle_state.entrants.is_empty()
+        // A second-chance prize still owed holds the raffle open, but only
+        // until its claim window lapses
+        constraint = (raffle_state.claimed
+            && !raffle_state.is_consolation_pending(clock.unix_timestamp))
+            || raffle_state.entrants.is_empty()
             @ RaffleError::CanNotCloseActiveRaffle,
         constraint = raffle_state.raffle_manager == signer.key()
             || program_data.upgrade_authority_address == Some(signer.key())
-            @ RaffleError::OnlyRaffleManagerOrProgramOwnerCanClose
+            || raffle_state.close_authority == Some(signer.key())
+            @ RaffleError::OnlyRaffleManagerOrProgramOwnerCanClose,
+        constraint = !raffle_state.records_purchases || purchase_log.is_some()
+            @ RaffleError::PurchaseLogMissing,
+        constraint = !raffle_state.listed_in_manager_index || manager_index.is_some()
+            @ RaffleError::ManagerIndexMissing
     )]
     pub raffle_state: Account<'info, RaffleState>,
 
+    /// Purchase log PDA [PURCHASE_LOG_SEED, raffle_state], closed along with
+    /// the raffle; required when the raffle records purchases.
+    #[account(
+        mut,
+        close = raffle_manager,
+        seeds = [PURCHASE_LOG_SEED.as_bytes(), raffle_state.key().as_ref()],
+        bump,
+    )]
+    pub purchase_log: Option<Account<'info, PurchaseLog>>,
+
+    /// Creator's manager index PDA [MANAGER_INDEX_SEED, creator], which the
+    /// raffle is removed from, shrinking it and returning the freed rent to
+    /// `raffle_manager`; required when the raffle is listed in it.
+    #[account(
+        mut,
+        seeds = [MANAGER_INDEX_SEED.as_bytes(), raffle_state.creator.as_ref()],
+        bump,
+        realloc = 8 + ManagerRaffleIndex::space(
+            manager_index
+                .raffles
+                .len()
+                .saturating_sub(usize::from(raffle_state.listed_in_manager_index)),
+        ),
+        realloc::payer = raffle_manager,
+        realloc::zero = false,
+    )]
+    pub manager_index: Option<Account<'info, ManagerRaffleIndex>>,
+
+    /// The raffle's escrow, closed along with the raffle; token raffles only.
+    #[account(mut, address = escrow_address(&raffle_state) @ RaffleError::InvalidEscrow)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// The raffle's escrow holding the prize NFT, closed along with the
+    /// raffle; raffles with a prize NFT only.
+    #[account(
+        mut,
+        address = prize_nft_escrow_address(&raffle_state) @ RaffleError::InvalidEscrow
+    )]
+    pub prize_nft_escrow: Option<Account<'info, TokenAccount>>,
+
+    /// The raffle's escrow holding the prize tokens, closed along with the
+    /// raffle; raffles with a token prize only.
+    #[account(
+        mut,
+        address = prize_token_escrow_address(&raffle_state) @ RaffleError::InvalidEscrow
+    )]
+    pub prize_token_escrow: Option<Account<'info, TokenAccount>>,
+
+    /// Raffle manager's token account, receiving anything left in the escrow,
+    /// or the prize NFT or prize tokens if they were never claimed; only
+    /// needed if the escrow isn't empty.
+    #[account(mut, token::authority = raffle_manager)]
+    pub manager_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Token program; token raffles and raffles with a prize NFT or token
+    /// prize only.
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// System program, required to resize `manager_index`.
+    pub system_program: Program<'info, System>,
+
     /// Program data account (upgrade authority source).
     #[account(
         seeds = [crate::ID.as_ref()],
@@ -48,4 +207,21 @@ pub struct CloseRaffle<'info> {
         seeds::program = bpf_loader_upgradeable::id(),
     )]
     pub program_data: Account<'info, ProgramData>,
+
+    /// Clock sysvar, for the consolation winner's claim window.
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[event]
+/// Emitted when a raffle is closed.
+pub struct RaffleClosedEvent {
+    /// Raffle state PDA that was closed.
+    pub raffle_state: Pubkey,
+    /// Raffle manager the rent was returned to.
+    pub raffle_manager: Pubkey,
+    /// Lamports returned to the raffle manager: the raffle account's balance,
+    /// plus the escrow's rent for token raffles and raffles with a prize NFT
+    /// or token prize, and the purchase log's rent for raffles recording
+    /// purchases.
+    pub refunded_lamports: u64,
 }
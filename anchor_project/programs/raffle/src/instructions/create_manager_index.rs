@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ManagerRaffleIndex, MANAGER_INDEX_SEED};
+
+pub(crate) fn create_manager_index_impl(
+    ctx: Context<CreateManagerIndex>,
+    create_cooldown: bool,
+) -> Result<()> {
+    let manager_index = &mut ctx.accounts.manager_index;
+    manager_index.manager = ctx.accounts.manager.key();
+    manager_index.create_cooldown = create_cooldown;
+    manager_index.last_created = 0;
+    manager_index.raffles = vec![];
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateManagerIndex<'info> {
+    /// Manager whose raffles the index lists; must sign. Pays the index's rent.
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    /// Manager index PDA [MANAGER_INDEX_SEED, manager], created empty here.
+    #[account(
+        init,
+        payer = manager,
+        space = 8 + ManagerRaffleIndex::space(0),
+        seeds = [MANAGER_INDEX_SEED.as_bytes(), manager.key().as_ref()],
+        bump,
+    )]
+    pub manager_index: Account<'info, ManagerRaffleIndex>,
+    /// System program, to create the index.
+    pub system_program: Program<'info, System>,
+}
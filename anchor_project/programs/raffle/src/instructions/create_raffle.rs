@@ -1,55 +1,368 @@
 use anchor_lang::{
     prelude::*,
-    solana_program::clock::{Clock, UnixTimestamp},
+    solana_program::{
+        clock::{Clock, UnixTimestamp, DEFAULT_MS_PER_SLOT},
+        program::invoke,
+        system_instruction::{transfer, MAX_PERMITTED_DATA_LENGTH},
+    },
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address, AssociatedToken, Create},
+    token::{Mint, Token, TokenAccount},
 };
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    math::FULL_BPS,
+    state::{
+        AcceptedMint, ManagerRaffleIndex, PriceCurve, PurchaseLog, RaffleState, RandomnessSource,
+        RevenueMode, VestingTranche, MANAGER_INDEX_SEED, MAX_ACCEPTED_MINTS, MAX_NAME_LEN,
+        MAX_URI_LEN, MAX_VESTING_TRANCHES, PURCHASE_LOG_SEED, RAFFLE_SEED,
+    },
 };
 
 /// Maximum raffle duration in seconds
 pub const THIRTY_DAYS_IN_SECS: i64 = 30 * 24 * 60 * 60;
+/// Minimum time in seconds between creating a raffle and its end, so buyers
+/// have a chance to discover it. Operators wanting a longer lead time (e.g.
+/// an hour) can raise it.
+pub const MIN_LEAD_SECS: i64 = 5;
 /// Minimum allowed ticket price in lamports
 pub const MIN_TICKET_PRICE_LAMPORTS: u64 = 100_000; // 0.0001 SOL
+/// Maximum share of the prize pool that can go to the loyalty bonus
+pub const MAX_LOYALTY_BONUS_BPS: u16 = 5_000; // 50%
+/// Maximum share of the ticket payments the manager can withdraw before the draw
+pub const MAX_OPERATOR_WITHDRAWAL_BPS: u16 = 5_000; // 50%
+/// Maximum share of the prize pool that can go to the consolation winner
+pub const MAX_SECOND_CHANCE_PRIZE_BPS: u16 = 5_000; // 50%
+/// Maximum share of the prize pool the manager can take as its fee
+pub const MAX_FEE_BPS: u16 = 1_000; // 10%
 
+#[expect(clippy::too_many_arguments, reason = "one argument per raffle setting")]
 pub(crate) fn create_raffle_impl(
     ctx: Context<CreateRaffle>,
     ticket_price: u64,
     max_tickets: u32,
+    min_tickets: u32,
     end_time: UnixTimestamp,
+    loyalty_bonus_bps: u16,
+    sales_cutoff_slots: u64,
+    terms_hash: [u8; 32],
+    vesting_schedule: Vec<VestingTranche>,
+    keeper_bounty: u64,
+    operator_withdrawal_cap_bps: u16,
+    claim_window_secs: u32,
+    refund_bond: u64,
+    max_tickets_per_wallet: u32,
+    allowlist_root: Option<[u8; 32]>,
+    max_prize: u64,
+    price_curve: PriceCurve,
+    name: String,
+    uri: String,
+    second_chance_prize_bps: u16,
+    free_entry: bool,
+    revenue_mode: RevenueMode,
+    fixed_prize_lamports: u64,
+    randomness_source: RandomnessSource,
+    close_authority: Option<Pubkey>,
+    draw_delay_secs: u32,
+    manager_cannot_enter: bool,
+    auto_draw: bool,
+    fee_bps: u16,
+    accepted_mints: Vec<AcceptedMint>,
+    list_in_manager_index: bool,
 ) -> Result<()> {
     let raffle_owner = &ctx.accounts.raffle_owner;
     let raffle_state = &mut ctx.accounts.raffle_state;
     msg!("New state account: {}", raffle_state.key());
 
-    let _ = ticket_price
-        .checked_mul(max_tickets as u64)
+    let max_pool = price_curve
+        .total_price(ticket_price, 0, max_tickets)
         .ok_or(RaffleError::RaffleTooLarge)?;
+    require!(
+        max_prize == 0 || max_pool <= max_prize,
+        RaffleError::PrizeCapExceeded
+    );
 
     raffle_state.raffle_manager = *raffle_owner.key;
+    raffle_state.creator = *raffle_owner.key;
+    raffle_state.pending_manager = None;
+    raffle_state.close_authority = close_authority;
     raffle_state.ticket_price = ticket_price;
+    raffle_state.price_curve = price_curve;
+    raffle_state.payment_mint = ctx.accounts.payment_mint.as_ref().map(Key::key);
+    raffle_state.accepted_mints = accepted_mints;
+    raffle_state.prize_nft_mint = None;
+    raffle_state.prize_token_mint = None;
+    raffle_state.prize_token_amount = 0;
     raffle_state.end_time = end_time;
+    raffle_state.original_end_time = end_time;
+    raffle_state.created_at = ctx.accounts.clock.unix_timestamp;
     raffle_state.winner_index = None;
+    raffle_state.winner = None;
+    raffle_state.previous_winner = None;
+    raffle_state.runner_up_index = None;
+    raffle_state.consolation_winner_index = None;
+    raffle_state.claim_window_secs = claim_window_secs;
+    raffle_state.draw_delay_secs = draw_delay_secs;
+    raffle_state.claim_deadline = None;
+    raffle_state.randomness_source = randomness_source;
+    raffle_state.commit_slot = None;
     raffle_state.max_tickets = max_tickets;
+    raffle_state.min_tickets = min_tickets;
+    // One entry per wallet limits sybil abuse of a free raffle
+    raffle_state.max_tickets_per_wallet = if free_entry {
+        1
+    } else {
+        max_tickets_per_wallet
+    };
     raffle_state.claimed = false;
+    raffle_state.consolation_claimed = false;
+    raffle_state.cancelled = false;
+    raffle_state.paused = false;
+    raffle_state.free_entry = free_entry;
+    raffle_state.manager_cannot_enter = manager_cannot_enter;
+    raffle_state.auto_draw = auto_draw;
+    raffle_state.loyalty_bonus_bps = loyalty_bonus_bps;
+    raffle_state.second_chance_prize_bps = second_chance_prize_bps;
+    raffle_state.fee_bps = fee_bps;
+    raffle_state.keeper_bounty = keeper_bounty;
+    raffle_state.operator_withdrawal_cap_bps = operator_withdrawal_cap_bps;
+    raffle_state.total_collected = 0;
+    raffle_state.operator_withdrawn = 0;
+    raffle_state.refund_bond = refund_bond;
+    raffle_state.revenue_mode = revenue_mode;
+    raffle_state.fixed_prize_lamports = fixed_prize_lamports;
+    raffle_state.revenue_withdrawn = false;
+    raffle_state.sales_cutoff_time = sales_cutoff_time(end_time, sales_cutoff_slots);
+    raffle_state.terms_hash = terms_hash;
+    raffle_state.name = name;
+    raffle_state.uri = uri;
+    raffle_state.allowlist_root = allowlist_root;
+    raffle_state.vesting_schedule = vesting_schedule;
+    raffle_state.claimed_bps = 0;
+    raffle_state.tickets_sold = 0;
+    raffle_state.accepted_mint_tickets = vec![];
     raffle_state.entrants = vec![];
+    raffle_state.records_purchases = ctx.accounts.purchase_log.is_some();
+    if let Some(purchase_log) = &mut ctx.accounts.purchase_log {
+        purchase_log.raffle_state = raffle_state.key();
+    }
+    raffle_state.listed_in_manager_index = list_in_manager_index;
+    let manager_index = &mut ctx.accounts.manager_index;
+    if list_in_manager_index {
+        manager_index.raffles.push(raffle_state.key());
+    }
+    // Unlisted raffles count towards the cooldown too
+    manager_index.last_created = ctx.accounts.clock.unix_timestamp;
+
+    // The bond and the fixed prize sit in the raffle account next to the
+    // ticket payments
+    let deposit = refund_bond
+        .checked_add(fixed_prize_lamports)
+        .ok_or(RaffleError::RaffleTooLarge)?;
+    if deposit > 0 {
+        invoke(
+            &transfer(raffle_owner.key, &raffle_state.key(), deposit),
+            &[
+                raffle_owner.to_account_info(),
+                raffle_state.to_account_info(),
+            ],
+        )?;
+    }
+
+    // Each accepted mint gets its own escrow, from the [mint, escrow] pairs
+    // passed as remaining accounts
+    require_eq!(
+        ctx.remaining_accounts.len(),
+        2 * raffle_state.accepted_mints.len(),
+        RaffleError::AcceptedMintAccountsMismatch
+    );
+    for (accepted_mint, accounts) in raffle_state
+        .accepted_mints
+        .iter()
+        .zip(ctx.remaining_accounts.chunks_exact(2))
+    {
+        let (mint, escrow) = (&accounts[0], &accounts[1]);
+        require_keys_eq!(
+            *mint.key,
+            accepted_mint.mint,
+            RaffleError::InvalidAcceptedMints
+        );
+        require_keys_eq!(
+            *escrow.key,
+            get_associated_token_address(&raffle_state.key(), mint.key),
+            RaffleError::InvalidEscrow
+        );
+        let (Some(token_program), Some(associated_token_program)) = (
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+        ) else {
+            return err!(RaffleError::TokenAccountsMissing);
+        };
+        associated_token::create(CpiContext::new(
+            associated_token_program.to_account_info(),
+            Create {
+                payer: raffle_owner.to_account_info(),
+                associated_token: escrow.clone(),
+                authority: raffle_state.to_account_info(),
+                mint: mint.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))?;
+    }
+
+    emit!(RaffleCreatedEvent {
+        raffle_state: raffle_state.key(),
+        raffle_manager: raffle_state.raffle_manager,
+        ticket_price,
+        payment_mint: raffle_state.payment_mint,
+        max_tickets,
+        end_time,
+        terms_hash,
+        name: raffle_state.name.clone(),
+        uri: raffle_state.uri.clone(),
+    });
 
     Ok(())
 }
 
+/// Space of the raffle account to create, including the discriminator. The
+/// account starts with room for no tickets and `buy_tickets` grows it as they
+/// sell, with buyers paying for the growth. Fails with a clear error rather
+/// than deep inside the account creation if the account could never grow to
+/// hold `max_tickets`, or if `payer` can't afford what creating the raffle
+/// takes from it: the rent of the account at this starting size and of the
+/// escrows of a token raffle and its `accepted_mints`, plus the `deposit`
+/// moved into the raffle. Anchor creates `init` accounts before checking any
+/// constraint, so this runs as part of the `space` expression.
+fn affordable_raffle_space(
+    payer: &AccountInfo,
+    max_tickets: u32,
+    token_raffle: bool,
+    accepted_mints: &[AcceptedMint],
+    deposit: u64,
+) -> Result<usize> {
+    let max_space = if accepted_mints.is_empty() {
+        RaffleState::account_space(max_tickets)
+    } else {
+        RaffleState::accepting_account_space(max_tickets)
+    };
+    require_gte!(
+        MAX_PERMITTED_DATA_LENGTH as usize,
+        8 + max_space,
+        RaffleError::RaffleTooLarge
+    );
+    let escrows = usize::from(token_raffle) + accepted_mints.len();
+    let space = 8 + RaffleState::account_space(0);
+    let rent = Rent::get()?;
+    let escrow_rent = rent.minimum_balance(TokenAccount::LEN);
+    let required = (0..escrows).fold(
+        rent.minimum_balance(space).saturating_add(deposit),
+        |required, _| required.saturating_add(escrow_rent),
+    );
+    require_gte!(
+        payer.lamports(),
+        required,
+        RaffleError::InsufficientRentBalance
+    );
+    Ok(space)
+}
+
+/// Converts a cutoff expressed in slots into the timestamp after which ticket
+/// sales stop, using the nominal slot duration. A cutoff longer than the raffle
+/// simply stops all sales.
+fn sales_cutoff_time(end_time: UnixTimestamp, sales_cutoff_slots: u64) -> UnixTimestamp {
+    let cutoff_secs = sales_cutoff_slots.saturating_mul(DEFAULT_MS_PER_SLOT) / 1000;
+    end_time.saturating_sub(i64::try_from(cutoff_secs).unwrap_or(i64::MAX))
+}
+
+/// A vesting schedule is either empty (no vesting) or at most
+/// `MAX_VESTING_TRANCHES` non-empty tranches that add up to the whole prize.
+fn is_valid_vesting_schedule(vesting_schedule: &[VestingTranche]) -> bool {
+    vesting_schedule.is_empty()
+        || (vesting_schedule.len() <= MAX_VESTING_TRANCHES
+            && vesting_schedule.iter().all(|tranche| tranche.bps > 0)
+            && vesting_schedule
+                .iter()
+                .map(|tranche| u32::from(tranche.bps))
+                .sum::<u32>()
+                == u32::from(FULL_BPS))
+}
+
+/// Accepted mints are at most `MAX_ACCEPTED_MINTS` distinct mints other than
+/// the payment mint, each selling tickets at a non-zero price whose total for
+/// all `max_tickets` tickets fits in `u64`.
+fn is_valid_accepted_mints(
+    accepted_mints: &[AcceptedMint],
+    payment_mint: Option<Pubkey>,
+    max_tickets: u32,
+) -> bool {
+    accepted_mints.len() <= MAX_ACCEPTED_MINTS
+        && accepted_mints.iter().enumerate().all(|(i, accepted_mint)| {
+            Some(accepted_mint.mint) != payment_mint
+                && accepted_mints[..i]
+                    .iter()
+                    .all(|other| other.mint != accepted_mint.mint)
+                && accepted_mint.ticket_price > 0
+                && accepted_mint
+                    .ticket_price
+                    .checked_mul(u64::from(max_tickets))
+                    .is_some()
+        })
+}
+
 #[derive(Accounts)]
-#[instruction(ticket_price: u64, max_tickets: u32, end_time: i64)]
+#[instruction(
+    ticket_price: u64,
+    max_tickets: u32,
+    min_tickets: u32,
+    end_time: i64,
+    loyalty_bonus_bps: u16,
+    sales_cutoff_slots: u64,
+    terms_hash: [u8; 32],
+    vesting_schedule: Vec<VestingTranche>,
+    keeper_bounty: u64,
+    operator_withdrawal_cap_bps: u16,
+    claim_window_secs: u32,
+    refund_bond: u64,
+    max_tickets_per_wallet: u32,
+    allowlist_root: Option<[u8; 32]>,
+    max_prize: u64,
+    price_curve: PriceCurve,
+    name: String,
+    uri: String,
+    second_chance_prize_bps: u16,
+    free_entry: bool,
+    revenue_mode: RevenueMode,
+    fixed_prize_lamports: u64,
+    randomness_source: RandomnessSource,
+    close_authority: Option<Pubkey>,
+    draw_delay_secs: u32,
+    manager_cannot_enter: bool,
+    auto_draw: bool,
+    fee_bps: u16,
+    accepted_mints: Vec<AcceptedMint>,
+    list_in_manager_index: bool,
+)]
 pub struct CreateRaffle<'info> {
-    /// Raffle manager and payer for raffle_state account creation.
+    /// Raffle manager and payer for raffle_state account creation, the
+    /// refund bond and the fixed prize.
     #[account(mut)]
     pub raffle_owner: Signer<'info>,
     /// Raffle state PDA [RAFFLE_SEED, raffle_owner, ticket_price, max_tickets, end_time].
-    /// Space scales with `max_tickets`; rent paid by `raffle_owner`.
+    /// Starts without room for tickets; rent paid by `raffle_owner`.
     #[account(
         init,
         payer = raffle_owner,
-        space = {8 + RaffleState::account_space(max_tickets)},
+        space = affordable_raffle_space(
+            &raffle_owner,
+            max_tickets,
+            payment_mint.is_some(),
+            &accepted_mints,
+            refund_bond.saturating_add(fixed_prize_lamports),
+        )?,
         seeds = [
             RAFFLE_SEED.as_bytes(),
             raffle_owner.key().as_ref(),
@@ -60,16 +373,141 @@ pub struct CreateRaffle<'info> {
         bump,
         constraint = end_time > clock.unix_timestamp
             @ RaffleError::RaffleEndTimeInPast,
+        constraint = end_time >= clock.unix_timestamp + MIN_LEAD_SECS
+            @ RaffleError::LeadTimeTooShort,
         constraint = end_time <= clock.unix_timestamp + THIRTY_DAYS_IN_SECS
             @ RaffleError::MaxRaffleLengthExceeded,
         constraint = max_tickets > 0
             @ RaffleError::MaxTicketsIsZero,
-        constraint = ticket_price >= MIN_TICKET_PRICE_LAMPORTS
-            @ RaffleError::TicketPriceTooLow
+        constraint = min_tickets <= max_tickets
+            @ RaffleError::MinTicketsExceedsMax,
+        constraint = free_entry || ticket_price >= MIN_TICKET_PRICE_LAMPORTS
+            @ RaffleError::TicketPriceTooLow,
+        // A giveaway stays free for every ticket
+        constraint = !free_entry
+            || (ticket_price == 0 && price_curve == PriceCurve::Flat && payment_mint.is_none())
+            @ RaffleError::InvalidFreeEntry,
+        constraint = loyalty_bonus_bps <= MAX_LOYALTY_BONUS_BPS
+            @ RaffleError::LoyaltyBonusTooHigh,
+        constraint = is_valid_vesting_schedule(&vesting_schedule)
+            @ RaffleError::InvalidVestingSchedule,
+        // A draw needs at least one ticket sold, so the pool always covers it
+        constraint = keeper_bounty <= ticket_price
+            @ RaffleError::KeeperBountyTooHigh,
+        constraint = operator_withdrawal_cap_bps <= MAX_OPERATOR_WITHDRAWAL_BPS
+            @ RaffleError::OperatorWithdrawalCapTooHigh,
+        constraint = second_chance_prize_bps <= MAX_SECOND_CHANCE_PRIZE_BPS
+            @ RaffleError::SecondChancePrizeTooHigh,
+        // The consolation winner claims within the winner's claim window, so
+        // an unclaimed second-chance prize can't hold the raffle open forever
+        constraint = second_chance_prize_bps == 0 || claim_window_secs > 0
+            @ RaffleError::SecondChancePrizeNeedsClaimWindow,
+        // The winner's share is what's left of the pool after the others
+        constraint = fee_bps <= MAX_FEE_BPS
+            && loyalty_bonus_bps + second_chance_prize_bps + fee_bps <= FULL_BPS
+            @ RaffleError::FeeTooHigh,
+        // All are paid in lamports, which a token raffle's pool doesn't hold
+        constraint = payment_mint.is_none()
+            || (loyalty_bonus_bps == 0
+                && keeper_bounty == 0
+                && refund_bond == 0
+                && second_chance_prize_bps == 0
+                && fee_bps == 0
+                && revenue_mode == RevenueMode::WinnerTakesPool)
+            @ RaffleError::UnsupportedForTokenRaffle,
+        // Only a raffle whose manager keeps the revenue has a fixed prize
+        constraint = (revenue_mode == RevenueMode::ManagerKeepsRevenue)
+            == (fixed_prize_lamports > 0)
+            @ RaffleError::InvalidFixedPrize,
+        constraint = is_valid_accepted_mints(
+            &accepted_mints,
+            payment_mint.as_ref().map(Key::key),
+            max_tickets,
+        )
+            @ RaffleError::InvalidAcceptedMints,
+        // Each accepted mint's escrow is paid out whole, as the prize or in
+        // refunds, so none of the features dividing the pool otherwise apply
+        constraint = accepted_mints.is_empty()
+            || (payment_mint.is_some()
+                && price_curve == PriceCurve::Flat
+                && vesting_schedule.is_empty()
+                && operator_withdrawal_cap_bps == 0)
+            @ RaffleError::UnsupportedForAcceptedMints,
+        constraint = payment_mint.is_some() == escrow_token_account.is_some()
+            @ RaffleError::TokenAccountsMissing,
+        constraint = name.len() <= MAX_NAME_LEN && uri.len() <= MAX_URI_LEN
+            @ RaffleError::MetadataTooLong
     )]
     pub raffle_state: Account<'info, RaffleState>,
+    /// Mint tickets are paid in, for a token raffle. Omitted for a SOL raffle.
+    pub payment_mint: Option<Account<'info, Mint>>,
+    /// Escrow for a token raffle's pool: the raffle state's associated token
+    /// account for `payment_mint`, created here with rent paid by `raffle_owner`.
+    #[account(
+        init,
+        payer = raffle_owner,
+        associated_token::mint = payment_mint,
+        associated_token::authority = raffle_state,
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Purchase log PDA [PURCHASE_LOG_SEED, raffle_state], created empty here
+    /// with rent paid by `raffle_owner` for a raffle that records its
+    /// purchases. Omitted otherwise.
+    #[account(
+        init,
+        payer = raffle_owner,
+        space = 8 + PurchaseLog::space(0),
+        seeds = [PURCHASE_LOG_SEED.as_bytes(), raffle_state.key().as_ref()],
+        bump,
+    )]
+    pub purchase_log: Option<Account<'info, PurchaseLog>>,
+    /// Manager index PDA [MANAGER_INDEX_SEED, raffle_owner], created
+    /// beforehand with `create_manager_index`. Its `last_created` is set,
+    /// unless its creation cooldown since the last raffle hasn't passed, and
+    /// it is grown by one raffle, with the extra rent paid by `raffle_owner`,
+    /// if `list_in_manager_index` is set.
+    #[account(
+        mut,
+        seeds = [MANAGER_INDEX_SEED.as_bytes(), raffle_owner.key().as_ref()],
+        bump,
+        realloc = 8 + ManagerRaffleIndex::space(
+            manager_index.raffles.len() + usize::from(list_in_manager_index)
+        ),
+        realloc::payer = raffle_owner,
+        realloc::zero = false,
+        constraint = !manager_index.is_cooling_down(clock.unix_timestamp)
+            @ RaffleError::CreateCooldownActive,
+    )]
+    pub manager_index: Account<'info, ManagerRaffleIndex>,
+    /// Token program; token raffles only.
+    pub token_program: Option<Program<'info, Token>>,
+    /// Associated token program, to create the escrows; token raffles only.
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     /// System program for account creation.
     pub system_program: Program<'info, System>,
     /// Clock sysvar for timestamp validation.
     pub clock: Sysvar<'info, Clock>,
 }
+
+#[event]
+/// Emitted when a new raffle is created.
+pub struct RaffleCreatedEvent {
+    /// Raffle state PDA of the new raffle.
+    pub raffle_state: Pubkey,
+    /// Manager/creator of the raffle.
+    pub raffle_manager: Pubkey,
+    /// Ticket price in lamports, or in base units of `payment_mint`.
+    pub ticket_price: u64,
+    /// Mint tickets are paid in, or `None` for lamports.
+    pub payment_mint: Option<Pubkey>,
+    /// Maximum number of tickets.
+    pub max_tickets: u32,
+    /// Raffle end time as Unix timestamp (seconds).
+    pub end_time: i64,
+    /// Hash of the off-chain terms document the raffle is bound to.
+    pub terms_hash: [u8; 32],
+    /// Human-readable name of the raffle.
+    pub name: String,
+    /// URI of the raffle's off-chain metadata, or empty.
+    pub uri: String,
+}
@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{self, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RevenueMode, RAFFLE_SEED},
+};
+
+pub(crate) fn deposit_prize_nft_impl(ctx: Context<DepositPrizeNft>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    raffle_state.prize_nft_mint = Some(ctx.accounts.prize_nft_mint.key());
+
+    // Move the NFT from the manager into the raffle's escrow
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.manager_nft_account.to_account_info(),
+                to: ctx.accounts.prize_nft_escrow.to_account_info(),
+                authority: ctx.accounts.raffle_manager.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+
+    emit!(PrizeNftDepositedEvent {
+        raffle_state: raffle_state.key(),
+        prize_nft_mint: ctx.accounts.prize_nft_mint.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DepositPrizeNft<'info> {
+    /// Raffle manager depositing the NFT; must sign. Pays the escrow's rent.
+    #[account(mut)]
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `prize_nft_mint` set to the deposited NFT.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanDeposit,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.prize_nft_mint.is_none()
+            @ RaffleError::PrizeNftAlreadyDeposited,
+        // Buyers must know what they are playing for
+        constraint = raffle_state.entrants.is_empty()
+            @ RaffleError::TicketsAlreadySold,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // The NFT can't be split into a loyalty bonus, a second-chance prize
+        // or vesting tranches, nor stand alongside a fixed prize or prize
+        // tokens, and the pool left for the manager is paid out in lamports
+        constraint = raffle_state.payment_mint.is_none()
+            && raffle_state.prize_token_mint.is_none()
+            && raffle_state.loyalty_bonus_bps == 0
+            && raffle_state.second_chance_prize_bps == 0
+            && raffle_state.vesting_schedule.is_empty()
+            && raffle_state.revenue_mode == RevenueMode::WinnerTakesPool
+            @ RaffleError::UnsupportedForNftPrize
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Mint of the NFT given away as the prize; a single token with no decimals.
+    #[account(
+        constraint = prize_nft_mint.decimals == 0 && prize_nft_mint.supply == 1
+            @ RaffleError::InvalidPrizeNft
+    )]
+    pub prize_nft_mint: Account<'info, Mint>,
+    /// Raffle manager's token account holding the NFT.
+    #[account(
+        mut,
+        token::mint = prize_nft_mint,
+        token::authority = raffle_manager,
+    )]
+    pub manager_nft_account: Account<'info, TokenAccount>,
+    /// Escrow holding the NFT until it is claimed: the raffle state's
+    /// associated token account for `prize_nft_mint`, created here.
+    #[account(
+        init,
+        payer = raffle_manager,
+        associated_token::mint = prize_nft_mint,
+        associated_token::authority = raffle_state,
+    )]
+    pub prize_nft_escrow: Account<'info, TokenAccount>,
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+    /// Associated token program, to create the escrow.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program, to create the escrow.
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+/// Emitted when the manager deposits a prize NFT.
+pub struct PrizeNftDepositedEvent {
+    /// Raffle state PDA the NFT is the prize of.
+    pub raffle_state: Pubkey,
+    /// Mint of the prize NFT.
+    pub prize_nft_mint: Pubkey,
+}
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{self, Mint, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn deposit_prize_tokens_impl(
+    ctx: Context<DepositPrizeTokens>,
+    amount: u64,
+) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    raffle_state.prize_token_mint = Some(ctx.accounts.prize_token_mint.key());
+    raffle_state.prize_token_amount = amount;
+
+    // Move the tokens from the manager into the raffle's escrow
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.manager_token_account.to_account_info(),
+                to: ctx.accounts.prize_token_escrow.to_account_info(),
+                authority: ctx.accounts.raffle_manager.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    emit!(PrizeTokensDepositedEvent {
+        raffle_state: raffle_state.key(),
+        prize_token_mint: ctx.accounts.prize_token_mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct DepositPrizeTokens<'info> {
+    /// Raffle manager depositing the tokens; must sign. Pays the escrow's rent.
+    #[account(mut)]
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `prize_token_mint` and `prize_token_amount` set to the deposit.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanDeposit,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.prize_token_mint.is_none()
+            @ RaffleError::PrizeTokensAlreadyDeposited,
+        constraint = amount > 0
+            @ RaffleError::PrizeTokenAmountIsZero,
+        // Buyers must know what they are playing for
+        constraint = raffle_state.entrants.is_empty()
+            @ RaffleError::TicketsAlreadySold,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // The tokens are paid in full with the lamport prize in a single
+        // claim, so they can't vest, and they sit next to the pool in the
+        // raffle's only token escrow
+        constraint = raffle_state.payment_mint.is_none()
+            && raffle_state.prize_nft_mint.is_none()
+            && raffle_state.vesting_schedule.is_empty()
+            @ RaffleError::UnsupportedForTokenPrize
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Mint of the tokens given away with the prize.
+    pub prize_token_mint: Account<'info, Mint>,
+    /// Raffle manager's token account holding the tokens.
+    #[account(
+        mut,
+        token::mint = prize_token_mint,
+        token::authority = raffle_manager,
+    )]
+    pub manager_token_account: Account<'info, TokenAccount>,
+    /// Escrow holding the tokens until they are claimed: the raffle state's
+    /// associated token account for `prize_token_mint`, created here.
+    #[account(
+        init,
+        payer = raffle_manager,
+        associated_token::mint = prize_token_mint,
+        associated_token::authority = raffle_state,
+    )]
+    pub prize_token_escrow: Account<'info, TokenAccount>,
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+    /// Associated token program, to create the escrow.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program, to create the escrow.
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+/// Emitted when the manager deposits prize tokens.
+pub struct PrizeTokensDepositedEvent {
+    /// Raffle state PDA the tokens are part of the prize of.
+    pub raffle_state: Pubkey,
+    /// Mint of the prize tokens.
+    pub prize_token_mint: Pubkey,
+    /// Base units of the mint deposited.
+    pub amount: u64,
+}
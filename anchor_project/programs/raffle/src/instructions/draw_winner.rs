@@ -1,4 +1,4 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
 use ephemeral_vrf_sdk::{
     anchor::vrf,
     instructions::{create_request_randomness_ix, RequestRandomnessParams},
@@ -7,7 +7,8 @@ use ephemeral_vrf_sdk::{
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    escrow::pay_from_pool,
+    state::{RaffleState, RandomnessSource, RAFFLE_SEED},
 };
 
 // The DISCRIMINATOR is generated by the top-level #[program] macro
@@ -20,27 +21,51 @@ pub(crate) fn draw_winner_impl(ctx: Context<DrawWinner>) -> Result<()> {
     let oracle_queue = &ctx.accounts.oracle_queue;
     let raffle_state = &mut ctx.accounts.raffle_state;
 
+    // Only the first call earns the bounty; a repeated call while the VRF
+    // callback is pending just re-requests randomness
+    if !raffle_state.draw_winner_started && raffle_state.keeper_bounty > 0 {
+        pay_from_pool(
+            raffle_state,
+            ctx.bumps.raffle_state,
+            None,
+            &oracle_payer.to_account_info(),
+            raffle_state.keeper_bounty,
+        )?;
+    }
     raffle_state.draw_winner_started = true;
 
-    let ix = create_request_randomness_ix(RequestRandomnessParams {
-        payer: oracle_payer.key(),
-        oracle_queue: oracle_queue.key(),
+    let ix = request_draw_randomness_ix(
+        &oracle_payer.key(),
+        &oracle_queue.key(),
+        &raffle_state.key(),
+    );
+    ctx.accounts
+        .invoke_signed_vrf(&oracle_payer.to_account_info(), &ix)?;
+
+    Ok(())
+}
+
+/// Builds the VRF request whose randomness is delivered to
+/// `draw_winner_callback` for `raffle_state`.
+pub(crate) fn request_draw_randomness_ix(
+    oracle_payer: &Pubkey,
+    oracle_queue: &Pubkey,
+    raffle_state: &Pubkey,
+) -> Instruction {
+    create_request_randomness_ix(RequestRandomnessParams {
+        payer: *oracle_payer,
+        oracle_queue: *oracle_queue,
         callback_program_id: crate::ID,
         callback_discriminator: DRAW_WINNER_CALLBACK_DISCRIMINATOR.to_vec(),
-        caller_seed: *raffle_state.key().as_array(),
+        caller_seed: *raffle_state.as_array(),
         // Account required by the callback
         accounts_metas: Some(vec![SerializableAccountMeta {
-            pubkey: raffle_state.key(),
+            pubkey: *raffle_state,
             is_signer: false,
             is_writable: true,
         }]),
         ..Default::default()
-    });
-
-    ctx.accounts
-        .invoke_signed_vrf(&oracle_payer.to_account_info(), &ix)?;
-
-    Ok(())
+    })
 }
 
 #[vrf]
@@ -49,28 +74,38 @@ pub struct DrawWinner<'info> {
     /// Payer for the VRF request and any CPI fees; must sign.
     #[account(mut)]
     pub oracle_payer: Signer<'info>,
-    /// Raffle state PDA derived from [RAFFLE_SEED, raffle_manager, ticket_price, max_tickets, end_time].
+    /// Raffle state PDA derived from [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
     /// This instruction sets `draw_winner_started`; its address is used as the VRF caller seed.
     #[account(
         mut,
         seeds = [
             RAFFLE_SEED.as_bytes(),
-            raffle_state.raffle_manager.key().as_ref(),
+            raffle_state.creator.as_ref(),
             raffle_state.ticket_price.to_le_bytes().as_ref(),
             raffle_state.max_tickets.to_le_bytes().as_ref(),
-            raffle_state.end_time.to_le_bytes().as_ref()
+            raffle_state.original_end_time.to_le_bytes().as_ref()
         ],
         bump,
+        constraint = raffle_state.randomness_source == RandomnessSource::Vrf
+            @ RaffleError::WrongRandomnessSource,
         // Ensure winner hasn't been drawn yet
         constraint = raffle_state.winner_index.is_none()
             @ RaffleError::WinnerAlreadyDrawn,
+        // An expired raffle turned into a refund pool can no longer be drawn
+        constraint = !raffle_state.refunds_open
+            @ RaffleError::RefundsOpen,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
         // Require at least one entrant (raffle manager can close if empty)
-        constraint = !raffle_state.entrants.is_empty()
+        constraint = raffle_state.tickets_sold > 0
             @ RaffleError::NoEntrants,
         // Check if raffle has ended (either max tickets sold or time elapsed)
-        constraint = raffle_state.entrants.len() >= raffle_state.max_tickets as usize
-            || clock.unix_timestamp >= raffle_state.end_time
-            @ RaffleError::RaffleNotOver
+        // and any draw delay has passed
+        constraint = raffle_state.is_draw_open(clock.unix_timestamp)
+            @ RaffleError::RaffleNotOver,
+        // Too few tickets sold; entrants are refunded instead
+        constraint = raffle_state.tickets_sold >= raffle_state.min_tickets
+            @ RaffleError::MinimumNotReached
     )]
     pub raffle_state: Account<'info, RaffleState>,
     /// CHECK: Oracle queue (must match DEFAULT_QUEUE constant).
@@ -3,7 +3,7 @@ use ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY;
 
 use crate::{
     errors::RaffleError,
-    state::{RaffleState, RAFFLE_SEED},
+    state::{entrants_u32, raffle_randomness, RaffleState, RAFFLE_SEED},
 };
 
 pub(crate) fn draw_winner_callback_impl(
@@ -20,14 +20,47 @@ pub(crate) fn draw_winner_callback_impl(
         RaffleError::CallbackNotInvokedByVRF
     );
 
-    let random_num = ephemeral_vrf_sdk::rnd::random_u64(&randomness) as usize;
-    let winner_index = random_num % raffle_state.entrants.len();
-    raffle_state.winner_index = Some(winner_index as u32);
+    record_winner(raffle_state, randomness)
+}
+
+/// Selects the winner from `randomness`, along with the runner-up and the
+/// consolation winner, starts the claim window and emits `WinnerDrawnEvent`.
+/// After `redraw_winner` the passed-over winner is excluded and the
+/// consolation winner already drawn is kept.
+pub(crate) fn record_winner(
+    raffle_state: &mut Account<'_, RaffleState>,
+    randomness: [u8; 32],
+) -> Result<()> {
+    // Randomness reused across raffles still draws independently in each
+    let draw_seed = raffle_randomness(&randomness, &raffle_state.key());
+    raffle_state.randomness = draw_seed;
+    // The draw instructions normally guarantee tickets were sold, and a
+    // redraw that there are other tickets to pick from
+    let winner_index = raffle_state.draw_winner_entry()?;
+    let winner = raffle_state.entrants[winner_index].buyer;
+    raffle_state.winner_index = Some(entrants_u32(winner_index)?);
+    raffle_state.winner = Some(winner);
+    raffle_state.runner_up_index = raffle_state.select_runner_up(winner_index)?;
+    // A redraw keeps the consolation winner drawn the first time
+    if raffle_state.previous_winner.is_none() {
+        raffle_state.consolation_winner_index =
+            raffle_state.select_consolation_winner(winner_index)?;
+    }
+    // The winner's claim window runs from the draw, not from end_time, so a
+    // late draw doesn't eat into it
+    let now = Clock::get()?.unix_timestamp;
+    raffle_state.claim_deadline = (raffle_state.claim_window_secs > 0)
+        .then(|| now.saturating_add(i64::from(raffle_state.claim_window_secs)));
 
     emit!(WinnerDrawnEvent {
         raffle_state: raffle_state.key(),
-        winner: raffle_state.entrants[winner_index],
-        randomness
+        winner,
+        runner_up: raffle_state.runner_up(),
+        consolation_winner: raffle_state.consolation_winner(),
+        winner_ticket_count: raffle_state.entrants[winner_index].count,
+        total_tickets: raffle_state.tickets_sold,
+        randomness,
+        draw_seed,
     });
 
     Ok(())
@@ -37,16 +70,17 @@ pub(crate) fn draw_winner_callback_impl(
 pub struct DrawWinnerCallback<'info> {
     /// VRF program identity signer (validated in code last to surface other constraint errors first).
     pub vrf_program_identity: Signer<'info>,
-    /// Raffle state PDA [RAFFLE_SEED, raffle_manager, ticket_price, max_tickets, end_time].
-    /// Mutated to record `winner_index` and emit the WinnerDrawnEvent.
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Mutated to record `winner_index`, the runner-up and the claim deadline,
+    /// and emit the WinnerDrawnEvent.
     #[account(
         mut,
         seeds = [
             RAFFLE_SEED.as_bytes(),
-            raffle_state.raffle_manager.key().as_ref(),
+            raffle_state.creator.as_ref(),
             raffle_state.ticket_price.to_le_bytes().as_ref(),
             raffle_state.max_tickets.to_le_bytes().as_ref(),
-            raffle_state.end_time.to_le_bytes().as_ref()
+            raffle_state.original_end_time.to_le_bytes().as_ref()
         ],
         bump,
         constraint = raffle_state.draw_winner_started @ RaffleError::DrawWinnerNotStarted,
@@ -60,13 +94,32 @@ pub struct DrawWinnerCallback<'info> {
 ///
 /// Fields:
 /// - `raffle_state`: the raffle state PDA for which the winner was drawn.
-/// - `winner_index`: index into `entrants` vector for the winning entry.
+/// - `winner_index`: index into `entrants` vector of the winner's entry.
 /// - `winner`: public key of the winning entrant.
+/// - `runner_up`: public key of the runner-up, if the raffle has a claim window.
+/// - `winner_ticket_count` / `total_tickets`: the winner's odds before the draw.
+/// - `randomness` / `draw_seed`: the randomness as delivered, and the seed
+///   derived from it that the winner was selected from.
 pub struct WinnerDrawnEvent {
     /// Raffle state PDA for which the winner was drawn.
     pub raffle_state: Pubkey,
     /// Winner's public key.
     pub winner: Pubkey,
-    /// Randomness from VRF used to draw the winner.
+    /// Runner-up's public key, who may claim once the winner's claim window
+    /// lapses; `None` without a claim window.
+    pub runner_up: Option<Pubkey>,
+    /// Consolation winner's public key, who may claim the second-chance prize
+    /// with `claim_consolation`; `None` without a second-chance prize.
+    pub consolation_winner: Option<Pubkey>,
+    /// Tickets the winner held, out of `total_tickets`.
+    pub winner_ticket_count: u32,
+    /// Tickets sold when the winner was drawn.
+    pub total_tickets: u32,
+    /// Randomness the draw was made from, as delivered: the VRF output, or
+    /// the committed slot hash for `RandomnessSource::SlotHashes` raffles.
     randomness: [u8; 32],
+    /// Seed the winner, runner-up and consolation winner were selected from:
+    /// `sha256(randomness || raffle_state)`, see `raffle_randomness`. Stored
+    /// as the raffle's `randomness`.
+    pub draw_seed: [u8; 32],
 }
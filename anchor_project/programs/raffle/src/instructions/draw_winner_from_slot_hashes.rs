@@ -0,0 +1,97 @@
+use anchor_lang::{prelude::*, solana_program::sysvar};
+
+use crate::{
+    errors::RaffleError,
+    escrow::pay_from_pool,
+    instructions::record_winner,
+    slot_hashes::{slot_hash_from, SlotHashLookup},
+    state::{RaffleState, RandomnessSource, RAFFLE_SEED},
+};
+
+/// Slots between committing to a slot and that slot being produced, so the
+/// committing caller can't know its hash yet.
+pub const COMMIT_DELAY_SLOTS: u64 = 10;
+
+pub(crate) fn draw_winner_from_slot_hashes_impl(
+    ctx: Context<DrawWinnerFromSlotHashes>,
+) -> Result<()> {
+    let caller = &ctx.accounts.caller;
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let slot = ctx.accounts.clock.slot;
+
+    let Some(commit_slot) = raffle_state.commit_slot else {
+        // Only the commit earns the bounty, like the first draw_winner call
+        if raffle_state.keeper_bounty > 0 {
+            pay_from_pool(
+                raffle_state,
+                ctx.bumps.raffle_state,
+                None,
+                &caller.to_account_info(),
+                raffle_state.keeper_bounty,
+            )?;
+        }
+        commit_to_slot(raffle_state, slot);
+        return Ok(());
+    };
+
+    let lookup = slot_hash_from(&ctx.accounts.slot_hashes.try_borrow_data()?, commit_slot);
+    match lookup {
+        // Raffles committed to the same slot still draw independently, as
+        // record_winner binds the hash to the raffle
+        SlotHashLookup::Found(hash) => record_winner(raffle_state, hash),
+        SlotHashLookup::Pending => err!(RaffleError::CommitSlotNotReached),
+        // Nobody drew in time; commit to a new slot
+        SlotHashLookup::Expired => {
+            raffle_state.commit_slot = Some(slot + COMMIT_DELAY_SLOTS);
+            Ok(())
+        }
+    }
+}
+
+/// Starts the draw by committing to the slot `COMMIT_DELAY_SLOTS` after
+/// `slot`, whose hash will pick the winner.
+pub(crate) fn commit_to_slot(raffle_state: &mut RaffleState, slot: u64) {
+    raffle_state.draw_winner_started = true;
+    raffle_state.commit_slot = Some(slot + COMMIT_DELAY_SLOTS);
+}
+
+#[derive(Accounts)]
+pub struct DrawWinnerFromSlotHashes<'info> {
+    /// Whoever calls the draw; must sign. Paid the keeper bounty, if any, for
+    /// the commit.
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Records the committed slot, then the winner.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.randomness_source == RandomnessSource::SlotHashes
+            @ RaffleError::WrongRandomnessSource,
+        constraint = raffle_state.winner_index.is_none()
+            @ RaffleError::WinnerAlreadyDrawn,
+        constraint = !raffle_state.refunds_open
+            @ RaffleError::RefundsOpen,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        constraint = raffle_state.tickets_sold > 0
+            @ RaffleError::NoEntrants,
+        constraint = raffle_state.is_draw_open(clock.unix_timestamp)
+            @ RaffleError::RaffleNotOver,
+        constraint = raffle_state.tickets_sold >= raffle_state.min_tickets
+            @ RaffleError::MinimumNotReached
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// CHECK: SlotHashes sysvar, read raw as it is too large to deserialize.
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    /// Clock sysvar for the end time and the current slot.
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,77 @@
+use anchor_lang::{prelude::*, solana_program::clock::UnixTimestamp};
+
+use crate::{
+    errors::RaffleError,
+    instructions::create_raffle::THIRTY_DAYS_IN_SECS,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn extend_end_time_impl(
+    ctx: Context<ExtendEndTime>,
+    new_end_time: UnixTimestamp,
+) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let old_end_time = raffle_state.end_time;
+
+    // The sales cutoff keeps its distance from the end
+    let extension = new_end_time - old_end_time;
+    raffle_state.end_time = new_end_time;
+    raffle_state.sales_cutoff_time = raffle_state.sales_cutoff_time.saturating_add(extension);
+
+    emit!(EndTimeExtendedEvent {
+        raffle_state: raffle_state.key(),
+        old_end_time,
+        new_end_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(new_end_time: i64)]
+pub struct ExtendEndTime<'info> {
+    /// Raffle manager extending the raffle; must sign.
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `end_time` and `sales_cutoff_time` moved to the new end.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanExtend,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // Once over the raffle may already be drawn or refunding
+        constraint = !raffle_state.is_raffle_over(clock.unix_timestamp)
+            @ RaffleError::RaffleHasEnded,
+        constraint = new_end_time > raffle_state.end_time
+            @ RaffleError::EndTimeNotExtended,
+        // The address stays derived from original_end_time, so the end may
+        // only ever move past it
+        constraint = new_end_time >= raffle_state.original_end_time
+            @ RaffleError::EndTimeNotExtended,
+        // Same cap as at creation, counted from the creation time
+        constraint = new_end_time <= raffle_state.created_at + THIRTY_DAYS_IN_SECS
+            @ RaffleError::MaxRaffleLengthExceeded
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Clock sysvar for timestamp validation.
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[event]
+/// Emitted when the manager extends a raffle.
+pub struct EndTimeExtendedEvent {
+    /// Raffle state PDA that was extended.
+    pub raffle_state: Pubkey,
+    /// End time before the extension.
+    pub old_end_time: i64,
+    /// New end time.
+    pub new_end_time: i64,
+}
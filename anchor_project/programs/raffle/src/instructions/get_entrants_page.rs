@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{entrants_page, EntrantsPage, RaffleState, RAFFLE_SEED};
+
+pub(crate) fn get_entrants_page_impl(
+    ctx: Context<GetEntrantsPage>,
+    start: u32,
+    limit: u32,
+) -> Result<EntrantsPage> {
+    entrants_page(&ctx.accounts.raffle_state.entrants, start, limit)
+}
+
+#[derive(Accounts)]
+pub struct GetEntrantsPage<'info> {
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{RaffleState, RAFFLE_SEED};
+
+pub(crate) fn get_my_odds_impl(ctx: Context<GetMyOdds>) -> Result<u16> {
+    Ok(ctx
+        .accounts
+        .raffle_state
+        .win_probability_bps(ctx.accounts.wallet.key))
+}
+
+#[derive(Accounts)]
+pub struct GetMyOdds<'info> {
+    /// CHECK: Only its key is counted among the entrants; it need not sign.
+    pub wallet: UncheckedAccount<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
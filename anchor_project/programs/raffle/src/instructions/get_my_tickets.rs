@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{RaffleState, WalletTickets, RAFFLE_SEED};
+
+pub(crate) fn get_my_tickets_impl(ctx: Context<GetMyTickets>) -> Result<WalletTickets> {
+    let raffle_state = &ctx.accounts.raffle_state;
+    Ok(WalletTickets {
+        tickets: raffle_state.tickets_of(ctx.accounts.wallet.key),
+        total_tickets: raffle_state.tickets_sold,
+    })
+}
+
+#[derive(Accounts)]
+pub struct GetMyTickets<'info> {
+    /// CHECK: Only its key is counted among the entrants; it need not sign.
+    pub wallet: UncheckedAccount<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
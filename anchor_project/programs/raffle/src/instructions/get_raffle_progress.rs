@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{RaffleProgress, RaffleState, RAFFLE_SEED};
+
+pub(crate) fn get_raffle_progress_impl(ctx: Context<GetRaffleProgress>) -> Result<RaffleProgress> {
+    Ok(ctx
+        .accounts
+        .raffle_state
+        .progress(ctx.accounts.clock.unix_timestamp))
+}
+
+#[derive(Accounts)]
+pub struct GetRaffleProgress<'info> {
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Clock sysvar, to compute the time left.
+    pub clock: Sysvar<'info, Clock>,
+}
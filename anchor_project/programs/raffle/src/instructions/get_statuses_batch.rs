@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{RaffleState, RaffleStatus};
+
+pub(crate) fn get_statuses_batch_impl<'info>(
+    ctx: Context<'_, '_, '_, 'info, GetStatusesBatch<'info>>,
+) -> Result<Vec<u8>> {
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    ctx.remaining_accounts
+        .iter()
+        .map(|account| {
+            // close_raffle hands a closed raffle back to the system program with no data
+            if account.data_is_empty() {
+                return Ok(RaffleStatus::Closed as u8);
+            }
+            require_keys_eq!(
+                *account.owner,
+                crate::ID,
+                ErrorCode::AccountOwnedByWrongProgram
+            );
+            let raffle_state = RaffleState::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+            Ok(raffle_state.status(now) as u8)
+        })
+        .collect()
+}
+
+#[derive(Accounts)]
+pub struct GetStatusesBatch<'info> {
+    /// Clock sysvar, to tell open raffles from ended ones.
+    pub clock: Sysvar<'info, Clock>,
+}
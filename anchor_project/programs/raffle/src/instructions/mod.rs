@@ -1,17 +1,92 @@
+pub mod create_manager_index;
+pub use create_manager_index::*;
+
 pub mod create_raffle;
 pub use create_raffle::*;
 
 pub mod buy_tickets;
 pub use buy_tickets::*;
 
+pub mod withdraw_operator_funds;
+pub use withdraw_operator_funds::*;
+
+pub mod withdraw_revenue;
+pub use withdraw_revenue::*;
+
+pub mod cancel_raffle;
+pub use cancel_raffle::*;
+
+pub mod extend_end_time;
+pub use extend_end_time::*;
+
+pub mod pause_raffle;
+pub use pause_raffle::*;
+
+pub mod unpause_raffle;
+pub use unpause_raffle::*;
+
+pub mod deposit_prize_nft;
+pub use deposit_prize_nft::*;
+
+pub mod deposit_prize_tokens;
+pub use deposit_prize_tokens::*;
+
+pub mod transfer_manager;
+pub use transfer_manager::*;
+
+pub mod accept_manager;
+pub use accept_manager::*;
+
 pub mod draw_winner;
 pub use draw_winner::*;
 
 pub mod draw_winner_callback;
 pub use draw_winner_callback::*;
 
+pub mod draw_winner_from_slot_hashes;
+pub use draw_winner_from_slot_hashes::*;
+
+pub mod redraw_winner;
+pub use redraw_winner::*;
+
 pub mod claim_prize;
 pub use claim_prize::*;
 
+pub mod claim_prize_to;
+pub use claim_prize_to::*;
+
+pub mod claim_consolation;
+pub use claim_consolation::*;
+
+pub mod open_refunds_after_expiry;
+pub use open_refunds_after_expiry::*;
+
+pub mod claim_refund;
+pub use claim_refund::*;
+
 pub mod close_raffle;
 pub use close_raffle::*;
+
+pub mod snapshot_distribution;
+pub use snapshot_distribution::*;
+
+pub mod repair_winner_index;
+pub use repair_winner_index::*;
+
+pub mod get_statuses_batch;
+pub use get_statuses_batch::*;
+
+pub mod get_raffle_progress;
+pub use get_raffle_progress::*;
+
+pub mod check_claim_eligibility;
+pub use check_claim_eligibility::*;
+
+pub mod get_my_tickets;
+pub use get_my_tickets::*;
+
+pub mod get_entrants_page;
+pub use get_entrants_page::*;
+
+pub mod get_my_odds;
+pub use get_my_odds::*;
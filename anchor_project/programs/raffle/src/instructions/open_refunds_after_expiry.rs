@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+/// How long after the draw opens anyone may turn an undrawn raffle into a
+/// refund pool. A VRF callback normally lands within seconds of
+/// `draw_winner`, but a day leaves keepers time to ride out an oracle or RPC
+/// outage before entrants lose their chance at the prize.
+pub const REFUND_GRACE_PERIOD_SECS: i64 = 24 * 60 * 60;
+
+pub(crate) fn open_refunds_after_expiry_impl(ctx: Context<OpenRefundsAfterExpiry>) -> Result<()> {
+    ctx.accounts.raffle_state.refunds_open = true;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenRefundsAfterExpiry<'info> {
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `refunds_open` flipped to true.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = !raffle_state.refunds_open
+            @ RaffleError::RefundsOpen,
+        // Once a draw is started its keeper bounty may be paid out, and the
+        // pending VRF request can still select a winner
+        constraint = !raffle_state.draw_winner_started
+            @ RaffleError::DrawAlreadyStarted,
+        constraint = clock.unix_timestamp
+            >= raffle_state.draw_opens_at().saturating_add(REFUND_GRACE_PERIOD_SECS)
+            @ RaffleError::RefundGracePeriodNotOver
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Clock sysvar for timestamp validation.
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn pause_raffle_impl(ctx: Context<PauseRaffle>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    raffle_state.paused = true;
+
+    emit!(RafflePausedEvent {
+        raffle_state: raffle_state.key(),
+        raffle_manager: raffle_state.raffle_manager,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PauseRaffle<'info> {
+    /// Raffle manager pausing ticket sales; must sign.
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `paused` flipped to true.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanPause,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = !raffle_state.paused
+            @ RaffleError::RafflePaused,
+        constraint = !raffle_state.cancelled
+            @ RaffleError::RaffleCancelled,
+        // Only ticket sales are paused, and they are over already
+        constraint = !raffle_state.is_raffle_over(clock.unix_timestamp)
+            @ RaffleError::RaffleHasEnded
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Clock sysvar for timestamp validation.
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[event]
+/// Emitted when the manager pauses a raffle's ticket sales.
+pub struct RafflePausedEvent {
+    /// Raffle state PDA that was paused.
+    pub raffle_state: Pubkey,
+    /// Raffle manager who paused it.
+    pub raffle_manager: Pubkey,
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use ephemeral_vrf_sdk::anchor::vrf;
+
+use crate::{
+    errors::RaffleError,
+    instructions::request_draw_randomness_ix,
+    state::{RaffleState, RandomnessSource, RAFFLE_SEED},
+};
+
+pub(crate) fn redraw_winner_impl(ctx: Context<RedrawWinner>) -> Result<()> {
+    let oracle_payer = &ctx.accounts.oracle_payer;
+    let oracle_queue = &ctx.accounts.oracle_queue;
+    let raffle_state = &mut ctx.accounts.raffle_state;
+
+    // Clearing the winner lets draw_winner_callback select a new one, which
+    // excludes the winner passed over here
+    raffle_state.previous_winner = raffle_state.winner.take();
+    raffle_state.winner_index = None;
+    raffle_state.runner_up_index = None;
+    raffle_state.claim_deadline = None;
+
+    emit!(WinnerRedrawRequestedEvent {
+        raffle_state: raffle_state.key(),
+        previous_winner: raffle_state.previous_winner,
+    });
+
+    let ix = request_draw_randomness_ix(
+        &oracle_payer.key(),
+        &oracle_queue.key(),
+        &raffle_state.key(),
+    );
+    ctx.accounts
+        .invoke_signed_vrf(&oracle_payer.to_account_info(), &ix)?;
+
+    Ok(())
+}
+
+#[vrf]
+#[derive(Accounts)]
+pub struct RedrawWinner<'info> {
+    /// Payer for the VRF request and any CPI fees; must sign.
+    #[account(mut)]
+    pub oracle_payer: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// The winner is cleared until `draw_winner_callback` selects a new one;
+    /// its address is used as the VRF caller seed.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.randomness_source == RandomnessSource::Vrf
+            @ RaffleError::WrongRandomnessSource,
+        constraint = raffle_state.winner_index.is_some()
+            @ RaffleError::WinnerNotYetDrawn,
+        constraint = raffle_state.claimed_bps == 0
+            @ RaffleError::PrizeAlreadyClaimed,
+        constraint = raffle_state.is_claim_window_expired(clock.unix_timestamp)
+            @ RaffleError::ClaimWindowNotOver,
+        // The runner-up holds neither the winner's nor the consolation
+        // winner's tickets, so its presence guarantees someone to redraw
+        constraint = raffle_state.runner_up_index.is_some()
+            @ RaffleError::NoOtherEntrants
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// CHECK: Oracle queue (must match DEFAULT_QUEUE constant).
+    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_QUEUE)]
+    pub oracle_queue: AccountInfo<'info>,
+    /// Clock sysvar for the claim window.
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[event]
+/// Emitted when a winner who let the claim window lapse is passed over and
+/// fresh randomness is requested to draw a new one.
+pub struct WinnerRedrawRequestedEvent {
+    /// Raffle state PDA being redrawn.
+    pub raffle_state: Pubkey,
+    /// Winner passed over, excluded from the redraw.
+    pub previous_winner: Option<Pubkey>,
+}
@@ -0,0 +1,75 @@
+use anchor_lang::{prelude::*, solana_program::bpf_loader_upgradeable};
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn repair_winner_index_impl(ctx: Context<RepairWinnerIndex>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+
+    // Guaranteed by the winner_index constraint below
+    let previous_winner_index = raffle_state.winner_index.unwrap();
+    let winner_index = raffle_state.reselect_winner()?;
+
+    emit!(WinnerRepairedEvent {
+        raffle_state: raffle_state.key(),
+        previous_winner_index,
+        winner_index,
+        winner: raffle_state.winner,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RepairWinnerIndex<'info> {
+    /// Program upgrade authority; must sign.
+    pub upgrade_authority: Signer<'info>,
+
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Only repairable while the stored `winner_index` is out of range and no
+    /// part of the prize has been paid.
+    #[account(
+        mut,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state
+            .winner_index
+            .is_some_and(|index| index as usize >= raffle_state.entrants.len())
+            @ RaffleError::WinnerIndexNotCorrupted,
+        // No vested tranche may have been paid to the old winner either
+        constraint = raffle_state.claimed_bps == 0
+            @ RaffleError::PrizeAlreadyClaimed
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+
+    /// Program data account (upgrade authority source).
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::id(),
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ RaffleError::OnlyProgramOwnerCanRepair
+    )]
+    pub program_data: Account<'info, ProgramData>,
+}
+
+#[event]
+/// Emitted when the upgrade authority repairs an out-of-range winner index.
+pub struct WinnerRepairedEvent {
+    /// Raffle state PDA that was repaired.
+    pub raffle_state: Pubkey,
+    /// The out-of-range index that was stored before the repair.
+    pub previous_winner_index: u32,
+    /// Newly selected index, or `None` if there were no entrants left.
+    pub winner_index: Option<u32>,
+    /// Public key of the newly selected winner, if any.
+    pub winner: Option<Pubkey>,
+}
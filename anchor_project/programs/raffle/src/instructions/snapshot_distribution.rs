@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{
+        entrants_u32, Distribution, DistributionBucket, RaffleState, DISTRIBUTION_SEED,
+        DISTRIBUTION_TOP_N, RAFFLE_SEED,
+    },
+};
+
+pub(crate) fn snapshot_distribution_impl(ctx: Context<SnapshotDistribution>) -> Result<()> {
+    let raffle_state = &ctx.accounts.raffle_state;
+    let distribution = &mut ctx.accounts.distribution;
+
+    let mut top_holders = raffle_state.ranked_ticket_counts();
+    let others = top_holders.split_off(top_holders.len().min(DISTRIBUTION_TOP_N));
+
+    distribution.raffle_state = raffle_state.key();
+    distribution.total_tickets = raffle_state.tickets_sold;
+    distribution.top_holders = top_holders
+        .into_iter()
+        .map(|(holder, tickets)| DistributionBucket { holder, tickets })
+        .collect();
+    distribution.others_wallets = entrants_u32(others.len())?;
+    distribution.others_tickets = others.iter().map(|&(_, tickets)| tickets).sum();
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SnapshotDistribution<'info> {
+    /// Pays rent for the distribution account; must sign.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Read only; the raffle must be over so the distribution is final.
+    #[account(
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.is_raffle_over(clock.unix_timestamp)
+            @ RaffleError::RaffleNotOver
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Distribution PDA [DISTRIBUTION_SEED, raffle_state]. Created here, so a
+    /// raffle can only be snapshotted once.
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Distribution::SPACE,
+        seeds = [DISTRIBUTION_SEED.as_bytes(), raffle_state.key().as_ref()],
+        bump,
+    )]
+    pub distribution: Account<'info, Distribution>,
+    /// System program for account creation.
+    pub system_program: Program<'info, System>,
+    /// Clock sysvar for timestamp validation.
+    pub clock: Sysvar<'info, Clock>,
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn transfer_manager_impl(
+    ctx: Context<TransferManager>,
+    new_manager: Pubkey,
+) -> Result<()> {
+    ctx.accounts.raffle_state.pending_manager = Some(new_manager);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TransferManager<'info> {
+    /// Current raffle manager handing the raffle over; must sign.
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `pending_manager` set to the new manager.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanTransfer,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn unpause_raffle_impl(ctx: Context<UnpauseRaffle>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    raffle_state.paused = false;
+
+    emit!(RaffleUnpausedEvent {
+        raffle_state: raffle_state.key(),
+        raffle_manager: raffle_state.raffle_manager,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UnpauseRaffle<'info> {
+    /// Raffle manager resuming ticket sales; must sign.
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// `paused` flipped back to false.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanPause,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.paused
+            @ RaffleError::RaffleNotPaused
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
+
+#[event]
+/// Emitted when the manager resumes a paused raffle's ticket sales.
+pub struct RaffleUnpausedEvent {
+    /// Raffle state PDA that was unpaused.
+    pub raffle_state: Pubkey,
+    /// Raffle manager who unpaused it.
+    pub raffle_manager: Pubkey,
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::{
+    errors::RaffleError,
+    escrow::{escrow_address, pay_from_pool, TokenEscrow},
+    math::bps_of,
+    state::{RaffleState, RAFFLE_SEED},
+};
+
+pub(crate) fn withdraw_operator_funds_impl(
+    ctx: Context<WithdrawOperatorFunds>,
+    amount: u64,
+) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+
+    let collected = raffle_state.total_collected;
+    // The cap applies to what has been collected so far, and never touches
+    // what a draw needs for the keeper bounty
+    let limit = bps_of(collected, raffle_state.operator_withdrawal_cap_bps)?
+        .min(collected.saturating_sub(raffle_state.keeper_bounty));
+    let withdrawn = raffle_state
+        .operator_withdrawn
+        .checked_add(amount)
+        .ok_or(RaffleError::OperatorWithdrawalCapExceeded)?;
+    require_gte!(limit, withdrawn, RaffleError::OperatorWithdrawalCapExceeded);
+
+    let escrow = TokenEscrow::for_raffle(
+        raffle_state,
+        ctx.accounts.escrow_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+    // Token raffles pay the manager's token account rather than the manager
+    let recipient = match (&escrow, &ctx.accounts.manager_token_account) {
+        (None, _) => ctx.accounts.raffle_manager.to_account_info(),
+        (Some(_), Some(manager_token_account)) => manager_token_account.to_account_info(),
+        (Some(_), None) => return err!(RaffleError::TokenAccountsMissing),
+    };
+
+    pay_from_pool(
+        raffle_state,
+        ctx.bumps.raffle_state,
+        escrow.as_ref(),
+        &recipient,
+        amount,
+    )?;
+    raffle_state.operator_withdrawn = withdrawn;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawOperatorFunds<'info> {
+    /// Raffle manager withdrawing the funds; must sign.
+    #[account(mut)]
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited for the withdrawal; `operator_withdrawn` increased by it.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanWithdraw,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        // Once the draw starts the pool belongs to the winner
+        constraint = !raffle_state.draw_winner_started
+            @ RaffleError::DrawAlreadyStarted,
+        constraint = !raffle_state.is_refunding(clock.unix_timestamp)
+            @ RaffleError::RefundsOpen
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+    /// Manager's token account receiving the withdrawal; token raffles only.
+    #[account(mut, token::authority = raffle_manager)]
+    pub manager_token_account: Option<Account<'info, TokenAccount>>,
+    /// The raffle's escrow, paying the withdrawal; token raffles only.
+    #[account(mut, address = escrow_address(&raffle_state) @ RaffleError::InvalidEscrow)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    /// Token program; token raffles only.
+    pub token_program: Option<Program<'info, Token>>,
+    /// Clock sysvar, to tell whether the raffle missed its minimum.
+    pub clock: Sysvar<'info, Clock>,
+}
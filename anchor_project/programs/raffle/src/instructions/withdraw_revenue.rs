@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::RaffleError,
+    escrow::pay_from_pool,
+    state::{RaffleState, RevenueMode, RAFFLE_SEED},
+};
+
+pub(crate) fn withdraw_revenue_impl(ctx: Context<WithdrawRevenue>) -> Result<()> {
+    let raffle_state = &mut ctx.accounts.raffle_state;
+    let raffle_manager = &ctx.accounts.raffle_manager;
+
+    // Raffles with a fixed prize are SOL only, so there is no escrow
+    let amount = raffle_state.ticket_revenue()?;
+    pay_from_pool(
+        raffle_state,
+        ctx.bumps.raffle_state,
+        None,
+        &raffle_manager.to_account_info(),
+        amount,
+    )?;
+    raffle_state.revenue_withdrawn = true;
+
+    emit!(RevenueWithdrawnEvent {
+        raffle_state: raffle_state.key(),
+        raffle_manager: raffle_manager.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawRevenue<'info> {
+    /// Raffle manager withdrawing the revenue; must sign.
+    #[account(mut)]
+    pub raffle_manager: Signer<'info>,
+    /// Raffle state PDA [RAFFLE_SEED, creator, ticket_price, max_tickets, original_end_time].
+    /// Debited for the revenue; `revenue_withdrawn` flipped to true.
+    #[account(
+        mut,
+        has_one = raffle_manager @ RaffleError::OnlyRaffleManagerCanWithdraw,
+        seeds = [
+            RAFFLE_SEED.as_bytes(),
+            raffle_state.creator.as_ref(),
+            raffle_state.ticket_price.to_le_bytes().as_ref(),
+            raffle_state.max_tickets.to_le_bytes().as_ref(),
+            raffle_state.original_end_time.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = raffle_state.revenue_mode == RevenueMode::ManagerKeepsRevenue
+            @ RaffleError::RevenueNotWithdrawable,
+        // Until the draw, entrants may still need the revenue for refunds
+        constraint = raffle_state.winner_index.is_some()
+            @ RaffleError::WinnerNotYetDrawn,
+        constraint = !raffle_state.revenue_withdrawn
+            @ RaffleError::RevenueAlreadyWithdrawn
+    )]
+    pub raffle_state: Account<'info, RaffleState>,
+}
+
+#[event]
+/// Emitted when the manager withdraws a raffle's ticket revenue.
+pub struct RevenueWithdrawnEvent {
+    /// Raffle state PDA the revenue was withdrawn from.
+    pub raffle_state: Pubkey,
+    /// Manager who was paid.
+    pub raffle_manager: Pubkey,
+    /// Lamports paid.
+    pub amount: u64,
+}
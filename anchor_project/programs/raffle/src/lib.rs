@@ -1,18 +1,49 @@
 //! Raffle program
 //!
 //! Implements a raffle with the following flow:
+//! - create_manager_index: Creates the account listing a manager's raffles.
 //! - create_raffle: Initialize a raffle state PDA with pricing, capacity, and end time.
-//! - buy_tickets: Users buy one or more tickets; entrants are appended.
+//! - buy_tickets: Users buy one or more tickets, in SOL or an SPL token; each buyer's entry counts its tickets.
+//! - withdraw_operator_funds: Manager withdraws a capped share of ticket payments before the draw.
+//! - withdraw_revenue: Manager of a fixed-prize raffle takes the ticket revenue after the draw.
+//! - cancel_raffle: Manager cancels an undrawn raffle, opening refunds.
+//! - extend_end_time: Manager pushes back the end time of a raffle still on sale.
+//! - pause_raffle / unpause_raffle: Manager freezes and resumes ticket sales.
+//! - deposit_prize_nft: Manager escrows an NFT as the prize before tickets are sold.
+//! - deposit_prize_tokens: Manager escrows SPL tokens paid to the winner with the lamport prize.
+//! - transfer_manager / accept_manager: Two-step hand-over of the raffle to a new manager.
 //! - draw_winner: Starts a VRF request to select a winner once the raffle is over.
 //! - draw_winner_callback: VRF callback that finalizes winner selection and emits `WinnerDrawnEvent`.
-//! - claim_prize: Winner claims the accumulated prize from the raffle account.
+//! - draw_winner_from_slot_hashes: Draws the winner from a committed slot hash instead of the VRF.
+//! - redraw_winner: Requests fresh VRF randomness to replace a winner whose claim window lapsed.
+//! - claim_prize: Winner claims the accumulated prize from the raffle account and emits `PrizeClaimedEvent`.
+//! - claim_prize_to: Winner redirects the prize to another account.
+//! - claim_consolation: Consolation winner claims the second-chance prize.
+//! - open_refunds_after_expiry: Turns an expired, undrawn raffle into a refund pool.
+//! - claim_refund: Entrant reclaims their ticket payments from a refund pool.
 //! - close_raffle: Raffle manager reclaims rent once eligible.
+//! - repair_winner_index: Upgrade authority re-selects an out-of-range winner.
+//! - snapshot_distribution: Caches per-wallet ticket counts once the raffle is over.
+//! - get_statuses_batch: Read-only; returns the lifecycle status of many raffles at once.
+//! - get_raffle_progress: Read-only; returns tickets and time left for one raffle.
+//! - check_claim_eligibility: Read-only; returns whether a wallet can claim the prize.
+//! - get_my_tickets: Read-only; returns how many tickets a wallet holds.
+//! - get_entrants_page: Read-only; returns a page of the entrant list.
+//! - get_my_odds: Read-only; returns a wallet's chance of winning in basis points.
 
 #![allow(unexpected_cfgs)]
 use anchor_lang::prelude::*;
 pub use instructions::*;
+use state::{
+    AcceptedMint, ClaimEligibility, EntrantsPage, PriceCurve, RaffleProgress, RandomnessSource,
+    RevenueMode, VestingTranche, WalletTickets,
+};
 pub mod errors;
+pub mod escrow;
 pub mod instructions;
+pub mod math;
+pub mod merkle;
+pub mod slot_hashes;
 pub mod state;
 
 declare_id!("Rafs56vPtgBLfMQoafTVmf4QB11gqqkysfJx949d99p");
@@ -21,48 +52,576 @@ declare_id!("Rafs56vPtgBLfMQoafTVmf4QB11gqqkysfJx949d99p");
 pub mod raffle {
     use super::*;
 
+    /// Creates the manager index PDA listing the signer's raffles, so clients
+    /// can find them without knowing each raffle's seeds. Every manager
+    /// creates one before its first raffle. It starts empty; `create_raffle`
+    /// lists a raffle in it when asked to, and `close_raffle` removes it
+    /// again.
+    ///
+    /// Args:
+    /// - `create_cooldown` (bool): opts in to `CREATE_COOLDOWN_SECS` between
+    ///   two of the manager's raffles, listed or not, so the index can't be
+    ///   spammed. It can't be turned off again.
+    ///
+    /// Accounts: see [`CreateManagerIndex`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - The system program's "account already in use" error if the signer
+    ///   already has a manager index.
+    pub fn create_manager_index(
+        ctx: Context<CreateManagerIndex>,
+        create_cooldown: bool,
+    ) -> Result<()> {
+        create_manager_index_impl(ctx, create_cooldown)
+    }
+
     /// Creates and initializes a new raffle state account (PDA) with the
     /// provided parameters.
     ///
+    /// Passing the optional `payment_mint` account makes it a token raffle:
+    /// tickets are paid in that SPL token into an escrow (the raffle state's
+    /// associated token account, created here) and the prize and refunds are
+    /// paid out of it. Prices and amounts are then in the mint's base units.
+    /// Token raffles don't support the loyalty bonus, the keeper bounty or the
+    /// refund bond.
+    ///
+    /// Passing the optional `purchase_log` account makes the raffle record the
+    /// slot and first ticket index of every purchase in that PDA, for features
+    /// that need to know when tickets were bought. The log starts empty and
+    /// each buyer pays the rent for their own entry.
+    ///
+    /// Every raffle is counted in the manager's index, created beforehand with
+    /// `create_manager_index`, and `list_in_manager_index` lists it there too,
+    /// with the manager paying for the room it takes. If the index has the
+    /// creation cooldown on, no raffle can be created until it has passed
+    /// since the last one was.
+    ///
+    /// A free-entry raffle is a giveaway: tickets cost nothing, so the prize is
+    /// usually an NFT deposited with `deposit_prize_nft`. Each wallet may enter
+    /// once, signing for its own ticket.
+    ///
+    /// In `RevenueMode::ManagerKeepsRevenue` the manager posts a fixed prize
+    /// in lamports at creation, which is what the winner is paid, and keeps
+    /// the ticket revenue, taken with `withdraw_revenue` once the winner is
+    /// drawn.
+    ///
     /// Args:
     /// - `ticket_price` (u64): price per ticket in lamports.
     /// - `max_tickets` (u32): maximum number of entrants allowed.
+    /// - `min_tickets` (u32): tickets that must be sold by `end_time` for the
+    ///   raffle to be drawn; otherwise entrants claim refunds. Zero disables it.
     /// - `end_time` (i64): Unix timestamp (seconds) when the raffle ends.
+    /// - `loyalty_bonus_bps` (u16): share of the prize pool, in basis points,
+    ///   split among the top ticket holders at claim time. Zero disables it.
+    /// - `sales_cutoff_slots` (u64): stop selling tickets this many slots
+    ///   before `end_time`, to reduce sniping at the boundary. Slots are
+    ///   converted to a timestamp at the nominal 400ms slot time. Zero disables it.
+    /// - `terms_hash` ([u8; 32]): hash of the off-chain terms document the
+    ///   raffle is bound to; immutable once set. All zeros if there are none.
+    /// - `vesting_schedule` (Vec<VestingTranche>): tranches in which the
+    ///   winner's share of the prize unlocks, at most `MAX_VESTING_TRANCHES`
+    ///   of them, adding up to 10_000 bps. Empty to pay the prize at once.
+    /// - `keeper_bounty` (u64): lamports paid from the pool to whoever first
+    ///   calls `draw_winner`, to reward permissionless draws. At most
+    ///   `ticket_price`. Zero disables it.
+    /// - `operator_withdrawal_cap_bps` (u16): share of the collected ticket
+    ///   payments, in basis points, the manager may withdraw before the draw
+    ///   with `withdraw_operator_funds`. Zero disables it.
+    /// - `claim_window_secs` (u32): seconds the winner has after the draw to
+    ///   start claiming. Once it lapses with nothing claimed, a runner-up drawn
+    ///   from the same randomness may claim instead, or `redraw_winner` can
+    ///   draw a new winner. Zero disables it.
+    /// - `refund_bond` (u64): lamports the manager posts into the raffle
+    ///   account to top up refunds reduced by operator withdrawals. Whatever
+    ///   is left returns to the manager when the raffle is closed. Zero
+    ///   disables it.
+    /// - `max_tickets_per_wallet` (u32): most tickets any one wallet may hold,
+    ///   to keep a single buyer from taking over the raffle. Zero disables it.
+    /// - `allowlist_root` (Option<[u8; 32]>): root of a Merkle tree of the
+    ///   wallets allowed to enter (see the `merkle` module), for a private
+    ///   raffle. `None` lets anyone enter.
+    /// - `max_prize` (u64): ceiling on the largest possible pool, the price
+    ///   of all `max_tickets` tickets, in lamports or base units of the
+    ///   payment mint. Zero disables it.
+    /// - `price_curve` (PriceCurve): `Flat` to sell every ticket at
+    ///   `ticket_price`, or `LinearStep { increment }` to raise the price by
+    ///   `increment` with each ticket sold, starting at `ticket_price`.
+    /// - `name` (String): human-readable name shown by frontends, at most
+    ///   `MAX_NAME_LEN` (64) bytes.
+    /// - `uri` (String): URI of off-chain metadata for frontends, at most
+    ///   `MAX_URI_LEN` (200) bytes. Empty if there is none.
+    /// - `second_chance_prize_bps` (u16): share of the prize pool, in basis
+    ///   points, paid to a consolation winner drawn among the wallets other
+    ///   than the winner's and the runner-up's.
+    ///   Requires a `claim_window_secs`, which the consolation winner must
+    ///   claim within. Zero disables it.
+    /// - `free_entry` (bool): make the raffle a free giveaway. Requires a zero
+    ///   `ticket_price`, a `Flat` price curve and a SOL raffle, and overrides
+    ///   `max_tickets_per_wallet` with 1.
+    /// - `revenue_mode` (RevenueMode): `WinnerTakesPool` to pay the ticket
+    ///   revenue out as the prize, or `ManagerKeepsRevenue` to pay a fixed
+    ///   prize and leave the revenue to the manager.
+    /// - `fixed_prize_lamports` (u64): lamports transferred from the manager
+    ///   into the raffle account as the prize in `ManagerKeepsRevenue` mode.
+    ///   Must be zero in `WinnerTakesPool` mode.
+    /// - `randomness_source` (RandomnessSource): `Vrf` to draw with
+    ///   `draw_winner` and the VRF oracle, or `SlotHashes` to draw with
+    ///   `draw_winner_from_slot_hashes`, which needs no oracle but gives the
+    ///   leader of the committed slot influence over the winner.
+    /// - `close_authority` (Option<Pubkey>): wallet, e.g. a DAO, that may
+    ///   close the raffle alongside the manager and the upgrade authority.
+    ///   `None` if there is none.
+    /// - `draw_delay_secs` (u32): seconds after `end_time` before the raffle
+    ///   can be drawn, even if it sold out earlier. Zero allows drawing as
+    ///   soon as it is over.
+    /// - `manager_cannot_enter` (bool): bar the manager from buying tickets or
+    ///   being gifted any, so it can't buy out its own raffle.
+    /// - `auto_draw` (bool): have the purchase that sells out the raffle start
+    ///   the draw. A slot-hashes raffle without a draw delay then commits to a
+    ///   slot, paying the keeper bounty to the buyer, and either kind emits
+    ///   [`RaffleReadyToDrawEvent`] for keepers to finish the draw.
+    /// - `fee_bps` (u16): share of the prize pool, in basis points, paid to
+    ///   the raffle manager with the first prize claim. Zero takes no fee.
+    /// - `accepted_mints` (Vec<AcceptedMint>): up to `MAX_ACCEPTED_MINTS`
+    ///   other mints a token raffle also sells tickets in, each at its own
+    ///   fixed price. A ticket is the same entry whatever it was paid in; the
+    ///   winner is paid every mint's escrow and each ticket is refunded in
+    ///   the mint it was paid in. Requires a `payment_mint`, a `Flat` price
+    ///   curve, no vesting and no operator withdrawals. Empty to accept only
+    ///   the payment mint.
+    /// - `list_in_manager_index` (bool): lists the raffle in the manager's
+    ///   index, so clients can find it there.
+    ///
+    /// Emits: [`RaffleCreatedEvent`]
     ///
-    /// Accounts: see [`CreateRaffle`] for required accounts and seeds.
+    /// Accounts: see [`CreateRaffle`] for required accounts and seeds. Each
+    /// accepted mint, in order, is passed as remaining accounts: the mint,
+    /// then its escrow, the raffle state's associated token account for it,
+    /// created here with rent paid by `raffle_owner`.
     ///
     /// Errors:
     /// - `RaffleError::RaffleEndTimeInPast`: the provided `end_time` must be in the
     ///   future relative to the cluster clock.
+    /// - `RaffleError::LeadTimeTooShort`: the provided `end_time` must be at
+    ///   least `MIN_LEAD_SECS` (currently 5 seconds) from the current time.
     /// - `RaffleError::MaxRaffleLengthExceeded`: the provided `end_time` cannot be more
     ///   than 30 days from the current time.
     /// - `RaffleError::MaxTicketsIsZero`: `max_tickets` must be at least 1.
+    /// - `RaffleError::MinTicketsExceedsMax`: `min_tickets` exceeds `max_tickets`.
     /// - `RaffleError::RaffleTooLarge`: the computed maximum prize pool
-    ///   (`ticket_price * max_tickets`) overflowed `u64`.
+    ///   (the price of all `max_tickets` tickets) overflowed `u64`, or the
+    ///   raffle account could not grow large enough to hold them all.
+    /// - `RaffleError::PrizeCapExceeded`: the maximum prize pool exceeds
+    ///   `max_prize`.
     /// - `RaffleError::TicketPriceTooLow`: `ticket_price` must be at least
-    ///   `MIN_TICKET_PRICE_LAMPORTS` (currently 100_000 lamports, i.e. 0.0001 SOL).
+    ///   `MIN_TICKET_PRICE_LAMPORTS` (currently 100_000 lamports, i.e. 0.0001 SOL),
+    ///   unless the raffle is free.
+    /// - `RaffleError::InvalidFreeEntry`: a free-entry raffle has a non-zero
+    ///   `ticket_price`, a `LinearStep` price curve or a `payment_mint`.
+    /// - `RaffleError::LoyaltyBonusTooHigh`: `loyalty_bonus_bps` exceeds
+    ///   `MAX_LOYALTY_BONUS_BPS` (currently 5_000, i.e. 50%).
+    /// - `RaffleError::InvalidVestingSchedule`: the schedule has too many
+    ///   tranches, an empty tranche, or does not add up to 10_000 bps.
+    /// - `RaffleError::KeeperBountyTooHigh`: `keeper_bounty` exceeds `ticket_price`.
+    /// - `RaffleError::OperatorWithdrawalCapTooHigh`: `operator_withdrawal_cap_bps`
+    ///   exceeds `MAX_OPERATOR_WITHDRAWAL_BPS` (currently 5_000, i.e. 50%).
+    /// - `RaffleError::SecondChancePrizeTooHigh`: `second_chance_prize_bps`
+    ///   exceeds `MAX_SECOND_CHANCE_PRIZE_BPS` (currently 5_000, i.e. 50%).
+    /// - `RaffleError::SecondChancePrizeNeedsClaimWindow`: the raffle has a
+    ///   second-chance prize but no `claim_window_secs`.
+    /// - `RaffleError::FeeTooHigh`: `fee_bps` exceeds `MAX_FEE_BPS` (currently
+    ///   1_000, i.e. 10%), or together with `loyalty_bonus_bps` and
+    ///   `second_chance_prize_bps` exceeds the whole prize pool.
+    /// - `RaffleError::InsufficientRentBalance`: `raffle_owner` can't afford
+    ///   the rent of the new raffle account and its token escrows plus the
+    ///   `refund_bond` and `fixed_prize_lamports` deposited in it.
+    /// - `ErrorCode::AccountNotInitialized` on `manager_index`: the manager
+    ///   hasn't created its index yet.
+    /// - `RaffleError::CreateCooldownActive`: the manager index has the
+    ///   creation cooldown on and it hasn't passed since the manager's last
+    ///   raffle.
+    /// - `RaffleError::UnsupportedForTokenRaffle`: a token raffle was given a
+    ///   loyalty bonus, keeper bounty, refund bond, second-chance prize, fee
+    ///   or fixed prize.
+    /// - `RaffleError::InvalidFixedPrize`: `fixed_prize_lamports` is zero in
+    ///   `ManagerKeepsRevenue` mode, or non-zero in `WinnerTakesPool` mode.
+    /// - `RaffleError::TokenAccountsMissing`: `payment_mint` and the escrow
+    ///   must be passed together.
+    /// - `RaffleError::MetadataTooLong`: `name` or `uri` is too long.
+    /// - `RaffleError::InvalidAcceptedMints`: too many accepted mints, a
+    ///   repeated one or the payment mint, a zero price, a price whose total
+    ///   for `max_tickets` overflows `u64`, or a remaining account that isn't
+    ///   the accepted mint it stands for.
+    /// - `RaffleError::UnsupportedForAcceptedMints`: accepted mints were given
+    ///   to a SOL raffle, or with a `LinearStep` price curve, a vesting
+    ///   schedule or an operator withdrawal cap.
+    /// - `RaffleError::AcceptedMintAccountsMismatch`: the remaining accounts
+    ///   are not a mint and escrow pair per accepted mint.
+    /// - `RaffleError::InvalidEscrow`: an accepted mint's escrow isn't the
+    ///   raffle state's associated token account for it.
+    #[expect(clippy::too_many_arguments, reason = "one argument per raffle setting")]
     pub fn create_raffle(
         ctx: Context<CreateRaffle>,
         ticket_price: u64,
         max_tickets: u32,
+        min_tickets: u32,
         end_time: i64,
+        loyalty_bonus_bps: u16,
+        sales_cutoff_slots: u64,
+        terms_hash: [u8; 32],
+        vesting_schedule: Vec<VestingTranche>,
+        keeper_bounty: u64,
+        operator_withdrawal_cap_bps: u16,
+        claim_window_secs: u32,
+        refund_bond: u64,
+        max_tickets_per_wallet: u32,
+        allowlist_root: Option<[u8; 32]>,
+        max_prize: u64,
+        price_curve: PriceCurve,
+        name: String,
+        uri: String,
+        second_chance_prize_bps: u16,
+        free_entry: bool,
+        revenue_mode: RevenueMode,
+        fixed_prize_lamports: u64,
+        randomness_source: RandomnessSource,
+        close_authority: Option<Pubkey>,
+        draw_delay_secs: u32,
+        manager_cannot_enter: bool,
+        auto_draw: bool,
+        fee_bps: u16,
+        accepted_mints: Vec<AcceptedMint>,
+        list_in_manager_index: bool,
     ) -> Result<()> {
-        create_raffle_impl(ctx, ticket_price, max_tickets, end_time)
+        create_raffle_impl(
+            ctx,
+            ticket_price,
+            max_tickets,
+            min_tickets,
+            end_time,
+            loyalty_bonus_bps,
+            sales_cutoff_slots,
+            terms_hash,
+            vesting_schedule,
+            keeper_bounty,
+            operator_withdrawal_cap_bps,
+            claim_window_secs,
+            refund_bond,
+            max_tickets_per_wallet,
+            allowlist_root,
+            max_prize,
+            price_curve,
+            name,
+            uri,
+            second_chance_prize_bps,
+            free_entry,
+            revenue_mode,
+            fixed_prize_lamports,
+            randomness_source,
+            close_authority,
+            draw_delay_secs,
+            manager_cannot_enter,
+            auto_draw,
+            fee_bps,
+            accepted_mints,
+            list_in_manager_index,
+        )
     }
 
-    /// Buys one or more tickets for the caller and transfers the ticket price
-    /// in lamports from the buyer to the raffle account.
+    /// Buys one or more tickets for the caller, or as a gift for another
+    /// wallet, and transfers the ticket price in lamports from the buyer to
+    /// the raffle account, or for token raffles from the buyer's token account
+    /// to the raffle's escrow. Under a `LinearStep` price curve each ticket in
+    /// the batch is priced by how many were sold before it. Entering a
+    /// free-entry raffle transfers nothing.
+    ///
+    /// The raffle account is grown to fit the new tickets, with the buyer
+    /// paying the extra rent. As an account can only grow by 10 KiB per
+    /// instruction, one call buys at most `MAX_TICKETS_PER_PURCHASE` (256)
+    /// tickets; clients should split larger purchases into batches of that
+    /// size.
+    ///
+    /// The purchase that sells out an auto-draw raffle also starts its draw,
+    /// see `auto_draw` in `create_raffle`.
     ///
     /// Args:
-    /// - `number_of_tickets` (u32): how many tickets to purchase in this call.
+    /// - `number_of_tickets` (u32): how many tickets to purchase in this call,
+    ///   at least one and at most `MAX_TICKETS_PER_PURCHASE`.
+    /// - `recipient` (Option<Pubkey>): wallet to enter instead of the buyer,
+    ///   to gift the tickets. The recipient may then win and claim the prize,
+    ///   or claim the refund. `None` enters the buyer.
+    /// - `proof` (Vec<[u8; 32]>): Merkle proof that the entrant is on the
+    ///   raffle's allowlist, sibling hashes from the leaf up. Empty if the
+    ///   raffle has no allowlist.
+    /// - `mint_index` (Option<u8>): index into the raffle's `accepted_mints`
+    ///   to pay in that mint, at its ticket price and into its escrow.
+    ///   `None` pays in lamports or the `payment_mint`.
+    ///
+    /// Emits: [`RaffleReadyToDrawEvent`] when it sells out an auto-draw raffle.
     ///
     /// Accounts: see [`BuyTickets`] for required accounts and seeds.
     ///
     /// Errors:
     /// - `RaffleError::RaffleHasEnded`: attempting to buy after the raffle end time.
+    /// - `RaffleError::SoldOut`: every ticket has already been sold.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::RafflePaused`: the manager paused ticket sales.
     /// - `RaffleError::InsufficientTickets`: the purchase would exceed available tickets.
-    pub fn buy_tickets(ctx: Context<BuyTickets>, number_of_tickets: u32) -> Result<()> {
-        buy_tickets_impl(ctx, number_of_tickets)
+    /// - `RaffleError::NumberOfTicketsIsZero`: `number_of_tickets` is zero.
+    /// - `RaffleError::BatchTooLarge`: more than `MAX_TICKETS_PER_PURCHASE`
+    ///   tickets in one call.
+    /// - `RaffleError::WalletTicketLimitExceeded`: the entrant would hold more
+    ///   than `max_tickets_per_wallet` tickets, or enter a free raffle twice.
+    /// - `RaffleError::FreeEntryNotGiftable`: a free-entry ticket was gifted
+    ///   to a `recipient` other than the buyer.
+    /// - `RaffleError::NotOnAllowlist`: the raffle has an allowlist and
+    ///   `proof` doesn't show the entrant is on it.
+    /// - `RaffleError::ManagerCannotEnter`: the raffle bars its manager, and
+    ///   the manager is the buyer or the `recipient`.
+    /// - `RaffleError::SalesCutoffReached`: the raffle's sales cutoff before
+    ///   `end_time` has been reached.
+    /// - `RaffleError::PurchaseLogMissing`: the raffle records purchases and
+    ///   its `purchase_log` was not passed.
+    /// - `RaffleError::InsufficientFunds`: the buyer can't pay for the
+    ///   tickets and the rent of the raffle account's growth, and still keep
+    ///   its account rent exempt.
+    /// - `RaffleError::PriceOverflow`: the price of the tickets overflows
+    ///   `u64`, which `create_raffle` should already rule out.
+    /// - `RaffleError::InvalidMintIndex`: `mint_index` is past the raffle's
+    ///   accepted mints.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the keeper bounty
+    ///   of a sold-out auto-draw raffle would leave the raffle account below
+    ///   rent exemption.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's token accounts are missing or wrong.
+    pub fn buy_tickets(
+        ctx: Context<BuyTickets>,
+        number_of_tickets: u32,
+        recipient: Option<Pubkey>,
+        proof: Vec<[u8; 32]>,
+        mint_index: Option<u8>,
+    ) -> Result<()> {
+        buy_tickets_impl(ctx, number_of_tickets, recipient, proof, mint_index)
+    }
+
+    /// Lets the raffle manager withdraw part of the collected ticket payments
+    /// before the draw, e.g. to cover operational costs when the prize is
+    /// funded separately. In total at most `operator_withdrawal_cap_bps` of
+    /// what has been collected so far can be withdrawn, always leaving enough
+    /// for the keeper bounty. The withdrawn amount no longer counts towards
+    /// the prize, and refunds are reduced by each entrant's share of it.
+    ///
+    /// Args:
+    /// - `amount` (u64): lamports, or base units of the payment mint, to withdraw.
+    ///
+    /// Accounts: see [`WithdrawOperatorFunds`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanWithdraw`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::DrawAlreadyStarted`: `draw_winner` was already called.
+    /// - `RaffleError::RefundsOpen`: the raffle was turned into a refund pool,
+    ///   or ended without selling `min_tickets`.
+    /// - `RaffleError::OperatorWithdrawalCapExceeded`: the withdrawal would
+    ///   exceed the cap.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's token accounts are missing or wrong.
+    pub fn withdraw_operator_funds(ctx: Context<WithdrawOperatorFunds>, amount: u64) -> Result<()> {
+        withdraw_operator_funds_impl(ctx, amount)
+    }
+
+    /// Pays a `ManagerKeepsRevenue` raffle's ticket revenue to the manager
+    /// once the winner is drawn: the ticket payments less the keeper bounty
+    /// and any operator withdrawals. The fixed prize stays in the raffle
+    /// account for the winner.
+    ///
+    /// Emits: [`RevenueWithdrawnEvent`]
+    ///
+    /// Accounts: see [`WithdrawRevenue`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanWithdraw`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::RevenueNotWithdrawable`: the winner takes the revenue
+    ///   as the prize.
+    /// - `RaffleError::WinnerNotYetDrawn`: no winner has been selected yet.
+    /// - `RaffleError::RevenueAlreadyWithdrawn`: the revenue was already
+    ///   withdrawn.
+    /// - `RaffleError::PrizeOverflow`: the keeper bounty and operator
+    ///   withdrawals exceed the ticket payments collected.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the revenue would
+    ///   leave the raffle account below rent exemption.
+    pub fn withdraw_revenue(ctx: Context<WithdrawRevenue>) -> Result<()> {
+        withdraw_revenue_impl(ctx)
+    }
+
+    /// Lets the raffle manager unwind a raffle, e.g. one created with the
+    /// wrong settings, as long as its draw hasn't started. Ticket sales and
+    /// the draw stop, and entrants reclaim their payments with `claim_refund`.
+    ///
+    /// Emits: [`RaffleCancelledEvent`]
+    ///
+    /// Accounts: see [`CancelRaffle`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanCancel`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::RaffleCancelled`: the raffle is already cancelled.
+    /// - `RaffleError::DrawAlreadyStarted`: `draw_winner` was already called.
+    pub fn cancel_raffle(ctx: Context<CancelRaffle>) -> Result<()> {
+        cancel_raffle_impl(ctx)
+    }
+
+    /// Lets the raffle manager push back the end of a raffle that is still
+    /// selling, e.g. one that is short of `min_tickets`. The sales cutoff
+    /// moves with it. The raffle's address stays derived from the original
+    /// end time, so the end never moves before it. The new end time is
+    /// bounded by the same 30 day cap as at creation, counted from when the
+    /// raffle was created.
+    ///
+    /// Args:
+    /// - `new_end_time` (i64): new Unix timestamp (seconds) when the raffle ends.
+    ///
+    /// Emits: [`EndTimeExtendedEvent`]
+    ///
+    /// Accounts: see [`ExtendEndTime`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanExtend`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::RaffleHasEnded`: the raffle sold out or reached its
+    ///   end time.
+    /// - `RaffleError::EndTimeNotExtended`: `new_end_time` is not after the
+    ///   current end time, or is before the original end time.
+    /// - `RaffleError::MaxRaffleLengthExceeded`: `new_end_time` is more than
+    ///   30 days after the raffle was created.
+    pub fn extend_end_time(ctx: Context<ExtendEndTime>, new_end_time: i64) -> Result<()> {
+        extend_end_time_impl(ctx, new_end_time)
+    }
+
+    /// Lets the raffle manager freeze ticket sales of a raffle that is still
+    /// selling, e.g. while a problem is investigated, without cancelling it.
+    /// Only `buy_tickets` is blocked: the end time doesn't move, and a raffle
+    /// that ends while paused is drawn and claimed as usual.
+    ///
+    /// Emits: [`RafflePausedEvent`]
+    ///
+    /// Accounts: see [`PauseRaffle`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanPause`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::RafflePaused`: the raffle is already paused.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::RaffleHasEnded`: the raffle sold out or reached its
+    ///   end time.
+    pub fn pause_raffle(ctx: Context<PauseRaffle>) -> Result<()> {
+        pause_raffle_impl(ctx)
+    }
+
+    /// Lets the raffle manager resume ticket sales of a paused raffle.
+    ///
+    /// Emits: [`RaffleUnpausedEvent`]
+    ///
+    /// Accounts: see [`UnpauseRaffle`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanPause`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::RaffleNotPaused`: the raffle is not paused.
+    pub fn unpause_raffle(ctx: Context<UnpauseRaffle>) -> Result<()> {
+        unpause_raffle_impl(ctx)
+    }
+
+    /// Makes an NFT the raffle's prize: moves it from the manager into an
+    /// escrow owned by the raffle state (its associated token account for the
+    /// NFT's mint). The winner then claims the NFT with `claim_prize`, while
+    /// the ticket payments go to the manager when the raffle is closed. If the
+    /// raffle ends up refunding instead, the NFT returns to the manager on
+    /// close. Only possible before any ticket is sold, for SOL raffles without
+    /// a loyalty bonus, second-chance prize, vesting schedule or fixed prize.
+    ///
+    /// Emits: [`PrizeNftDepositedEvent`]
+    ///
+    /// Accounts: see [`DepositPrizeNft`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanDeposit`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::PrizeNftAlreadyDeposited`: the raffle already has a
+    ///   prize NFT.
+    /// - `RaffleError::TicketsAlreadySold`: tickets were already sold.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::UnsupportedForNftPrize`: the raffle is a token raffle,
+    ///   or has a loyalty bonus, second-chance prize, vesting schedule, fixed
+    ///   prize or prize tokens.
+    /// - `RaffleError::InvalidPrizeNft`: the mint is not a single token with
+    ///   no decimals.
+    pub fn deposit_prize_nft(ctx: Context<DepositPrizeNft>) -> Result<()> {
+        deposit_prize_nft_impl(ctx)
+    }
+
+    /// Adds SPL tokens to the raffle's prize, for a combined jackpot: moves
+    /// them from the manager into an escrow owned by the raffle state (its
+    /// associated token account for the tokens' mint). `claim_prize` then
+    /// pays the winner the tokens and the lamport prize in one instruction,
+    /// so neither can be claimed without the other. If the raffle ends up
+    /// refunding instead, the tokens return to the manager on close. Only
+    /// possible once, before any ticket is sold, for SOL raffles without a
+    /// prize NFT or vesting schedule.
+    ///
+    /// Args:
+    /// - `amount` (u64): base units of the mint to deposit.
+    ///
+    /// Emits: [`PrizeTokensDepositedEvent`]
+    ///
+    /// Accounts: see [`DepositPrizeTokens`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanDeposit`: the signer is not the
+    ///   raffle manager.
+    /// - `RaffleError::PrizeTokensAlreadyDeposited`: the raffle already has
+    ///   prize tokens.
+    /// - `RaffleError::PrizeTokenAmountIsZero`: `amount` is zero.
+    /// - `RaffleError::TicketsAlreadySold`: tickets were already sold.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::UnsupportedForTokenPrize`: the raffle is a token raffle,
+    ///   or has a prize NFT or vesting schedule.
+    pub fn deposit_prize_tokens(ctx: Context<DepositPrizeTokens>, amount: u64) -> Result<()> {
+        deposit_prize_tokens_impl(ctx, amount)
+    }
+
+    /// First step of handing a raffle to a new manager: the current manager
+    /// names the new one, who then takes over with `accept_manager`. Calling
+    /// it again replaces the pending manager. The raffle's address stays
+    /// derived from its creator.
+    ///
+    /// Args:
+    /// - `new_manager` (Pubkey): wallet to hand the raffle to.
+    ///
+    /// Accounts: see [`TransferManager`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::OnlyRaffleManagerCanTransfer`: the signer is not the
+    ///   raffle manager.
+    pub fn transfer_manager(ctx: Context<TransferManager>, new_manager: Pubkey) -> Result<()> {
+        transfer_manager_impl(ctx, new_manager)
+    }
+
+    /// Second step of handing a raffle to a new manager: the pending manager
+    /// named by `transfer_manager` signs to become the raffle manager. From
+    /// then on they receive operator withdrawals and the rent on close, and
+    /// hold every manager-only instruction.
+    ///
+    /// Emits: [`ManagerTransferredEvent`]
+    ///
+    /// Accounts: see [`AcceptManager`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::NotPendingManager`: the signer is not the pending manager.
+    pub fn accept_manager(ctx: Context<AcceptManager>) -> Result<()> {
+        accept_manager_impl(ctx)
     }
 
     /// Requests verifiable randomness for the raffle and marks the draw process
@@ -70,12 +629,23 @@ pub mod raffle {
     /// seconds) invokes the `draw_winner_callback` callback that does the actual
     /// winner selection.
     ///
+    /// If the raffle has a keeper bounty, the first caller is paid it from the
+    /// pool, as the `oracle_payer`.
+    ///
     /// Accounts: see [`DrawWinner`] for required accounts and seeds.
     ///
     /// Errors:
+    /// - `RaffleError::WrongRandomnessSource`: the raffle draws from slot
+    ///   hashes instead.
     /// - `RaffleError::WinnerAlreadyDrawn`: a winner has already been selected.
-    /// - `RaffleError::RaffleNotOver`: the raffle has not reached its end time yet.
+    /// - `RaffleError::RaffleNotOver`: the raffle has not reached its end time,
+    ///   or its draw delay after it, yet.
     /// - `RaffleError::NoEntrants`: there are no entrants in the raffle.
+    /// - `RaffleError::RefundsOpen`: the raffle was turned into a refund pool.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::MinimumNotReached`: fewer than `min_tickets` were sold.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the keeper bounty
+    ///   would leave the raffle account below rent exemption.
     pub fn draw_winner(ctx: Context<DrawWinner>) -> Result<()> {
         draw_winner_impl(ctx)
     }
@@ -94,6 +664,9 @@ pub mod raffle {
     /// - `RaffleError::DrawWinnerNotStarted`: the draw process was not started
     ///   (i.e., `draw_winner` was not called successfully before the callback).
     /// - `RaffleError::WinnerAlreadyDrawn`: a winner has already been set by a previous callback.
+    /// - `RaffleError::NoEntrants`: there are no entrants to select a winner from.
+    /// - `RaffleError::NoOtherEntrants`: a redraw has no ticket left to
+    ///   select from.
     pub fn draw_winner_callback(
         ctx: Context<DrawWinnerCallback>,
         randomness: [u8; 32],
@@ -101,36 +674,466 @@ pub mod raffle {
         draw_winner_callback_impl(ctx, randomness)
     }
 
+    /// Draws the winner of a `RandomnessSource::SlotHashes` raffle without an
+    /// oracle, in two calls. The first call, once the raffle is over, commits
+    /// to a slot `COMMIT_DELAY_SLOTS` ahead of the current one. Any later call
+    /// reads that slot's hash from the SlotHashes sysvar and selects the
+    /// winner from it, like `draw_winner_callback`. If the committed slot has
+    /// fallen out of the sysvar's window before anyone drew, the call commits
+    /// to a new slot instead.
+    ///
+    /// Security: much weaker than the VRF. The leader producing the committed
+    /// slot sees its hash first and can withhold the block to reroll it, and
+    /// anyone can watch the hash land and front-run the reveal. Only use it
+    /// for raffles whose prize is not worth a validator's while.
+    ///
+    /// If the raffle has a keeper bounty, the caller of the commit is paid it
+    /// from the pool.
+    ///
+    /// Emits: [`WinnerDrawnEvent`] once the winner is drawn.
+    ///
+    /// Accounts: see [`DrawWinnerFromSlotHashes`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::WrongRandomnessSource`: the raffle draws from the VRF instead.
+    /// - `RaffleError::WinnerAlreadyDrawn`: a winner has already been selected.
+    /// - `RaffleError::RaffleNotOver`: the raffle has not reached its end time,
+    ///   or its draw delay after it, yet.
+    /// - `RaffleError::NoEntrants`: there are no entrants in the raffle.
+    /// - `RaffleError::RefundsOpen`: the raffle was turned into a refund pool.
+    /// - `RaffleError::RaffleCancelled`: the manager cancelled the raffle.
+    /// - `RaffleError::MinimumNotReached`: fewer than `min_tickets` were sold.
+    /// - `RaffleError::CommitSlotNotReached`: the committed slot has not been
+    ///   produced yet.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the keeper bounty
+    ///   would leave the raffle account below rent exemption.
+    pub fn draw_winner_from_slot_hashes(ctx: Context<DrawWinnerFromSlotHashes>) -> Result<()> {
+        draw_winner_from_slot_hashes_impl(ctx)
+    }
+
+    /// Passes over a winner who let the claim window lapse without claiming
+    /// anything and requests fresh verifiable randomness to draw a new one.
+    /// Anyone can call it. Like `draw_winner`, the VRF flow then invokes
+    /// `draw_winner_callback`, which selects the new winner from the tickets
+    /// of wallets other than the passed-over winner and the consolation
+    /// winner, draws a new runner-up and starts a new claim window. The
+    /// consolation winner drawn the first time is kept. Once the redraw is
+    /// requested, the previous runner-up can no longer claim.
+    ///
+    /// Emits: [`WinnerRedrawRequestedEvent`]
+    ///
+    /// Accounts: see [`RedrawWinner`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::WrongRandomnessSource`: the raffle draws from slot
+    ///   hashes instead.
+    /// - `RaffleError::WinnerNotYetDrawn`: no winner has been selected yet, or
+    ///   a redraw is already pending.
+    /// - `RaffleError::PrizeAlreadyClaimed`: part of the prize was already
+    ///   paid.
+    /// - `RaffleError::ClaimWindowNotOver`: the raffle has no claim window, or
+    ///   the winner's hasn't lapsed yet.
+    /// - `RaffleError::NoOtherEntrants`: no other wallet holds a ticket to
+    ///   redraw from.
+    pub fn redraw_winner(ctx: Context<RedrawWinner>) -> Result<()> {
+        redraw_winner_impl(ctx)
+    }
+
     /// Transfers the total prize pool to the winner and marks the raffle as
     /// claimed. Can be called by anyone after the winner has been drawn; the
     /// prize is always sent to the winner selected by `draw_winner_callback`
     /// using the VRF's randomness.
     ///
+    /// When the raffle has a loyalty bonus, that share of the pool is split
+    /// among the top `LOYALTY_BONUS_HOLDERS` ticket holders in proportion to
+    /// their ticket counts, and the winner receives the rest. The holders must
+    /// be passed as writable remaining accounts, largest holder first (ties go
+    /// to the earliest buyer). Ranking the holders walks the whole entrants
+    /// vec, so the compute cost grows with the number of wallets entered.
+    ///
+    /// When the raffle has a vesting schedule, each call releases only the
+    /// part of the winner's share whose tranches have unlocked since the last
+    /// claim; the loyalty bonus is paid in full with the first one. `claimed`
+    /// is set once the last tranche is paid.
+    ///
+    /// When the raffle has a second-chance prize and a consolation winner was
+    /// drawn, their share is left in the pool for `claim_consolation`.
+    ///
+    /// When the raffle has a claim window and the winner lets it lapse without
+    /// claiming anything, they can no longer claim. The runner-up may claim
+    /// instead, and from then on is the winner for the remaining tranches, or
+    /// `redraw_winner` can draw a new winner.
+    ///
+    /// Token raffles pay the prize from the escrow into the winner's
+    /// associated token account, creating it at `payer`'s expense if the
+    /// winner doesn't have one yet.
+    ///
+    /// Token raffles with accepted mints also pay the winner each accepted
+    /// mint's escrow in full, into the winner's associated token account for
+    /// that mint, created the same way. Each accepted mint, in order, is
+    /// passed as remaining accounts: the mint, then its escrow and the
+    /// winner's token account, both writable.
+    ///
+    /// Raffles with a prize NFT send the NFT to the winner's associated token
+    /// account for it instead, created the same way, and leave the pool to
+    /// the manager.
+    ///
+    /// Raffles with prize tokens send them to the winner's associated token
+    /// account for them, created the same way, along with the lamport prize.
+    ///
+    /// A raffle with a fee pays `fee_bps` of the prize pool to
+    /// `raffle_manager` with the first claim, and the winner the rest.
+    ///
+    /// A free raffle without a prize NFT has a zero prize, which is marked
+    /// claimed without any transfer.
+    ///
+    /// Emits: [`PrizeClaimedEvent`]
+    ///
     /// Accounts: see [`ClaimPrize`] for required accounts and seeds.
     ///
     /// Errors:
     /// - `RaffleError::WinnerNotYetDrawn`: no winner has been selected yet.
-    /// - `RaffleError::Unauthorized`: the provided winner account does not match the selected winner.
+    /// - `RaffleError::NotWinner`: the provided winner account is neither the
+    ///   selected winner nor the runner-up.
+    /// - `RaffleError::ClaimWindowNotOver`: the runner-up claimed before the
+    ///   winner's claim window lapsed, or after the winner started claiming.
+    /// - `RaffleError::ClaimWindowExpired`: the winner claimed after their
+    ///   claim window lapsed with nothing claimed.
     /// - `RaffleError::PrizeAlreadyClaimed`: the prize was already claimed.
-    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+    /// - `RaffleError::LoyaltyHoldersMismatch`: the remaining accounts are not
+    ///   the top ticket holders in rank order.
+    /// - `RaffleError::NoTrancheUnlocked`: no vesting tranche unlocked since
+    ///   the last claim.
+    /// - `RaffleError::PrizeOverflow`: the prize pool can't be computed
+    ///   without overflowing.
+    /// - `RaffleError::RaffleManagerMissing`: the raffle takes a fee and
+    ///   `raffle_manager` was not passed.
+    /// - `RaffleError::InvalidRaffleManager`: `raffle_manager` is not the
+    ///   raffle's manager.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the prize would leave
+    ///   the raffle account below rent exemption.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's token accounts are missing or wrong, including the
+    ///   accounts needed to create the winner's token account.
+    /// - `RaffleError::InvalidTokenAccount`: `winner_token_account`,
+    ///   `winner_nft_account`, `winner_prize_token_account` or an accepted
+    ///   mint's token account is not the winner's associated token account.
+    /// - `RaffleError::AcceptedMintAccountsMismatch`: the remaining accounts
+    ///   are not a mint, escrow and token account per accepted mint.
+    /// - `RaffleError::InvalidPrizeNft`: `prize_nft_mint` is not the raffle's
+    ///   prize NFT mint.
+    /// - `RaffleError::InvalidPrizeTokenMint`: `prize_token_mint` is not the
+    ///   raffle's prize token mint.
+    /// - `ErrorCode::AccountNotInitialized` on `raffle_state`: the raffle was
+    ///   already settled and closed. `close_raffle` refuses to close a raffle
+    ///   with entrants until its prizes are claimed, so this never loses a prize.
+    pub fn claim_prize<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>) -> Result<()> {
         claim_prize_impl(ctx)
     }
 
+    /// Same as `claim_prize`, except the winner signs and has the prize sent
+    /// to `destination` (e.g. cold storage or a DAO treasury) instead of their
+    /// own account. Loyalty bonus shares still go to the top ticket holders,
+    /// and the fee to the raffle manager.
+    /// For token raffles `destination` must be a token account for the
+    /// payment mint. Each accepted mint's escrow goes to the token account
+    /// passed after it in the remaining accounts, as with `claim_prize`, which
+    /// may belong to anyone but must already exist.
+    ///
+    /// Args:
+    /// - `destination` (Pubkey): account to receive the prize; must match the
+    ///   `recipient` account.
+    ///
+    /// Emits: [`PrizeClaimedEvent`]
+    ///
+    /// Accounts: see [`ClaimPrizeTo`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::WinnerNotYetDrawn`: no winner has been selected yet.
+    /// - `RaffleError::NotWinner`: the signer is neither the selected winner
+    ///   nor the runner-up.
+    /// - `RaffleError::ClaimWindowNotOver`: the runner-up claimed before the
+    ///   winner's claim window lapsed, or after the winner started claiming.
+    /// - `RaffleError::ClaimWindowExpired`: the winner claimed after their
+    ///   claim window lapsed with nothing claimed.
+    /// - `RaffleError::PrizeAlreadyClaimed`: the prize was already claimed.
+    /// - `RaffleError::UnsupportedForNftPrize`: the prize is an NFT, which
+    ///   `claim_prize` sends to the winner's own token account.
+    /// - `RaffleError::UnsupportedForTokenPrize`: the prize includes tokens,
+    ///   which `claim_prize` sends to the winner's own token account.
+    /// - `RaffleError::LoyaltyHoldersMismatch`: the remaining accounts are not
+    ///   the top ticket holders in rank order.
+    /// - `RaffleError::NoTrancheUnlocked`: no vesting tranche unlocked since
+    ///   the last claim.
+    /// - `RaffleError::PrizeOverflow`: the prize pool can't be computed
+    ///   without overflowing.
+    /// - `RaffleError::RaffleManagerMissing`: the raffle takes a fee and
+    ///   `raffle_manager` was not passed.
+    /// - `RaffleError::InvalidRaffleManager`: `raffle_manager` is not the
+    ///   raffle's manager.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the prize would leave
+    ///   the raffle account below rent exemption.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's token accounts are missing or wrong.
+    /// - `RaffleError::AcceptedMintAccountsMismatch`: the remaining accounts
+    ///   are not a mint, escrow and token account per accepted mint.
+    pub fn claim_prize_to<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimPrizeTo<'info>>,
+        destination: Pubkey,
+    ) -> Result<()> {
+        claim_prize_to_impl(ctx, destination)
+    }
+
+    /// Pays the second-chance prize, `second_chance_prize_bps` of the prize
+    /// pool, to the consolation winner drawn along with the winner from
+    /// another wallet's tickets. Can be called by anyone once the winner has
+    /// been drawn, independently of `claim_prize`, until the winner's claim
+    /// window lapses. An unclaimed second-chance prize then stays in the
+    /// raffle account and goes to the manager when the raffle is closed.
+    ///
+    /// Emits: [`ConsolationClaimedEvent`]
+    ///
+    /// Accounts: see [`ClaimConsolation`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::WinnerNotYetDrawn`: no winner has been selected yet.
+    /// - `RaffleError::NotConsolationWinner`: the raffle has no consolation
+    ///   winner, or it isn't the provided account.
+    /// - `RaffleError::ConsolationAlreadyClaimed`: the second-chance prize
+    ///   was already claimed.
+    /// - `RaffleError::ConsolationClaimWindowExpired`: the claim window
+    ///   lapsed before the second-chance prize was claimed.
+    /// - `RaffleError::PrizeOverflow`: the prize pool can't be computed
+    ///   without overflowing.
+    /// - `RaffleError::InsufficientPoolBalance`: paying the prize would leave
+    ///   the raffle account below rent exemption.
+    pub fn claim_consolation(ctx: Context<ClaimConsolation>) -> Result<()> {
+        claim_consolation_impl(ctx)
+    }
+
+    /// Turns a raffle that expired without a draw into a refund pool, so
+    /// entrants' funds are not stuck if no one ever calls `draw_winner` (e.g.
+    /// while the VRF is unavailable). Anyone can call it once
+    /// `REFUND_GRACE_PERIOD_SECS` (24 hours) have passed since the draw
+    /// opened, i.e. `end_time` plus any draw delay. Until then the raffle can
+    /// still be drawn late. Afterwards it can't be drawn and entrants reclaim
+    /// their tickets with `claim_refund`.
+    ///
+    /// Accounts: see [`OpenRefundsAfterExpiry`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::RefundsOpen`: refunds are already open.
+    /// - `RaffleError::DrawAlreadyStarted`: `draw_winner` was already called.
+    /// - `RaffleError::RefundGracePeriodNotOver`: the grace period after
+    ///   `end_time` and any draw delay has not passed yet.
+    pub fn open_refunds_after_expiry(ctx: Context<OpenRefundsAfterExpiry>) -> Result<()> {
+        open_refunds_after_expiry_impl(ctx)
+    }
+
+    /// Refunds the ticket price of every ticket the signing entrant holds in a
+    /// raffle whose refunds are open, which was cancelled, or which ended
+    /// without selling `min_tickets`, and removes its entry from `entrants`. Once every
+    /// entrant is refunded the raffle has no entrants left and the manager can
+    /// close it. If the manager withdrew operator funds, each refund is reduced
+    /// by the entrant's share of them, less whatever the manager's refund bond
+    /// still covers. Under a `LinearStep` price curve every ticket is refunded
+    /// at the average price paid. Token raffles refund into
+    /// `entrant_token_account`.
+    ///
+    /// A raffle with accepted mints refunds each ticket in the mint it was paid
+    /// in, at that mint's price, into the entrant's associated token account
+    /// for the mint, created at the entrant's expense if it doesn't exist.
+    /// Each accepted mint, in order, is passed as remaining accounts: the
+    /// mint, then its escrow and the entrant's token account, both writable.
+    ///
+    /// Accounts: see [`ClaimRefund`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::RefundsNotOpen`: `open_refunds_after_expiry` was not
+    ///   called, the raffle was not cancelled, and it did not miss its minimum.
+    /// - `RaffleError::NoTicketsToRefund`: the signer holds no tickets, or
+    ///   was already refunded.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's token accounts are missing or wrong, including the
+    ///   accounts needed to create an accepted mint's token account.
+    /// - `RaffleError::AcceptedMintAccountsMismatch`: the remaining accounts
+    ///   are not a mint, escrow and token account per accepted mint.
+    /// - `RaffleError::InvalidTokenAccount`: an accepted mint's token account
+    ///   is not the entrant's associated token account.
+    pub fn claim_refund<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimRefund<'info>>,
+    ) -> Result<()> {
+        claim_refund_impl(ctx)
+    }
+
     /// Closes the raffle state account and returns the remaining rent/lamports
-    /// to the raffle manager. Can be called by either the raffle manager or the
-    /// program upgrade authority. Only possible if no tickets were sold or the
-    /// prize has already been claimed.
+    /// to the raffle manager. Can be called by the raffle manager, the program
+    /// upgrade authority or the raffle's `close_authority`. Only possible if no tickets were sold, every
+    /// entrant was refunded, or the prize and any second-chance prize have
+    /// already been claimed.
     ///
-    /// Emits: none
+    /// A token raffle's escrow, the prize NFT or prize token escrow and a
+    /// raffle's purchase log are closed with it, their rent also going to the
+    /// raffle manager. A prize NFT or prize tokens that were never claimed
+    /// are returned to `manager_token_account`. A listed raffle is removed
+    /// from its creator's manager index, the rent freed there also going to
+    /// the raffle manager.
+    ///
+    /// Accepted mints' escrows are closed too, with anything left in them
+    /// returned to the raffle manager's associated token account for the
+    /// mint. Each accepted mint, in order, is passed as remaining accounts:
+    /// the mint, then its escrow and that token account, both writable; the
+    /// token account only has to exist if the escrow isn't empty.
+    ///
+    /// Emits: [`RaffleClosedEvent`]
     ///
     /// Accounts: see [`CloseRaffle`] for required accounts and seeds.
     ///
     /// Errors:
     /// - `RaffleError::OnlyRaffleManagerOrProgramOwnerCanClose`: caller is neither
-    ///   the raffle manager nor the program upgrade authority.
+    ///   the raffle manager, the program upgrade authority nor the close authority.
     /// - `RaffleError::CanNotCloseActiveRaffle`: tickets were sold and the prize
-    ///   has not yet been claimed.
-    pub fn close_raffle(ctx: Context<CloseRaffle>) -> Result<()> {
+    ///   has not yet been claimed, or the second-chance prize is unclaimed and
+    ///   its claim window hasn't lapsed.
+    /// - `RaffleError::TokenAccountsMissing` / `RaffleError::InvalidEscrow`:
+    ///   a token raffle's escrow or a prize escrow is missing or wrong, or it
+    ///   holds leftover tokens or the unclaimed prize and no
+    ///   `manager_token_account` was passed.
+    /// - `RaffleError::PurchaseLogMissing`: the raffle records purchases and
+    ///   its `purchase_log` was not passed.
+    /// - `RaffleError::ManagerIndexMissing`: the raffle is listed in its
+    ///   creator's manager index and `manager_index` was not passed.
+    /// - `RaffleError::AcceptedMintAccountsMismatch`: the remaining accounts
+    ///   are not a mint, escrow and token account per accepted mint.
+    /// - `RaffleError::InvalidTokenAccount`: an accepted mint's token account
+    ///   is not the raffle manager's associated token account.
+    pub fn close_raffle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseRaffle<'info>>,
+    ) -> Result<()> {
         close_raffle_impl(ctx)
     }
+
+    /// Recovery path for a raffle whose stored `winner_index` no longer points
+    /// into `entrants` (e.g. after manual account edits or a bug in an older
+    /// program version). Re-selects the winner from the current entrants using
+    /// the randomness stored by `draw_winner_callback`. If no entrants are left
+    /// the winner is cleared, which makes the raffle closable. Only the program
+    /// upgrade authority can call it.
+    ///
+    /// Emits: [`WinnerRepairedEvent`]
+    ///
+    /// Accounts: see [`RepairWinnerIndex`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::WinnerIndexNotCorrupted`: no winner is drawn, or the
+    ///   stored winner index is valid.
+    /// - `RaffleError::PrizeAlreadyClaimed`: the prize was already claimed.
+    /// - `RaffleError::OnlyProgramOwnerCanRepair`: caller is not the program
+    ///   upgrade authority.
+    pub fn repair_winner_index(ctx: Context<RepairWinnerIndex>) -> Result<()> {
+        repair_winner_index_impl(ctx)
+    }
+
+    /// Records how the raffle's tickets are spread across wallets into a
+    /// separate `Distribution` PDA, so dashboards can read one small account
+    /// instead of tallying the entrants vec. The largest `DISTRIBUTION_TOP_N`
+    /// holders are listed individually and the rest are summed into an
+    /// "others" bucket. Anyone can call it once the raffle is over, and only
+    /// once per raffle. The caller pays the rent for the new account.
+    ///
+    /// Accounts: see [`SnapshotDistribution`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::RaffleNotOver`: tickets are still on sale.
+    pub fn snapshot_distribution(ctx: Context<SnapshotDistribution>) -> Result<()> {
+        snapshot_distribution_impl(ctx)
+    }
+
+    /// Reports the lifecycle status of every raffle passed as a remaining
+    /// account, one `RaffleStatus` byte per raffle in the order given, so list
+    /// views can read many raffles in a single simulated call. A closed raffle
+    /// reports `RaffleStatus::Closed`. Modifies nothing; the statuses are
+    /// returned through the transaction return data.
+    ///
+    /// Accounts: see [`GetStatusesBatch`]; raffle state accounts go in the
+    /// remaining accounts.
+    ///
+    /// Errors:
+    /// - `ErrorCode::AccountOwnedByWrongProgram`: a remaining account is not
+    ///   owned by this program.
+    /// - `ErrorCode::AccountDiscriminatorMismatch`: a remaining account is not
+    ///   a raffle state account.
+    pub fn get_statuses_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetStatusesBatch<'info>>,
+    ) -> Result<Vec<u8>> {
+        get_statuses_batch_impl(ctx)
+    }
+
+    /// Reports how far a raffle's ticket sales have got: tickets sold and
+    /// remaining, seconds left until `end_time` (zero once it has passed),
+    /// whether the raffle is over and whether its winner was drawn. Modifies
+    /// nothing; the result is returned through the transaction return data,
+    /// so clients can read it with a simulated call.
+    ///
+    /// Accounts: see [`GetRaffleProgress`] for required accounts and seeds.
+    pub fn get_raffle_progress(ctx: Context<GetRaffleProgress>) -> Result<RaffleProgress> {
+        get_raffle_progress_impl(ctx)
+    }
+
+    /// Reports whether `claimant` can claim a raffle's prize: whether the
+    /// winner was drawn, whether `claimant` holds the winning ticket, whether
+    /// the prize was already paid in full, and the prize pool. Modifies
+    /// nothing; like `get_raffle_progress`, clients read the result with a
+    /// simulated call before building a claim_prize transaction.
+    ///
+    /// Accounts: see [`CheckClaimEligibility`] for required accounts and seeds.
+    ///
+    /// Errors:
+    /// - `RaffleError::PrizeOverflow`: the keeper bounty and operator
+    ///   withdrawals exceed the ticket payments collected.
+    pub fn check_claim_eligibility(
+        ctx: Context<CheckClaimEligibility>,
+    ) -> Result<ClaimEligibility> {
+        check_claim_eligibility_impl(ctx)
+    }
+
+    /// Reports how many tickets `wallet` holds and how many were sold in
+    /// total, so frontends can show a wallet's entries without fetching and
+    /// scanning the whole entrant list. Modifies nothing; clients read the
+    /// result with a simulated call.
+    ///
+    /// Accounts: see [`GetMyTickets`] for required accounts and seeds.
+    pub fn get_my_tickets(ctx: Context<GetMyTickets>) -> Result<WalletTickets> {
+        get_my_tickets_impl(ctx)
+    }
+
+    /// Returns up to `limit` entrants, each a wallet and its ticket count,
+    /// starting at entry `start`, along with the total number of entrants,
+    /// so frontends can render large raffles page by page. The range is
+    /// clamped to the entrant list and to `MAX_ENTRANTS_PER_PAGE` (28), so a
+    /// `start` past the end gives an empty page. Modifies nothing; clients
+    /// read the result with a simulated call.
+    ///
+    /// Args:
+    /// - `start` (u32): index of the first entrant to return.
+    /// - `limit` (u32): most entrants to return.
+    ///
+    /// Accounts: see [`GetEntrantsPage`] for required accounts and seeds.
+    pub fn get_entrants_page(
+        ctx: Context<GetEntrantsPage>,
+        start: u32,
+        limit: u32,
+    ) -> Result<EntrantsPage> {
+        get_entrants_page_impl(ctx, start, limit)
+    }
+
+    /// Returns the chance, in basis points rounded down, that `wallet` wins
+    /// if the winner were drawn now: its share of the tickets sold, or 0 if
+    /// none were. Modifies nothing; clients read the result with a simulated
+    /// call.
+    ///
+    /// Accounts: see [`GetMyOdds`] for required accounts and seeds.
+    pub fn get_my_odds(ctx: Context<GetMyOdds>) -> Result<u16> {
+        get_my_odds_impl(ctx)
+    }
 }
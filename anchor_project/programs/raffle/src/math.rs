@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+/// Denominator for values expressed in basis points.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// 100% expressed in basis points.
+pub const FULL_BPS: u16 = 10_000;
+
+/// Computes `amount * numerator / denominator`, rounded down, without
+/// overflowing the intermediate product.
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = u128::from(amount) * u128::from(numerator) / u128::from(denominator);
+    u64::try_from(result).map_err(|_| ProgramError::ArithmeticOverflow.into())
+}
+
+/// Returns `bps` basis points of `amount`, rounded down.
+pub fn bps_of(amount: u64, bps: u16) -> Result<u64> {
+    mul_div(amount, u64::from(bps), BPS_DENOMINATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bps_of() {
+        assert_eq!(bps_of(1_000_000, 2_000).unwrap(), 200_000);
+        assert_eq!(bps_of(9_999, 1).unwrap(), 0);
+        assert_eq!(bps_of(u64::MAX, 10_000).unwrap(), u64::MAX);
+        assert!(mul_div(u64::MAX, 2, 1).is_err());
+    }
+}
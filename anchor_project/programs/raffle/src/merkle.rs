@@ -0,0 +1,61 @@
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+
+/// Domain separators keep a leaf from being passed off as an inner node.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hash of the allowlist leaf for `wallet`.
+pub fn leaf_hash(wallet: &Pubkey) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref()]).to_bytes()
+}
+
+/// Hash of an inner node. The children are sorted first, so proofs don't need
+/// to say on which side each sibling sits.
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Whether `proof` shows `wallet` is a leaf of the allowlist tree with root
+/// `root`. The proof lists the sibling hashes from the leaf up to the root.
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], wallet: &Pubkey) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf_hash(wallet), |hash, sibling| node_hash(&hash, sibling));
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let wallets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = wallets.iter().map(leaf_hash).collect();
+        // Three leaves: the odd one out is paired with the root of the other two
+        let pair = node_hash(&leaves[0], &leaves[1]);
+        let root = node_hash(&pair, &leaves[2]);
+
+        assert!(verify(&[leaves[1], leaves[2]], &root, &wallets[0]));
+        assert!(verify(&[leaves[0], leaves[2]], &root, &wallets[1]));
+        assert!(verify(&[pair], &root, &wallets[2]));
+
+        // Wrong wallet, wrong sibling, or a truncated proof
+        let outsider = Pubkey::new_unique();
+        assert!(!verify(&[leaves[1], leaves[2]], &root, &outsider));
+        assert!(!verify(&[leaves[2], leaves[1]], &root, &wallets[0]));
+        assert!(!verify(&[leaves[1]], &root, &wallets[0]));
+
+        // A single wallet allowlist is just its leaf
+        assert!(verify(&[], &leaves[0], &wallets[0]));
+    }
+
+    #[test]
+    fn test_node_hash_is_order_independent() {
+        let a = [1; 32];
+        let b = [2; 32];
+        assert_eq!(node_hash(&a, &b), node_hash(&b, &a));
+        assert_ne!(node_hash(&a, &b), leaf_hash(&Pubkey::new_from_array(a)));
+    }
+}
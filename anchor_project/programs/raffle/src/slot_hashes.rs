@@ -0,0 +1,73 @@
+/// Size of one `(slot, hash)` entry in the SlotHashes sysvar.
+const ENTRY_LEN: usize = 8 + 32;
+
+/// What the SlotHashes sysvar says about a committed slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotHashLookup {
+    /// Hash of the first block at or after the committed slot.
+    Found([u8; 32]),
+    /// No block at or after the committed slot has been produced yet.
+    Pending,
+    /// The committed slot fell out of the sysvar's window of recent slots.
+    Expired,
+}
+
+/// Looks up the hash of the first block produced at or after `slot` in the
+/// raw data of the SlotHashes sysvar, a length-prefixed list of `(slot, hash)`
+/// entries from newest to oldest. Read without deserializing, as the sysvar
+/// is too large for that. A skipped slot resolves to the next block, which
+/// is fixed as soon as that block exists.
+pub fn slot_hash_from(data: &[u8], slot: u64) -> SlotHashLookup {
+    let entries = data.get(8..).unwrap_or_default().chunks_exact(ENTRY_LEN);
+    let mut at_or_after = None;
+    for entry in entries {
+        let (entry_slot, hash) = entry.split_at(8);
+        let entry_slot = u64::from_le_bytes(entry_slot.try_into().unwrap());
+        if entry_slot < slot {
+            // Every older entry is further back still
+            return at_or_after.map_or(SlotHashLookup::Pending, SlotHashLookup::Found);
+        }
+        at_or_after = Some((entry_slot, hash.try_into().unwrap()));
+    }
+    match at_or_after {
+        None => SlotHashLookup::Pending,
+        // The oldest entry remembered may not be the first block at or after
+        // `slot` unless it is `slot` itself
+        Some((entry_slot, hash)) if entry_slot == slot => SlotHashLookup::Found(hash),
+        Some(_) => SlotHashLookup::Expired,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raw sysvar data for `entries`, newest first.
+    fn sysvar_data(entries: &[(u64, [u8; 32])]) -> Vec<u8> {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, hash) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(hash);
+        }
+        data
+    }
+
+    #[test]
+    fn test_slot_hash_from() {
+        // Slot 12 was skipped
+        let data = sysvar_data(&[(14, [4; 32]), (13, [3; 32]), (11, [1; 32]), (10, [0; 32])]);
+
+        assert_eq!(slot_hash_from(&data, 11), SlotHashLookup::Found([1; 32]));
+        assert_eq!(slot_hash_from(&data, 14), SlotHashLookup::Found([4; 32]));
+        // A skipped slot resolves to the next block
+        assert_eq!(slot_hash_from(&data, 12), SlotHashLookup::Found([3; 32]));
+        // The oldest entry is still exact
+        assert_eq!(slot_hash_from(&data, 10), SlotHashLookup::Found([0; 32]));
+
+        assert_eq!(slot_hash_from(&data, 15), SlotHashLookup::Pending);
+        assert_eq!(slot_hash_from(&[], 15), SlotHashLookup::Pending);
+
+        // Blocks between slot 9 and 10 may have fallen out of the window
+        assert_eq!(slot_hash_from(&data, 9), SlotHashLookup::Expired);
+    }
+}
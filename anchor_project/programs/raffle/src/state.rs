@@ -1,44 +1,1162 @@
-use anchor_lang::prelude::*;
+use core::cmp::Reverse;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        clock::UnixTimestamp,
+        hash::{hash, hashv},
+    },
+};
+
+use crate::{
+    errors::RaffleError,
+    math::{bps_of, FULL_BPS},
+    merkle,
+};
 
 pub const RAFFLE_SEED: &str = "RaffleSeed";
+pub const DISTRIBUTION_SEED: &str = "DistributionSeed";
+pub const PURCHASE_LOG_SEED: &str = "PurchaseLogSeed";
+pub const MANAGER_INDEX_SEED: &str = "ManagerIndexSeed";
+/// Maximum number of top ticket holders that share the loyalty bonus.
+pub const LOYALTY_BONUS_HOLDERS: usize = 3;
+/// Number of largest holders listed individually in a `Distribution`.
+pub const DISTRIBUTION_TOP_N: usize = 5;
+/// Maximum number of tranches in a prize vesting schedule.
+pub const MAX_VESTING_TRANCHES: usize = 4;
+/// Most mints besides `payment_mint` a token raffle may accept tickets in.
+pub const MAX_ACCEPTED_MINTS: usize = 3;
+/// Maximum length in bytes of a raffle's name.
+pub const MAX_NAME_LEN: usize = 64;
+/// Maximum length in bytes of a raffle's metadata URI.
+pub const MAX_URI_LEN: usize = 200;
+/// Most entrants returned by one `get_entrants_page` call, so the page fits
+/// in the 1024 bytes of an instruction's return data.
+pub const MAX_ENTRANTS_PER_PAGE: u32 = 28;
+/// Minimum seconds between two raffles created by a manager whose index has
+/// the creation cooldown on. Operators facing more spam can raise it.
+pub const CREATE_COOLDOWN_SECS: i64 = 10;
 
 /// Raffle state account stored as a PDA. Tracks configuration and lifecycle
 /// of a single raffle instance.
 #[account]
 pub struct RaffleState {
-    /// The manager/creator of the raffle. The only party that can close the
-    /// raffle to receive the rent refund.
+    /// The manager of the raffle, initially its creator. The only party that
+    /// can close the raffle to receive the rent refund.
     pub raffle_manager: Pubkey,
-    /// Ticket price in lamports.
+    /// The wallet that created the raffle. Part of the PDA seeds, so it stays
+    /// fixed when the raffle is handed to another manager.
+    pub creator: Pubkey,
+    /// Manager the raffle is being handed to with `transfer_manager`, until
+    /// they take over with `accept_manager`.
+    pub pending_manager: Option<Pubkey>,
+    /// Additional wallet allowed to close the raffle alongside the manager
+    /// and the upgrade authority, e.g. a DAO. `None` when there is none.
+    pub close_authority: Option<Pubkey>,
+    /// Ticket price in lamports, or in base units of `payment_mint`.
     pub ticket_price: u64,
+    /// How the price moves as tickets sell; `ticket_price` is the price of
+    /// the first ticket.
+    pub price_curve: PriceCurve,
+    /// Mint of the SPL token tickets are paid in, or `None` for lamports.
+    /// A token raffle keeps its pool in its escrow (see `escrow_address`).
+    pub payment_mint: Option<Pubkey>,
+    /// Other mints a token raffle sells tickets in, each at its own fixed
+    /// price and into its own escrow (see `accepted_escrow_address`). Every
+    /// ticket is the same entry whatever it was paid in, so refunds and the
+    /// prize are paid from each escrow in proportion to tickets.
+    pub accepted_mints: Vec<AcceptedMint>,
+    /// Mint of the NFT given away as the prize, or `None` when the prize is
+    /// the pool. Set by `deposit_prize_nft`, which moves the NFT into an escrow
+    /// (see `prize_nft_escrow_address`); the pool then goes to the manager.
+    pub prize_nft_mint: Option<Pubkey>,
+    /// Mint of the SPL tokens paid to the winner on top of the lamport prize,
+    /// or `None` without a token prize. Set by `deposit_prize_tokens`, which
+    /// moves them into an escrow (see `prize_token_escrow_address`).
+    pub prize_token_mint: Option<Pubkey>,
+    /// Base units of `prize_token_mint` the winner receives with the prize.
+    pub prize_token_amount: u64,
     /// Maximum number of tickets/entrants allowed.
     pub max_tickets: u32,
+    /// Minimum number of tickets that must be sold for the raffle to be
+    /// drawn. If fewer were sold by `end_time`, entrants are refunded instead.
+    pub min_tickets: u32,
+    /// Maximum number of tickets a single wallet may hold. Zero means no limit.
+    pub max_tickets_per_wallet: u32,
     /// Raffle end time as Unix timestamp (seconds). No new tickets may be
     /// bought after this time; drawing is allowed once this time is reached.
     pub end_time: i64,
-    /// Index of the winner in `entrants` once drawn; `None` until selected.
-    pub winner_index: Option<u32>, // index of the winner in the entrants vec
-    /// Whether `draw_winner` has been invoked and the VRF flow started.
+    /// End time given at creation. Part of the PDA seeds, so it stays fixed
+    /// when `extend_end_time` moves `end_time`.
+    pub original_end_time: i64,
+    /// Unix timestamp (seconds) at which the raffle was created; extensions
+    /// can't take `end_time` past `THIRTY_DAYS_IN_SECS` after it.
+    pub created_at: i64,
+    /// Index of the winner's entry in `entrants` once drawn; `None` until
+    /// selected.
+    pub winner_index: Option<u32>, // index of the winner's entry in the entrants vec
+    /// Winner's public key, resolved from `winner_index` when it is set, so
+    /// claims don't depend on re-indexing `entrants`.
+    pub winner: Option<Pubkey>,
+    /// Winner passed over by the latest `redraw_winner`, excluded from the
+    /// redraw along with the consolation winner; `None` until a redraw.
+    pub previous_winner: Option<Pubkey>,
+    /// Index in `entrants` of a second selection, another wallet than the
+    /// winner, who may claim instead if the winner lets `claim_deadline`
+    /// pass without claiming. `None` without a claim window.
+    pub runner_up_index: Option<u32>,
+    /// Index in `entrants` of the consolation winner, another wallet than the
+    /// winner or the runner-up, drawn along with them when the
+    /// raffle has a second-chance prize. `None` otherwise, or if those two
+    /// hold every ticket. Must claim before `claim_deadline`, like the winner.
+    pub consolation_winner_index: Option<u32>,
+    /// Seconds the winner has after the draw to start claiming before the
+    /// runner-up may claim instead. Zero gives the winner unlimited time.
+    pub claim_window_secs: u32,
+    /// Seconds after `end_time` before the raffle can be drawn, e.g. to give
+    /// entrants time to review it. Zero allows drawing as soon as it is over.
+    pub draw_delay_secs: u32,
+    /// Unix timestamp (seconds) at which the winner's and the consolation
+    /// winner's claim window lapses; set by the draw when there is a claim
+    /// window.
+    pub claim_deadline: Option<i64>,
+    /// Whether `draw_winner` has been invoked and the VRF flow started, or
+    /// `draw_winner_from_slot_hashes` committed to a slot.
     pub draw_winner_started: bool,
+    /// Where the randomness for the draw comes from.
+    pub randomness_source: RandomnessSource,
+    /// Slot whose hash `draw_winner_from_slot_hashes` draws the winner from;
+    /// `None` until it is committed, and always for VRF raffles.
+    pub commit_slot: Option<u64>,
     /// Whether the prize has been claimed by the selected winner.
     pub claimed: bool,
-    /// Entrant public keys, one entry per ticket purchased.
-    pub entrants: Vec<Pubkey>,
+    /// Whether the consolation winner claimed the second-chance prize.
+    pub consolation_claimed: bool,
+    /// Whether the raffle expired undrawn and was turned into a refund pool
+    /// by `open_refunds_after_expiry`.
+    pub refunds_open: bool,
+    /// Whether the manager cancelled the raffle with `cancel_raffle`, which
+    /// stops sales and the draw and lets entrants claim refunds.
+    pub cancelled: bool,
+    /// Whether the manager paused ticket sales with `pause_raffle`, e.g. while
+    /// a problem is investigated. Drawing and claiming carry on regardless.
+    pub paused: bool,
+    /// Whether this is a free giveaway: tickets cost nothing, and each wallet
+    /// enters at most once with its own signature.
+    pub free_entry: bool,
+    /// Whether the manager is barred from the raffle: it may neither buy
+    /// tickets nor be gifted any, so it can't buy it out and draw itself.
+    pub manager_cannot_enter: bool,
+    /// Whether the purchase that sells out the raffle starts its draw: a
+    /// slot-hashes raffle commits to a slot right away, so a keeper can draw
+    /// the winner as soon as it is produced.
+    pub auto_draw: bool,
+    /// Whether every purchase is recorded in the raffle's `PurchaseLog`.
+    pub records_purchases: bool,
+    /// Whether the raffle is listed in its creator's `ManagerRaffleIndex`,
+    /// which it must then be removed from when it is closed.
+    pub listed_in_manager_index: bool,
+    /// Share of the prize pool, in basis points, split among the top
+    /// `LOYALTY_BONUS_HOLDERS` ticket holders at claim time. Zero disables it.
+    pub loyalty_bonus_bps: u16,
+    /// Share of the prize pool, in basis points, paid to the consolation
+    /// winner with `claim_consolation`. Zero disables it.
+    pub second_chance_prize_bps: u16,
+    /// Share of the prize pool, in basis points, paid to the raffle manager
+    /// with the first prize claim. Zero takes no fee.
+    pub fee_bps: u16,
+    /// Running total of the ticket payments received, in lamports or base
+    /// units of `payment_mint`. Reduced as entrants are refunded.
+    pub total_collected: u64,
+    /// Lamports paid from the pool to whoever first calls `draw_winner`, and
+    /// so excluded from the winner's prize. At most one ticket price.
+    pub keeper_bounty: u64,
+    /// Share of the collected ticket payments, in basis points, the manager
+    /// may withdraw for operational costs before the draw. Zero disables it.
+    pub operator_withdrawal_cap_bps: u16,
+    /// Amount withdrawn by the manager under `operator_withdrawal_cap_bps`
+    /// and so no longer in the pool. Reduced as refunded entrants take their
+    /// share of it.
+    pub operator_withdrawn: u64,
+    /// Lamports posted by the manager at creation to make refunds whole:
+    /// refunds reduced by `operator_withdrawn` are topped up from it while
+    /// it lasts. Whatever remains returns to the manager on close.
+    pub refund_bond: u64,
+    /// Whether the winner takes the ticket revenue or a fixed prize.
+    pub revenue_mode: RevenueMode,
+    /// Lamports posted by the manager at creation and paid to the winner in
+    /// `RevenueMode::ManagerKeepsRevenue`; zero otherwise.
+    pub fixed_prize_lamports: u64,
+    /// Whether the manager took the ticket revenue with `withdraw_revenue`.
+    pub revenue_withdrawn: bool,
+    /// Unix timestamp (seconds) from which `buy_tickets` is rejected, derived
+    /// at creation from a cutoff given in slots. Equals `end_time` when there
+    /// is no cutoff.
+    pub sales_cutoff_time: i64,
+    /// Hash of the off-chain terms document, fixed at creation so the terms
+    /// can be verified against it. All zeros when no terms are bound.
+    pub terms_hash: [u8; 32],
+    /// Human-readable name for frontends, at most `MAX_NAME_LEN` bytes.
+    pub name: String,
+    /// URI of off-chain metadata (e.g. an image and description) for
+    /// frontends, at most `MAX_URI_LEN` bytes. Empty when there is none.
+    pub uri: String,
+    /// Root of the Merkle tree of wallets allowed to enter, checked by
+    /// `buy_tickets`. `None` when anyone may enter.
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Randomness the winner was drawn from, bound to this raffle by
+    /// `raffle_randomness`, kept so the winner can be re-derived later. All
+    /// zeros until the winner is drawn.
+    pub randomness: [u8; 32],
+    /// Tranches in which the winner's share of the prize unlocks. Empty when
+    /// the whole prize is claimable at once.
+    pub vesting_schedule: Vec<VestingTranche>,
+    /// Basis points of the winner's share already paid out.
+    pub claimed_bps: u16,
+    /// Tickets sold and not refunded, the sum of the entrants' counts.
+    pub tickets_sold: u32,
+    /// One entry per wallet holding tickets paid in an accepted mint, with
+    /// how many were paid in each, so refunds go back in the mint paid.
+    /// Always empty without accepted mints.
+    pub accepted_mint_tickets: Vec<AcceptedMintTickets>,
+    /// One entry per wallet holding tickets, with how many it holds, in
+    /// order of first purchase.
+    pub entrants: Vec<EntrantEntry>,
 }
 
 impl RaffleState {
-    /// Calculates the raffle account space based on the maximum number of tickets.
+    /// Size of everything but the entrants themselves, i.e. the space of a
+    /// raffle with no tickets. This does not include the 8 bytes added as a
+    /// discriminator by Anchor.
+    pub const BASE_SIZE: usize = 32 + // raffle_manager
+        32 + // creator
+        33 + // pending_manager (Option<Pubkey>)
+        33 + // close_authority (Option<Pubkey>)
+        8 + // ticket_price
+        9 + // price_curve (largest variant: LinearStep)
+        33 + // payment_mint (Option<Pubkey>)
+        4 + (AcceptedMint::SPACE * MAX_ACCEPTED_MINTS) + // accepted_mints
+        33 + // prize_nft_mint (Option<Pubkey>)
+        33 + // prize_token_mint (Option<Pubkey>)
+        8 + // prize_token_amount
+        4 + // max_tickets
+        4 + // min_tickets
+        4 + // max_tickets_per_wallet
+        8 + // end_time
+        8 + // original_end_time
+        8 + // created_at
+        5 + // winner_index (Option<u32>)
+        33 + // winner (Option<Pubkey>)
+        33 + // previous_winner (Option<Pubkey>)
+        5 + // runner_up_index (Option<u32>)
+        5 + // consolation_winner_index (Option<u32>)
+        4 + // claim_window_secs
+        4 + // draw_delay_secs
+        9 + // claim_deadline (Option<i64>)
+        1 + // claimed
+        1 + // consolation_claimed
+        1 + // draw_winner_started
+        1 + // randomness_source
+        9 + // commit_slot (Option<u64>)
+        1 + // refunds_open
+        1 + // cancelled
+        1 + // paused
+        1 + // free_entry
+        1 + // manager_cannot_enter
+        1 + // auto_draw
+        1 + // records_purchases
+        1 + // listed_in_manager_index
+        2 + // loyalty_bonus_bps
+        2 + // second_chance_prize_bps
+        2 + // fee_bps
+        8 + // total_collected
+        8 + // keeper_bounty
+        2 + // operator_withdrawal_cap_bps
+        8 + // operator_withdrawn
+        8 + // refund_bond
+        1 + // revenue_mode
+        8 + // fixed_prize_lamports
+        1 + // revenue_withdrawn
+        8 + // sales_cutoff_time
+        32 + // terms_hash
+        4 + MAX_NAME_LEN + // name
+        4 + MAX_URI_LEN + // uri
+        33 + // allowlist_root (Option<[u8; 32]>)
+        32 + // randomness
+        4 + (VestingTranche::SPACE * MAX_VESTING_TRANCHES) + // vesting_schedule
+        2 + // claimed_bps
+        4 + // tickets_sold
+        4 + // length of accepted_mint_tickets vec
+        // length of entrants vec
+        4;
+
+    /// Calculates the raffle account space based on the maximum number of
+    /// tickets, for the worst case of every ticket held by another wallet.
     /// This does not include the 8 bytes added as a discriminator by Anchor.
     pub const fn account_space(max_tickets: u32) -> usize {
-        32 +  // raffle_manager
-            8 +   // ticket_price
-            4 +   // max_tickets
-            8 +   // end_time
-            5 +   // winner (Option<u32>)
-            1 +   // claimed
-            1 +   // draw_winner_started
-            4 +   // length of entrants vec
-            (32 * max_tickets as usize) // entrants
+        Self::BASE_SIZE + (EntrantEntry::SPACE * max_tickets as usize) // entrants
+    }
+
+    /// Like `account_space`, for a raffle with accepted mints, where every
+    /// ticket may also have been paid in one by another wallet.
+    pub const fn accepting_account_space(max_tickets: u32) -> usize {
+        Self::account_space(max_tickets) + (AcceptedMintTickets::SPACE * max_tickets as usize)
+    }
+
+    /// Size, discriminator included, a raffle account of `data_len` bytes
+    /// must grow to in order to hold `entries` entrants, `accepted_entries`
+    /// of them in `accepted_mint_tickets`. Accounts never shrink, so one
+    /// already large enough keeps its size.
+    pub const fn grown_space(data_len: usize, entries: usize, accepted_entries: usize) -> usize {
+        let needed = 8
+            + Self::BASE_SIZE
+            + EntrantEntry::SPACE * entries
+            + AcceptedMintTickets::SPACE * accepted_entries;
+        if needed > data_len {
+            needed
+        } else {
+            data_len
+        }
+    }
+
+    /// Whether the raffle is over: either every ticket has been sold or the
+    /// end time has been reached. `buy_tickets` accepts purchases exactly
+    /// while this is false, and `draw_winner` exactly once it is true, so
+    /// `end_time` itself already belongs to the draw.
+    pub fn is_raffle_over(&self, now: UnixTimestamp) -> bool {
+        self.tickets_sold >= self.max_tickets || now >= self.end_time
+    }
+
+    /// Whether the raffle can be drawn: it is over and `draw_delay_secs` have
+    /// passed since `end_time`. Without a delay a sold-out raffle can be
+    /// drawn right away; with one, it waits for the delay after `end_time`
+    /// like any other.
+    pub fn is_draw_open(&self, now: UnixTimestamp) -> bool {
+        self.is_raffle_over(now) && (self.draw_delay_secs == 0 || now >= self.draw_opens_at())
+    }
+
+    /// Unix timestamp (seconds) from which the draw is open if the raffle
+    /// doesn't sell out sooner.
+    pub fn draw_opens_at(&self) -> UnixTimestamp {
+        self.end_time
+            .saturating_add(i64::from(self.draw_delay_secs))
+    }
+
+    /// Index in `entrants` of `wallet`'s entry, if it holds any tickets.
+    pub fn entry_index_of(&self, wallet: &Pubkey) -> Option<usize> {
+        self.entrants
+            .iter()
+            .position(|entry| entry.buyer == *wallet)
+    }
+
+    /// Number of tickets held by `wallet`.
+    pub fn tickets_of(&self, wallet: &Pubkey) -> u32 {
+        self.entry_index_of(wallet)
+            .map_or(0, |index| self.entrants[index].count)
+    }
+
+    /// Number of entries `entrants` holds once `wallet` buys tickets: one
+    /// more unless it already has an entry.
+    pub fn entries_after_purchase(&self, wallet: &Pubkey) -> usize {
+        self.entrants.len() + usize::from(self.entry_index_of(wallet).is_none())
+    }
+
+    /// Adds `number_of_tickets` tickets for `wallet`, to its entry if it has
+    /// one and to a new entry otherwise. Callers check `max_tickets` first,
+    /// so the counts can't overflow.
+    pub fn add_tickets(&mut self, wallet: Pubkey, number_of_tickets: u32) {
+        match self.entry_index_of(&wallet) {
+            Some(index) => self.entrants[index].count += number_of_tickets,
+            None => self.entrants.push(EntrantEntry {
+                buyer: wallet,
+                count: number_of_tickets,
+            }),
+        }
+        self.tickets_sold += number_of_tickets;
+    }
+
+    /// Removes `wallet`'s entry, returning how many tickets it held.
+    pub fn remove_tickets(&mut self, wallet: &Pubkey) -> u32 {
+        let Some(index) = self.entry_index_of(wallet) else {
+            return 0;
+        };
+        let entry = self.entrants.remove(index);
+        self.tickets_sold -= entry.count;
+        entry.count
+    }
+
+    /// Index in `accepted_mint_tickets` of `wallet`'s entry, if it paid for
+    /// any tickets in an accepted mint.
+    fn accepted_entry_index_of(&self, wallet: &Pubkey) -> Option<usize> {
+        self.accepted_mint_tickets
+            .iter()
+            .position(|entry| entry.buyer == *wallet)
+    }
+
+    /// Number of entries `accepted_mint_tickets` holds once `wallet` buys
+    /// tickets with `mint_index`: one more if they are paid in an accepted
+    /// mint and it has no entry yet.
+    pub fn accepted_entries_after_purchase(
+        &self,
+        wallet: &Pubkey,
+        mint_index: Option<u8>,
+    ) -> usize {
+        self.accepted_mint_tickets.len()
+            + usize::from(mint_index.is_some() && self.accepted_entry_index_of(wallet).is_none())
+    }
+
+    /// Records that `number_of_tickets` of `wallet`'s tickets were paid in
+    /// the accepted mint at `mint_index`, which callers check is in range.
+    pub fn add_accepted_mint_tickets(
+        &mut self,
+        wallet: Pubkey,
+        mint_index: u8,
+        number_of_tickets: u32,
+    ) {
+        let index = self.accepted_entry_index_of(&wallet).unwrap_or_else(|| {
+            self.accepted_mint_tickets.push(AcceptedMintTickets {
+                buyer: wallet,
+                counts: [0; MAX_ACCEPTED_MINTS],
+            });
+            self.accepted_mint_tickets.len() - 1
+        });
+        self.accepted_mint_tickets[index].counts[usize::from(mint_index)] += number_of_tickets;
+    }
+
+    /// Removes `wallet`'s entry from `accepted_mint_tickets`, returning how
+    /// many of its tickets were paid in each accepted mint.
+    pub fn remove_accepted_mint_tickets(&mut self, wallet: &Pubkey) -> [u32; MAX_ACCEPTED_MINTS] {
+        self.accepted_entry_index_of(wallet)
+            .map_or([0; MAX_ACCEPTED_MINTS], |index| {
+                self.accepted_mint_tickets.remove(index).counts
+            })
+    }
+
+    /// Tickets sold and not refunded that were paid in an accepted mint
+    /// rather than in the payment mint.
+    pub fn accepted_mint_tickets_sold(&self) -> u32 {
+        self.accepted_mint_tickets
+            .iter()
+            .flat_map(|entry| entry.counts)
+            .sum()
+    }
+
+    /// Chance, in basis points rounded down, that `wallet` wins if the
+    /// winner were drawn now. Zero while no tickets are sold.
+    pub fn win_probability_bps(&self, wallet: &Pubkey) -> u16 {
+        if self.tickets_sold == 0 {
+            return 0;
+        }
+        let tickets = u64::from(self.tickets_of(wallet));
+        // At most FULL_BPS, as a wallet can't hold more than every ticket
+        u16::try_from(tickets * u64::from(FULL_BPS) / u64::from(self.tickets_sold))
+            .unwrap_or(FULL_BPS)
+    }
+
+    /// Whether `buyer` buying `number_of_tickets` more would hold more than
+    /// `max_tickets_per_wallet`.
+    pub fn exceeds_wallet_limit(&self, buyer: &Pubkey, number_of_tickets: u32) -> bool {
+        self.max_tickets_per_wallet != 0
+            && u64::from(self.tickets_of(buyer)) + u64::from(number_of_tickets)
+                > u64::from(self.max_tickets_per_wallet)
+    }
+
+    /// Whether `wallet` may enter: the raffle has no allowlist, or `proof`
+    /// shows `wallet` is on it.
+    pub fn is_allowed(&self, wallet: &Pubkey, proof: &[[u8; 32]]) -> bool {
+        match &self.allowlist_root {
+            None => true,
+            Some(root) => merkle::verify(proof, root, wallet),
+        }
+    }
+
+    /// Price of the next `number_of_tickets` tickets under the raffle's price
+    /// curve. `create_raffle` checks that all `max_tickets` tickets can be
+    /// priced, so this only fails if that invariant is broken.
+    pub fn purchase_price(&self, number_of_tickets: u32) -> Result<u64> {
+        self.price_curve
+            .total_price(self.ticket_price, self.tickets_sold, number_of_tickets)
+            .ok_or_else(|| error!(RaffleError::PriceOverflow))
+    }
+
+    /// Price of the next `number_of_tickets` tickets in the mint they are
+    /// paid in: the price curve's for `mint_index` `None`, and otherwise the
+    /// flat price of the accepted mint at that index.
+    pub fn purchase_price_in(&self, mint_index: Option<u8>, number_of_tickets: u32) -> Result<u64> {
+        let Some(mint_index) = mint_index else {
+            return self.purchase_price(number_of_tickets);
+        };
+        let accepted_mint = self
+            .accepted_mints
+            .get(usize::from(mint_index))
+            .ok_or(RaffleError::InvalidMintIndex)?;
+        accepted_mint
+            .ticket_price
+            .checked_mul(u64::from(number_of_tickets))
+            .ok_or_else(|| error!(RaffleError::PriceOverflow))
+    }
+
+    /// The ticket revenue: every ticket payment collected, less the keeper
+    /// bounty paid out by `draw_winner` and what the manager withdrew.
+    pub fn ticket_revenue(&self) -> Result<u64> {
+        self.total_collected
+            .checked_sub(self.keeper_bounty)
+            .and_then(|pool| pool.checked_sub(self.operator_withdrawn))
+            .ok_or_else(|| error!(RaffleError::PrizeOverflow))
+    }
+
+    /// The prize pool: the ticket revenue, or the fixed prize when the
+    /// manager keeps the revenue.
+    pub fn prize_amount(&self) -> Result<u64> {
+        match self.revenue_mode {
+            RevenueMode::WinnerTakesPool => self.ticket_revenue(),
+            RevenueMode::ManagerKeepsRevenue => Ok(self.fixed_prize_lamports),
+        }
+    }
+
+    /// The consolation winner's share of the prize pool; zero when there is
+    /// no consolation winner, in which case it stays with the winner.
+    pub fn consolation_amount(&self) -> Result<u64> {
+        if self.consolation_winner_index.is_none() {
+            return Ok(0);
+        }
+        bps_of(self.prize_amount()?, self.second_chance_prize_bps)
+    }
+
+    /// The raffle manager's fee out of the prize pool.
+    pub fn fee_amount(&self) -> Result<u64> {
+        bps_of(self.prize_amount()?, self.fee_bps)
+    }
+
+    /// Basis points of the winner's share unlocked at time `now`: the sum of
+    /// every tranche whose unlock time has passed, or everything when there
+    /// is no vesting schedule.
+    pub fn unlocked_bps(&self, now: UnixTimestamp) -> u16 {
+        if self.vesting_schedule.is_empty() {
+            return FULL_BPS;
+        }
+        self.vesting_schedule
+            .iter()
+            .filter(|tranche| tranche.unlock_time <= now)
+            .map(|tranche| tranche.bps)
+            .sum()
+    }
+
+    /// Whether the raffle ended by time `now` without selling `min_tickets`,
+    /// so it can't be drawn.
+    pub fn is_minimum_missed(&self, now: UnixTimestamp) -> bool {
+        now >= self.end_time && self.tickets_sold < self.min_tickets
+    }
+
+    /// Whether entrants can reclaim their tickets at time `now`: either refunds
+    /// were opened for an undrawn raffle, the manager cancelled it, or the
+    /// minimum wasn't reached.
+    pub fn is_refunding(&self, now: UnixTimestamp) -> bool {
+        self.refunds_open || self.cancelled || self.is_minimum_missed(now)
+    }
+
+    /// Ticket sales and timing of the raffle at time `now`.
+    pub fn progress(&self, now: UnixTimestamp) -> RaffleProgress {
+        let tickets_sold = self.tickets_sold;
+        RaffleProgress {
+            tickets_sold,
+            tickets_remaining: self.max_tickets.saturating_sub(tickets_sold),
+            seconds_remaining: self.end_time.saturating_sub(now).max(0),
+            is_over: self.is_raffle_over(now),
+            winner_drawn: self.winner_index.is_some(),
+        }
+    }
+
+    /// Where the raffle is in its lifecycle at time `now`.
+    pub fn status(&self, now: UnixTimestamp) -> RaffleStatus {
+        if self.is_refunding(now) {
+            RaffleStatus::Refunding
+        } else if self.claimed {
+            RaffleStatus::Claimed
+        } else if self.winner_index.is_some() {
+            RaffleStatus::WinnerDrawn
+        } else if self.draw_winner_started {
+            RaffleStatus::Drawing
+        } else if self.is_raffle_over(now) {
+            RaffleStatus::Ended
+        } else {
+            RaffleStatus::Open
+        }
+    }
+
+    /// Re-derives `winner_index` from the stored randomness over the current
+    /// entrants, for when the stored index no longer points into `entrants`.
+    /// Clears the winner if there are no entrants left to select from. The
+    /// runner-up is re-derived along with it, and so is the consolation
+    /// winner unless the winner was redrawn.
+    pub fn reselect_winner(&mut self) -> Result<Option<u32>> {
+        let Some(winner_index) = self.select_winner() else {
+            self.winner_index = None;
+            self.winner = None;
+            self.runner_up_index = None;
+            if self.previous_winner.is_none() {
+                self.consolation_winner_index = None;
+            }
+            return Ok(None);
+        };
+        self.winner_index = Some(entrants_u32(winner_index)?);
+        self.winner = Some(self.entrants[winner_index].buyer);
+        self.runner_up_index = self.select_runner_up(winner_index)?;
+        if self.previous_winner.is_none() {
+            self.consolation_winner_index = self.select_consolation_winner(winner_index)?;
+        }
+        Ok(self.winner_index)
+    }
+
+    /// Selects the index of the winner's entry from the stored randomness:
+    /// the holder of any ticket on the first draw, and after `redraw_winner`
+    /// of a ticket held by neither the passed-over winner nor the
+    /// consolation winner. `None` if there is no such ticket.
+    pub fn select_winner(&self) -> Option<usize> {
+        match self.previous_winner {
+            None => select_entry_index(&self.randomness, &self.entrants),
+            Some(previous_winner) => {
+                let mut excluded = vec![previous_winner];
+                excluded.extend(self.consolation_winner());
+                select_index_excluding(&self.randomness, &self.entrants, &excluded)
+            }
+        }
+    }
+
+    /// Selects the winner's entry index for `record_winner`. Fails with
+    /// `NoEntrants` rather than dividing by zero if nothing was sold, and with
+    /// `NoOtherEntrants` if a redraw has no eligible ticket left.
+    pub fn draw_winner_entry(&self) -> Result<usize> {
+        require!(self.tickets_sold > 0, RaffleError::NoEntrants);
+        self.select_winner()
+            .ok_or_else(|| RaffleError::NoOtherEntrants.into())
+    }
+
+    /// Selects the runner-up for the winner at `winner_index` from the stored
+    /// randomness, if the raffle has a claim window. After a redraw the
+    /// passed-over winner and the consolation winner are excluded too.
+    pub fn select_runner_up(&self, winner_index: usize) -> Result<Option<u32>> {
+        if self.claim_window_secs == 0 {
+            return Ok(None);
+        }
+        let mut excluded = vec![self.entrants[winner_index].buyer];
+        if let Some(previous_winner) = self.previous_winner {
+            excluded.push(previous_winner);
+            excluded.extend(self.consolation_winner());
+        }
+        select_index_excluding(&self.randomness, &self.entrants, &excluded)
+            .map(entrants_u32)
+            .transpose()
+    }
+
+    /// Selects the consolation winner for the winner at `winner_index` from
+    /// the stored randomness, if the raffle has a second-chance prize. The
+    /// runner-up must already be selected, as they are excluded too.
+    pub fn select_consolation_winner(&self, winner_index: usize) -> Result<Option<u32>> {
+        if self.second_chance_prize_bps == 0 {
+            return Ok(None);
+        }
+        select_consolation_index(
+            &self.randomness,
+            &self.entrants,
+            winner_index,
+            self.runner_up_index.map(|index| index as usize),
+        )
+        .map(entrants_u32)
+        .transpose()
+    }
+
+    /// The consolation winner's public key, if one was selected.
+    pub fn consolation_winner(&self) -> Option<Pubkey> {
+        self.consolation_winner_index
+            .and_then(|index| self.entrants.get(index as usize))
+            .map(|entry| entry.buyer)
+    }
+
+    /// Whether a consolation winner was drawn and may still claim at time
+    /// `now`: the second-chance prize is unclaimed and the claim window it
+    /// shares with the winner hasn't lapsed. A lapsed share is left in the
+    /// raffle account and goes to the manager when the raffle is closed.
+    pub fn is_consolation_pending(&self, now: UnixTimestamp) -> bool {
+        self.consolation_winner_index.is_some()
+            && !self.consolation_claimed
+            && !self.claim_deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Whether `key` holds the winning ticket; false until the winner is drawn.
+    pub fn is_winner(&self, key: &Pubkey) -> bool {
+        self.winner == Some(*key)
+    }
+
+    /// The runner-up's public key, if one was selected.
+    pub fn runner_up(&self) -> Option<Pubkey> {
+        self.runner_up_index
+            .and_then(|index| self.entrants.get(index as usize))
+            .map(|entry| entry.buyer)
+    }
+
+    /// Whether the winner's claim window has lapsed at time `now` without
+    /// any part of the prize being paid. The winner can then no longer
+    /// claim; the runner-up may, or the winner can be redrawn.
+    pub fn is_claim_window_expired(&self, now: UnixTimestamp) -> bool {
+        self.claimed_bps == 0 && self.claim_deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Whether the runner-up may claim at time `now`: the winner's claim
+    /// window has lapsed without any part of the prize being paid.
+    pub fn is_runner_up_turn(&self, now: UnixTimestamp) -> bool {
+        self.runner_up_index.is_some() && self.is_claim_window_expired(now)
+    }
+
+    /// Makes the runner-up the winner, once they claim in the winner's place.
+    pub fn promote_runner_up(&mut self) {
+        self.winner = self.runner_up();
+        self.winner_index = self.runner_up_index.take();
+    }
+
+    /// The tickets held by each distinct entrant, in order of first
+    /// purchase. This copies the whole `entrants` vec, so only call it from
+    /// instructions that run once per raffle.
+    pub fn ticket_counts(&self) -> Vec<(Pubkey, u32)> {
+        self.entrants
+            .iter()
+            .map(|entry| (entry.buyer, entry.count))
+            .collect()
+    }
+
+    /// Same as `ticket_counts`, but ordered by ticket count, largest first.
+    /// Ties go to whoever bought first.
+    pub fn ranked_ticket_counts(&self) -> Vec<(Pubkey, u32)> {
+        let mut counts = self.ticket_counts();
+        // Stable sort, so equal counts keep their first purchase order
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+
+    /// Returns up to `limit` entrants holding the most tickets, in the order
+    /// of `ranked_ticket_counts`.
+    pub fn top_ticket_holders(&self, limit: usize) -> Vec<(Pubkey, u32)> {
+        let mut counts = self.ranked_ticket_counts();
+        counts.truncate(limit);
+        counts
+    }
+}
+
+/// Maps VRF randomness to a ticket number in `0..num_tickets`.
+/// `num_tickets` must not be zero.
+///
+/// The randomness is read as a 256-bit little-endian number `r` and scaled to
+/// `r * num_tickets / 2^256`, rather than reduced modulo `num_tickets`, so
+/// each ticket is equally likely to within `num_tickets / 2^256`. The product
+/// is computed one 64-bit limb at a time, keeping only the carry.
+pub fn select_winner_index(randomness: &[u8; 32], num_tickets: usize) -> usize {
+    let num_tickets = num_tickets as u128;
+    let index = randomness
+        .chunks_exact(8)
+        .map(|limb| u64::from_le_bytes(limb.try_into().unwrap()))
+        .fold(0_u128, |carry, limb| {
+            (u128::from(limb) * num_tickets + carry) >> 64
+        });
+    // Below num_tickets, so it always fits
+    usize::try_from(index).unwrap()
+}
+
+/// Converts a count of, or an index into, a raffle's entrants to the `u32`
+/// the raffle state stores it as. There are never more entrants than
+/// `max_tickets`, so this can't fail, but fails cleanly if that invariant is
+/// ever broken.
+pub fn entrants_u32(value: usize) -> Result<u32> {
+    u32::try_from(value).map_err(|_| error!(RaffleError::EntrantCountOverflow))
+}
+
+/// Index in `entrants` of the entry holding ticket number `ticket`, counting
+/// tickets entry by entry in order. `None` past the last ticket.
+pub fn entry_index_of_ticket(entrants: &[EntrantEntry], ticket: usize) -> Option<usize> {
+    let mut tickets_through = 0_usize;
+    entrants.iter().position(|entry| {
+        tickets_through += entry.count as usize;
+        ticket < tickets_through
+    })
+}
+
+/// Maps VRF randomness to the index in `entrants` of the entry holding a
+/// ticket drawn uniformly from all of them, so each entrant's chance is in
+/// proportion to its ticket count. `None` if no tickets are held.
+pub fn select_entry_index(randomness: &[u8; 32], entrants: &[EntrantEntry]) -> Option<usize> {
+    let total_tickets: usize = entrants.iter().map(|entry| entry.count as usize).sum();
+    if total_tickets == 0 {
+        return None;
+    }
+    entry_index_of_ticket(entrants, select_winner_index(randomness, total_tickets))
+}
+
+/// Binds `randomness` to the raffle it draws for by hashing it with the
+/// raffle's address, so the same randomness reused across raffles still
+/// selects independently in each.
+pub fn raffle_randomness(randomness: &[u8; 32], raffle: &Pubkey) -> [u8; 32] {
+    hashv(&[randomness, raffle.as_ref()]).to_bytes()
+}
+
+/// How many times the randomness is re-hashed looking for a runner-up or
+/// consolation winner before falling back to the first ticket of a wallet
+/// that isn't excluded.
+const MAX_RUNNER_UP_DRAWS: usize = 32;
+
+/// Maps VRF randomness to the entry index of a runner-up: the holder of a
+/// ticket held by another wallet than the winner at `winner_index`, drawn by
+/// re-hashing the randomness until it lands on one. `None` if the winner
+/// holds every ticket.
+pub fn select_runner_up_index(
+    randomness: &[u8; 32],
+    entrants: &[EntrantEntry],
+    winner_index: usize,
+) -> Option<usize> {
+    select_index_excluding(randomness, entrants, &[entrants[winner_index].buyer])
+}
+
+/// Maps VRF randomness to the entry index of a consolation winner: the
+/// holder of a ticket held by another wallet than the winner at `winner_index` and the runner-up at
+/// `runner_up_index`, so a runner-up promoted to winner can't also collect
+/// the second-chance prize. Drawn from a domain-separated hash of the
+/// randomness so it is independent of the runner-up. `None` if those two
+/// wallets hold every ticket.
+pub fn select_consolation_index(
+    randomness: &[u8; 32],
+    entrants: &[EntrantEntry],
+    winner_index: usize,
+    runner_up_index: Option<usize>,
+) -> Option<usize> {
+    let seed = hashv(&[b"consolation", randomness]).to_bytes();
+    let mut excluded = vec![entrants[winner_index].buyer];
+    excluded.extend(runner_up_index.map(|index| entrants[index].buyer));
+    select_index_excluding(&seed, entrants, &excluded)
+}
+
+/// Draws the entry index of the holder of a ticket held by none of the
+/// `excluded` wallets by re-hashing the randomness until it lands on one.
+/// `None` if they hold every ticket.
+fn select_index_excluding(
+    randomness: &[u8; 32],
+    entrants: &[EntrantEntry],
+    excluded: &[Pubkey],
+) -> Option<usize> {
+    let mut seed = *randomness;
+    for _ in 0..MAX_RUNNER_UP_DRAWS {
+        seed = hash(&seed).to_bytes();
+        let index = select_entry_index(&seed, entrants)?;
+        if !excluded.contains(&entrants[index].buyer) {
+            return Some(index);
+        }
+    }
+    // Only reached when the excluded wallets hold nearly every ticket
+    entrants
+        .iter()
+        .position(|entry| entry.count > 0 && !excluded.contains(&entry.buyer))
+}
+
+/// Lifecycle stage of a raffle, reported as a single byte by
+/// `get_statuses_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RaffleStatus {
+    /// Tickets may still be bought.
+    Open = 0,
+    /// Sold out or past `end_time`, waiting for `draw_winner`.
+    Ended = 1,
+    /// `draw_winner` ran and the VRF callback is pending.
+    Drawing = 2,
+    /// A winner is selected but the prize is unclaimed.
+    WinnerDrawn = 3,
+    /// The prize was claimed.
+    Claimed = 4,
+    /// The raffle account was closed.
+    Closed = 5,
+    /// The raffle expired undrawn and entrants can claim refunds.
+    Refunding = 6,
+}
+
+/// Where a raffle's randomness comes from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandomnessSource {
+    /// Verifiable randomness requested by `draw_winner` and delivered by the
+    /// VRF oracle to `draw_winner_callback`.
+    Vrf,
+    /// The hash of a slot committed to by `draw_winner_from_slot_hashes`,
+    /// read back from the SlotHashes sysvar. Needs no oracle, but the leader
+    /// of that slot can influence the result.
+    SlotHashes,
+}
+
+/// How the price of each ticket moves as the raffle sells.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceCurve {
+    /// Every ticket costs `ticket_price`.
+    Flat,
+    /// Each ticket costs `increment` more than the one sold before it.
+    LinearStep { increment: u64 },
+}
+
+impl PriceCurve {
+    /// Total price of `number_of_tickets` tickets bought once `sold` tickets
+    /// were already sold, the first ticket costing `base_price`. `None` if it
+    /// overflows `u64`.
+    pub fn total_price(&self, base_price: u64, sold: u32, number_of_tickets: u32) -> Option<u64> {
+        let count = u128::from(number_of_tickets);
+        let flat = u128::from(base_price) * count;
+        let total = match *self {
+            Self::Flat => flat,
+            // Ticket i (0-based) costs base_price + increment * i, so the
+            // batch adds increment * (sold + ... + sold + count - 1)
+            Self::LinearStep { increment } => {
+                let steps = count * u128::from(sold) + count * count.saturating_sub(1) / 2;
+                flat.checked_add(u128::from(increment).checked_mul(steps)?)?
+            }
+        };
+        u64::try_from(total).ok()
+    }
+}
+
+/// How far a raffle's ticket sales have got, as returned by
+/// `get_raffle_progress`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RaffleProgress {
+    /// Tickets bought so far.
+    pub tickets_sold: u32,
+    /// Tickets still available before the raffle sells out.
+    pub tickets_remaining: u32,
+    /// Seconds until `end_time`; zero once it has passed.
+    pub seconds_remaining: i64,
+    /// Whether the raffle is over, see `RaffleState::is_raffle_over`.
+    pub is_over: bool,
+    /// Whether the winner has been selected.
+    pub winner_drawn: bool,
+}
+
+/// Whether a wallet can claim a raffle's prize, as returned by
+/// `check_claim_eligibility`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ClaimEligibility {
+    /// Whether the winner has been selected.
+    pub drawn: bool,
+    /// Whether the wallet holds the winning ticket.
+    pub is_winner: bool,
+    /// Whether the prize has already been paid in full.
+    pub already_claimed: bool,
+    /// The prize pool, see `RaffleState::prize_amount`.
+    pub prize_amount: u64,
+}
+
+/// How many tickets a wallet holds in a raffle, as returned by
+/// `get_my_tickets`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalletTickets {
+    /// Tickets held by the wallet.
+    pub tickets: u32,
+    /// Tickets sold in total.
+    pub total_tickets: u32,
+}
+
+/// A wallet holding tickets in a raffle, and how many it holds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntrantEntry {
+    /// Wallet the tickets were bought for.
+    pub buyer: Pubkey,
+    /// Tickets it holds.
+    pub count: u32,
+}
+
+impl EntrantEntry {
+    pub const SPACE: usize = 32 + // buyer
+        4; // count
+}
+
+/// A slice of a raffle's entrants, as returned by `get_entrants_page`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntrantsPage {
+    /// Entrants from the requested start, one per wallet in order of first
+    /// purchase. Empty past the end of the list.
+    pub entrants: Vec<EntrantEntry>,
+    /// Entrants in total, one per wallet.
+    pub total_entrants: u32,
+}
+
+/// The page of up to `limit` entrants starting at entry `start`, clamped
+/// to the entrants there are and to `MAX_ENTRANTS_PER_PAGE`.
+pub fn entrants_page(entrants: &[EntrantEntry], start: u32, limit: u32) -> Result<EntrantsPage> {
+    let start = (start as usize).min(entrants.len());
+    let end = start
+        .saturating_add(limit.min(MAX_ENTRANTS_PER_PAGE) as usize)
+        .min(entrants.len());
+    Ok(EntrantsPage {
+        entrants: entrants[start..end].to_vec(),
+        total_entrants: entrants_u32(entrants.len())?,
+    })
+}
+
+/// Who a raffle's ticket revenue goes to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevenueMode {
+    /// The winner takes the ticket revenue as the prize.
+    WinnerTakesPool,
+    /// The manager withdraws the ticket revenue with `withdraw_revenue`, and
+    /// the winner receives `fixed_prize_lamports` instead.
+    ManagerKeepsRevenue,
+}
+
+/// A mint a token raffle also sells tickets in, at a fixed price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcceptedMint {
+    /// Mint the tickets are paid in.
+    pub mint: Pubkey,
+    /// Price of one ticket in base units of `mint`: the manager's fixed
+    /// conversion of the raffle's `ticket_price`.
+    pub ticket_price: u64,
+}
+
+impl AcceptedMint {
+    pub const SPACE: usize = 32 + // mint
+        8; // ticket_price
+}
+
+/// How many of a wallet's tickets were paid in each of a raffle's accepted
+/// mints, in the order of `accepted_mints`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcceptedMintTickets {
+    /// Wallet the tickets were bought for.
+    pub buyer: Pubkey,
+    /// Tickets paid in each accepted mint; zero past the raffle's mints.
+    pub counts: [u32; MAX_ACCEPTED_MINTS],
+}
+
+impl AcceptedMintTickets {
+    pub const SPACE: usize = 32 + // buyer
+        4 * MAX_ACCEPTED_MINTS; // counts
+}
+
+/// A portion of the winner's share that unlocks at a given time.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VestingTranche {
+    /// Unix timestamp (seconds) from which the tranche can be claimed.
+    pub unlock_time: i64,
+    /// Share of the winner's prize in the tranche, in basis points.
+    pub bps: u16,
+}
+
+impl VestingTranche {
+    pub const SPACE: usize = 8 + // unlock_time
+        2; // bps
+}
+
+/// A wallet and the number of tickets it holds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DistributionBucket {
+    pub holder: Pubkey,
+    pub tickets: u32,
+}
+
+/// Snapshot of how a raffle's tickets are spread across wallets, stored as a
+/// PDA so dashboards don't have to tally the entrants vec themselves.
+#[account]
+pub struct Distribution {
+    /// Raffle state PDA the snapshot was taken from.
+    pub raffle_state: Pubkey,
+    /// Tickets sold when the snapshot was taken.
+    pub total_tickets: u32,
+    /// Up to `DISTRIBUTION_TOP_N` largest holders, most tickets first.
+    pub top_holders: Vec<DistributionBucket>,
+    /// Number of wallets not listed in `top_holders`.
+    pub others_wallets: u32,
+    /// Tickets held by the wallets not listed in `top_holders`.
+    pub others_tickets: u32,
+}
+
+impl Distribution {
+    /// Distribution account space, not including the 8 byte discriminator.
+    pub const SPACE: usize = 32 + // raffle_state
+        4 + // total_tickets
+        4 + (36 * DISTRIBUTION_TOP_N) + // top_holders
+        4 + // others_wallets
+        4; // others_tickets
+}
+
+/// A single `buy_tickets` call: the tickets it bought are numbered from
+/// `first_index` in order of sale, up to the next purchase's.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Purchase {
+    /// Tickets sold before the purchase, i.e. the number of its first ticket.
+    pub first_index: u32,
+    /// Slot the purchase was made in.
+    pub slot: u64,
+}
+
+impl Purchase {
+    pub const SPACE: usize = 4 + // first_index
+        8; // slot
+}
+
+/// Timing of every purchase of a raffle, in purchase order, kept per purchase
+/// rather than per ticket so it stays small. Created with the raffle when it
+/// records purchases, and grown by each `buy_tickets` at the buyer's expense.
+/// Ticket numbers count the tickets in order of sale, before any refunds.
+#[account]
+pub struct PurchaseLog {
+    /// Raffle state PDA the purchases belong to.
+    pub raffle_state: Pubkey,
+    /// Purchases, oldest first.
+    pub purchases: Vec<Purchase>,
+}
+
+impl PurchaseLog {
+    /// Purchase log account space for `purchases` purchases, not including
+    /// the 8 byte discriminator.
+    pub const fn space(purchases: usize) -> usize {
+        32 + // raffle_state
+        4 + (Purchase::SPACE * purchases) // purchases
+    }
+
+    /// The purchase that bought ticket number `ticket_index` in order of sale.
+    pub fn purchase_of(&self, ticket_index: u32) -> Option<&Purchase> {
+        let later = self
+            .purchases
+            .partition_point(|purchase| purchase.first_index <= ticket_index);
+        later.checked_sub(1).map(|index| &self.purchases[index])
+    }
+}
+
+/// The raffles a manager created, so a client can list them by fetching one
+/// account instead of knowing every raffle's seeds. Created once with
+/// `create_manager_index` before the manager's first raffle, then passed to
+/// every `create_raffle`, which grows it for a raffle it lists, and shrunk
+/// again when a listed raffle is closed. Keyed by the creator, so a raffle
+/// handed over to a new manager stays in its creator's index.
+#[account]
+pub struct ManagerRaffleIndex {
+    /// Manager whose raffles are listed.
+    pub manager: Pubkey,
+    /// Whether the manager opted in to `CREATE_COOLDOWN_SECS` between two of
+    /// its raffles, to show it doesn't spam them.
+    pub create_cooldown: bool,
+    /// Unix timestamp (seconds) at which the manager last created a raffle,
+    /// listed or not; zero until it does.
+    pub last_created: i64,
+    /// Raffle state PDAs not yet closed, oldest first.
+    pub raffles: Vec<Pubkey>,
+}
+
+impl ManagerRaffleIndex {
+    /// Manager index account space for `raffles` raffles, not including the
+    /// 8 byte discriminator.
+    pub const fn space(raffles: usize) -> usize {
+        32 + // manager
+        1 + // create_cooldown
+        8 + // last_created
+        4 + (32 * raffles) // raffles
+    }
+
+    /// Whether creating another raffle at time `now` would come too soon
+    /// after the last one.
+    pub fn is_cooling_down(&self, now: UnixTimestamp) -> bool {
+        self.create_cooldown && now < self.last_created.saturating_add(CREATE_COOLDOWN_SECS)
     }
 }
 
@@ -46,23 +1164,1019 @@ impl RaffleState {
 mod tests {
     use super::*;
 
+    /// A raffle selling two one-lamport tickets until 100, with nothing sold
+    /// and every optional feature off. Tests override the fields they exercise.
+    fn test_state() -> RaffleState {
+        RaffleState {
+            raffle_manager: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            pending_manager: None,
+            close_authority: None,
+            ticket_price: 1,
+            price_curve: PriceCurve::Flat,
+            payment_mint: None,
+            accepted_mints: vec![],
+            prize_nft_mint: None,
+            prize_token_mint: None,
+            prize_token_amount: 0,
+            max_tickets: 2,
+            min_tickets: 0,
+            max_tickets_per_wallet: 0,
+            end_time: 100,
+            original_end_time: 100,
+            created_at: 0,
+            winner_index: None,
+            winner: None,
+            previous_winner: None,
+            runner_up_index: None,
+            consolation_winner_index: None,
+            claim_window_secs: 0,
+            draw_delay_secs: 0,
+            claim_deadline: None,
+            draw_winner_started: false,
+            randomness_source: RandomnessSource::Vrf,
+            commit_slot: None,
+            claimed: false,
+            consolation_claimed: false,
+            refunds_open: false,
+            cancelled: false,
+            paused: false,
+            free_entry: false,
+            manager_cannot_enter: false,
+            auto_draw: false,
+            records_purchases: false,
+            listed_in_manager_index: false,
+            loyalty_bonus_bps: 0,
+            second_chance_prize_bps: 0,
+            fee_bps: 0,
+            total_collected: 0,
+            keeper_bounty: 0,
+            operator_withdrawal_cap_bps: 0,
+            operator_withdrawn: 0,
+            refund_bond: 0,
+            revenue_mode: RevenueMode::WinnerTakesPool,
+            fixed_prize_lamports: 0,
+            revenue_withdrawn: false,
+            sales_cutoff_time: 100,
+            terms_hash: [0; 32],
+            name: String::new(),
+            uri: String::new(),
+            allowlist_root: None,
+            randomness: [0; 32],
+            vesting_schedule: vec![],
+            claimed_bps: 0,
+            tickets_sold: 0,
+            accepted_mint_tickets: vec![],
+            entrants: vec![],
+        }
+    }
+
+    /// `state` with a ticket sold to each of `tickets`, in order.
+    fn with_tickets(mut state: RaffleState, tickets: &[Pubkey]) -> RaffleState {
+        for &wallet in tickets {
+            state.add_tickets(wallet, 1);
+        }
+        state
+    }
+
+    /// The entries holding a ticket sold to each of `tickets`, in order.
+    fn entries(tickets: &[Pubkey]) -> Vec<EntrantEntry> {
+        with_tickets(test_state(), tickets).entrants
+    }
+
     #[test]
     fn test_raffle_state_account_space() {
-        const MAX_TICKETS: usize = 10;
+        const MAX_TICKETS: u32 = 10;
         let state = RaffleState {
-            raffle_manager: Pubkey::new_unique(),
-            ticket_price: 1,
-            end_time: 1,
+            pending_manager: Some(Pubkey::new_unique()),
+            close_authority: Some(Pubkey::new_unique()),
+            price_curve: PriceCurve::LinearStep { increment: 1 },
+            payment_mint: Some(Pubkey::new_unique()),
+            accepted_mints: vec![
+                AcceptedMint {
+                    mint: Pubkey::new_unique(),
+                    ticket_price: 1,
+                };
+                MAX_ACCEPTED_MINTS
+            ],
+            prize_nft_mint: Some(Pubkey::new_unique()),
+            prize_token_mint: Some(Pubkey::new_unique()),
+            max_tickets: MAX_TICKETS,
             winner_index: Some(1),
-            max_tickets: MAX_TICKETS as u32,
-            claimed: false,
-            draw_winner_started: false,
-            entrants: vec![Pubkey::new_unique(); MAX_TICKETS],
+            winner: Some(Pubkey::new_unique()),
+            runner_up_index: Some(1),
+            consolation_winner_index: Some(1),
+            claim_deadline: Some(1),
+            commit_slot: Some(1),
+            name: "n".repeat(MAX_NAME_LEN),
+            uri: "u".repeat(MAX_URI_LEN),
+            allowlist_root: Some([2; 32]),
+            vesting_schedule: vec![
+                VestingTranche {
+                    unlock_time: 1,
+                    bps: 1
+                };
+                MAX_VESTING_TRANCHES
+            ],
+            ..test_state()
         };
+        // Every ticket held by another wallet is the largest the entrants get
+        let tickets: Vec<Pubkey> = (0..MAX_TICKETS).map(|_| Pubkey::new_unique()).collect();
+        let mut state = with_tickets(state, &tickets);
 
         let mut serialized_data = Vec::new();
         state.serialize(&mut serialized_data).unwrap();
-        let expected_size = RaffleState::account_space(MAX_TICKETS as u32);
+        let expected_size = RaffleState::account_space(MAX_TICKETS);
         assert_eq!(serialized_data.len(), expected_size);
+
+        // And with every one of them paid in an accepted mint
+        for &wallet in &tickets {
+            state.add_accepted_mint_tickets(wallet, 0, 1);
+        }
+        let mut serialized_data = Vec::new();
+        state.serialize(&mut serialized_data).unwrap();
+        let expected_size = RaffleState::accepting_account_space(MAX_TICKETS);
+        assert_eq!(serialized_data.len(), expected_size);
+    }
+
+    #[test]
+    fn test_base_size() {
+        assert_eq!(RaffleState::account_space(0), RaffleState::BASE_SIZE);
+    }
+
+    #[test]
+    fn test_grown_space() {
+        let empty = 8 + RaffleState::account_space(0);
+        assert_eq!(
+            RaffleState::grown_space(empty, 3, 0),
+            8 + RaffleState::account_space(3)
+        );
+        // Each new entrant adds exactly one entry
+        assert_eq!(
+            RaffleState::grown_space(8 + RaffleState::account_space(3), 4, 0),
+            8 + RaffleState::account_space(4)
+        );
+        // Paying in an accepted mint adds one more
+        assert_eq!(
+            RaffleState::grown_space(empty, 4, 4),
+            8 + RaffleState::accepting_account_space(4)
+        );
+        // Never shrinks, e.g. an account allocated for all its entries up front
+        let full = 8 + RaffleState::account_space(10);
+        assert_eq!(RaffleState::grown_space(full, 4, 0), full);
+        assert_eq!(RaffleState::grown_space(full, 10, 0), full);
+    }
+
+    #[test]
+    fn test_accepted_mint_tickets() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = with_tickets(test_state(), &[alice, bob, alice, bob]);
+        assert_eq!(state.accepted_entries_after_purchase(&alice, None), 0);
+        assert_eq!(state.accepted_entries_after_purchase(&alice, Some(1)), 1);
+
+        // Alice paid for one ticket in the second accepted mint and Bob for
+        // both of his in the first
+        state.add_accepted_mint_tickets(alice, 1, 1);
+        state.add_accepted_mint_tickets(bob, 0, 1);
+        state.add_accepted_mint_tickets(bob, 0, 1);
+        assert_eq!(state.accepted_entries_after_purchase(&bob, Some(1)), 2);
+        assert_eq!(state.accepted_mint_tickets_sold(), 3);
+
+        assert_eq!(state.remove_accepted_mint_tickets(&alice), [0, 1, 0]);
+        assert_eq!(state.remove_accepted_mint_tickets(&alice), [0; 3]);
+        assert_eq!(state.accepted_mint_tickets_sold(), 2);
+        assert_eq!(state.remove_accepted_mint_tickets(&bob), [2, 0, 0]);
+        assert!(state.accepted_mint_tickets.is_empty());
+    }
+
+    #[test]
+    fn test_is_raffle_over() {
+        let mut state = with_tickets(test_state(), &[Pubkey::new_unique()]);
+
+        assert!(!state.is_raffle_over(99));
+        assert!(state.is_raffle_over(100));
+        assert!(state.is_raffle_over(101));
+
+        assert!(!state.is_draw_open(99));
+        assert!(state.is_draw_open(100));
+
+        // A draw delay holds the draw back after the end time
+        state.draw_delay_secs = 30;
+        assert_eq!(state.draw_opens_at(), 130);
+        assert!(!state.is_draw_open(100));
+        assert!(!state.is_draw_open(129));
+        assert!(state.is_draw_open(130));
+
+        // Selling out ends the raffle early, but the delay still applies
+        state.add_tickets(Pubkey::new_unique(), 1);
+        assert!(state.is_raffle_over(99));
+        assert!(!state.is_draw_open(99));
+        assert!(state.is_draw_open(130));
+        state.draw_delay_secs = 0;
+        assert!(state.is_draw_open(99));
+    }
+
+    #[test]
+    fn test_exceeds_wallet_limit() {
+        let whale = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut state = with_tickets(
+            RaffleState {
+                max_tickets: 10,
+                max_tickets_per_wallet: 3,
+                ..test_state()
+            },
+            &[whale, other, whale],
+        );
+
+        // A wallet buying again adds to its entry
+        assert_eq!(state.entrants.len(), 2);
+        assert_eq!(state.tickets_sold, 3);
+        assert_eq!(state.tickets_of(&whale), 2);
+        assert_eq!(state.tickets_of(&Pubkey::new_unique()), 0);
+        assert!(!state.exceeds_wallet_limit(&whale, 1));
+        assert!(state.exceeds_wallet_limit(&whale, 2));
+        // Other wallets' tickets don't count towards the limit
+        assert!(!state.exceeds_wallet_limit(&other, 2));
+        assert!(state.exceeds_wallet_limit(&other, 3));
+
+        // Zero means no limit
+        state.max_tickets_per_wallet = 0;
+        assert!(!state.exceeds_wallet_limit(&whale, 8));
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut state = RaffleState {
+            max_tickets: 5,
+            ..test_state()
+        };
+        state.add_tickets(Pubkey::new_unique(), 2);
+
+        assert_eq!(
+            state.progress(40),
+            RaffleProgress {
+                tickets_sold: 2,
+                tickets_remaining: 3,
+                seconds_remaining: 60,
+                is_over: false,
+                winner_drawn: false,
+            }
+        );
+
+        // Clamped at zero past the end
+        state.winner_index = Some(0);
+        assert_eq!(
+            state.progress(150),
+            RaffleProgress {
+                tickets_sold: 2,
+                tickets_remaining: 3,
+                seconds_remaining: 0,
+                is_over: true,
+                winner_drawn: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_entrants_page() {
+        let tickets: Vec<Pubkey> = (0..50).map(|_| Pubkey::new_unique()).collect();
+        let entrants = entries(&tickets);
+
+        let page = entrants_page(&entrants, 10, 5).unwrap();
+        assert_eq!(page.entrants, entrants[10..15]);
+        assert_eq!(page.total_entrants, 50);
+
+        // Clamped to the end of the list
+        assert_eq!(
+            entrants_page(&entrants, 45, 10).unwrap().entrants,
+            entrants[45..]
+        );
+        // And to the page size
+        assert_eq!(
+            entrants_page(&entrants, 0, u32::MAX)
+                .unwrap()
+                .entrants
+                .len(),
+            MAX_ENTRANTS_PER_PAGE as usize
+        );
+
+        // A start past the end gives an empty page
+        let page = entrants_page(&entrants, 50, 5).unwrap();
+        assert!(page.entrants.is_empty());
+        assert_eq!(page.total_entrants, 50);
+        assert!(entrants_page(&entrants, u32::MAX, u32::MAX)
+            .unwrap()
+            .entrants
+            .is_empty());
+        assert!(entrants_page(&[], 0, 5).unwrap().entrants.is_empty());
+    }
+
+    #[test]
+    fn test_reselect_winner() {
+        let tickets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut state = with_tickets(
+            RaffleState {
+                max_tickets: 10,
+                winner_index: Some(7), // out of range for 3 entrants
+                winner: Some(Pubkey::new_unique()),
+                draw_winner_started: true,
+                randomness: [42; 32],
+                ..test_state()
+            },
+            &tickets,
+        );
+
+        let expected = u32::try_from(select_winner_index(&state.randomness, 3)).unwrap();
+        assert_eq!(state.reselect_winner().unwrap(), Some(expected));
+        assert_eq!(state.winner_index, Some(expected));
+        assert_eq!(state.winner, Some(tickets[expected as usize]));
+        assert!(expected < 3);
+
+        state.entrants.clear();
+        state.tickets_sold = 0;
+        assert_eq!(state.reselect_winner().unwrap(), None);
+        assert_eq!(state.winner, None);
+        assert_eq!(state.winner_index, None);
+    }
+
+    #[test]
+    fn test_draw_winner_entry() {
+        // A callback for a raffle with no tickets fails cleanly, not with a
+        // division by zero
+        let state = RaffleState {
+            randomness: [42; 32],
+            ..test_state()
+        };
+        assert_eq!(
+            state.draw_winner_entry().unwrap_err(),
+            RaffleError::NoEntrants.into()
+        );
+
+        let tickets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut state = with_tickets(state, &tickets);
+        let expected = select_winner_index(&state.randomness, 3);
+        assert_eq!(state.draw_winner_entry().unwrap(), expected);
+
+        // A redraw with only the passed-over winner left has no one to pick
+        state.entrants.truncate(1);
+        state.tickets_sold = 1;
+        state.previous_winner = Some(state.entrants[0].buyer);
+        assert_eq!(
+            state.draw_winner_entry().unwrap_err(),
+            RaffleError::NoOtherEntrants.into()
+        );
+    }
+
+    #[test]
+    fn test_win_probability_bps() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = test_state();
+
+        // Nothing sold yet
+        assert_eq!(state.win_probability_bps(&alice), 0);
+
+        // Bob holds half the tickets, Alice the other half
+        state = with_tickets(state, &[alice, bob, bob, alice]);
+        assert_eq!(state.win_probability_bps(&alice), 5_000);
+        assert_eq!(state.win_probability_bps(&bob), 5_000);
+
+        // Rounded down
+        state.remove_tickets(&alice);
+        state.add_tickets(alice, 1);
+        assert_eq!(state.win_probability_bps(&alice), 3_333);
+        assert_eq!(state.win_probability_bps(&bob), 6_666);
+
+        // A wallet that never entered
+        assert_eq!(state.win_probability_bps(&Pubkey::new_unique()), 0);
+    }
+
+    #[test]
+    fn test_is_winner() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = with_tickets(test_state(), &[alice, bob]);
+
+        // Not drawn yet
+        assert!(!state.is_winner(&alice));
+        assert!(!state.is_winner(&bob));
+
+        state.winner_index = Some(1);
+        state.winner = Some(bob);
+        assert!(!state.is_winner(&alice));
+        assert!(state.is_winner(&bob));
+
+        // The stored winner stands even if the entrants no longer match it
+        state.entrants = entries(&[alice, alice]);
+        assert!(!state.is_winner(&alice));
+        assert!(state.is_winner(&bob));
+    }
+
+    #[test]
+    fn test_select_winner_index() {
+        // The randomness is a fraction of 2^256 scaled to the entrant count
+        let mut randomness = [0; 32];
+        assert_eq!(select_winner_index(&randomness, 7), 0);
+        randomness[31] = 0x80; // one half
+        assert_eq!(select_winner_index(&randomness, 7), 3);
+        assert_eq!(select_winner_index(&randomness, 10), 5);
+        randomness[31] = 0x40; // one quarter
+        assert_eq!(select_winner_index(&randomness, 10), 2);
+        // Just short of a third in the top limb alone, past it once the
+        // carries from the low limbs are added
+        randomness = [0; 32];
+        randomness[24..].copy_from_slice(&(u64::MAX / 3).to_le_bytes());
+        assert_eq!(select_winner_index(&randomness, 3), 0);
+        randomness[..24].fill(0xff);
+        assert_eq!(select_winner_index(&randomness, 3), 1);
+        assert_eq!(select_winner_index(&[0xff; 32], 10), 9);
+
+        for num_entrants in 1..=64 {
+            for byte in 0..=u8::MAX {
+                assert!(select_winner_index(&[byte; 32], num_entrants) < num_entrants);
+            }
+        }
+    }
+
+    #[test]
+    fn test_raffle_randomness() {
+        let randomness = [7; 32];
+        let raffle_a = Pubkey::new_from_array([1; 32]);
+        let raffle_b = Pubkey::new_from_array([2; 32]);
+
+        let seed_a = raffle_randomness(&randomness, &raffle_a);
+        let seed_b = raffle_randomness(&randomness, &raffle_b);
+        assert_eq!(seed_a, raffle_randomness(&randomness, &raffle_a));
+        assert_ne!(seed_a, seed_b);
+        assert_ne!(seed_a, randomness);
+
+        // The same randomness picks a different ticket in each raffle
+        assert_eq!(select_winner_index(&randomness, 1000), 27);
+        assert_eq!(select_winner_index(&seed_a, 1000), 753);
+        assert_eq!(select_winner_index(&seed_b, 1000), 545);
+    }
+
+    #[test]
+    fn test_select_winner_index_uniform() {
+        // 7 doesn't divide any power of two, the worst case for a modulo
+        const NUM_ENTRANTS: usize = 7;
+        const SAMPLES: u32 = 70_000;
+        let mut counts = [0_u32; NUM_ENTRANTS];
+        for sample in 0..SAMPLES {
+            let randomness = hash(&sample.to_le_bytes()).to_bytes();
+            counts[select_winner_index(&randomness, NUM_ENTRANTS)] += 1;
+        }
+
+        // Each index expects 10_000 draws with a standard deviation of about
+        // 93; allow five of them either way
+        let expected = SAMPLES / u32::try_from(NUM_ENTRANTS).unwrap();
+        for count in counts {
+            assert!(count.abs_diff(expected) < 465, "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn test_entry_index_of_ticket() {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        // Alice holds tickets 0-2, Bob ticket 3 and Carol tickets 4-5
+        let entrants = entries(&[alice, alice, bob, alice, carol, carol]);
+        assert_eq!(
+            entrants,
+            vec![
+                EntrantEntry {
+                    buyer: alice,
+                    count: 3
+                },
+                EntrantEntry {
+                    buyer: bob,
+                    count: 1
+                },
+                EntrantEntry {
+                    buyer: carol,
+                    count: 2
+                },
+            ]
+        );
+
+        assert_eq!(entry_index_of_ticket(&entrants, 0), Some(0));
+        assert_eq!(entry_index_of_ticket(&entrants, 2), Some(0));
+        assert_eq!(entry_index_of_ticket(&entrants, 3), Some(1));
+        assert_eq!(entry_index_of_ticket(&entrants, 4), Some(2));
+        assert_eq!(entry_index_of_ticket(&entrants, 5), Some(2));
+        assert_eq!(entry_index_of_ticket(&entrants, 6), None);
+        assert_eq!(entry_index_of_ticket(&[], 0), None);
+
+        // Every ticket is as likely to be drawn, so Alice, holding half, wins most
+        let mut wins = [0; 3];
+        for byte in 0..=u8::MAX {
+            let randomness = hash(&[byte]).to_bytes();
+            wins[select_entry_index(&randomness, &entrants).unwrap()] += 1;
+        }
+        assert!(wins.iter().all(|&count| count > 0));
+        assert!(wins[0] > wins[1] && wins[0] > wins[2]);
+        assert_eq!(select_entry_index(&[1; 32], &[]), None);
+    }
+
+    #[test]
+    fn test_select_runner_up_index() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let entrants = entries(&[alice, alice, bob, alice]);
+
+        for byte in 0..=u8::MAX {
+            let randomness = [byte; 32];
+            let winner_index = select_entry_index(&randomness, &entrants).unwrap();
+            let runner_up_index =
+                select_runner_up_index(&randomness, &entrants, winner_index).unwrap();
+            assert_ne!(entrants[runner_up_index], entrants[winner_index]);
+        }
+
+        // Nobody else to select
+        assert_eq!(
+            select_runner_up_index(&[1; 32], &entries(&[alice; 3]), 0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_consolation_index() {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let entrants = entries(&[alice, bob, alice, carol, bob]);
+
+        let mut differs_from_runner_up = false;
+        for byte in 0..=u8::MAX {
+            let randomness = [byte; 32];
+            let winner_index = select_entry_index(&randomness, &entrants).unwrap();
+            let runner_up_index = select_runner_up_index(&randomness, &entrants, winner_index);
+            let consolation_index =
+                select_consolation_index(&randomness, &entrants, winner_index, None).unwrap();
+            assert_ne!(entrants[consolation_index], entrants[winner_index]);
+            differs_from_runner_up |= Some(consolation_index) != runner_up_index;
+
+            // The runner-up's wallet is excluded too, leaving the third one
+            let consolation_index =
+                select_consolation_index(&randomness, &entrants, winner_index, runner_up_index)
+                    .unwrap();
+            assert_ne!(entrants[consolation_index], entrants[winner_index]);
+            assert_ne!(
+                entrants[consolation_index],
+                entrants[runner_up_index.unwrap()]
+            );
+        }
+        // Drawn independently of the runner-up
+        assert!(differs_from_runner_up);
+
+        // Nobody else to select
+        assert_eq!(
+            select_consolation_index(&[1; 32], &entries(&[alice; 3]), 0, None),
+            None
+        );
+        assert_eq!(
+            select_consolation_index(&[1; 32], &entries(&[alice, bob, alice]), 0, Some(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_promoted_runner_up_is_not_consolation_winner() {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        for byte in 0..=u8::MAX {
+            let mut state = with_tickets(
+                RaffleState {
+                    draw_winner_started: true,
+                    randomness: [byte; 32],
+                    claim_window_secs: 10,
+                    second_chance_prize_bps: 1_000,
+                    ..test_state()
+                },
+                &[alice, bob, carol, bob],
+            );
+            state.reselect_winner().unwrap();
+            let consolation_winner = state.consolation_winner().unwrap();
+
+            // Taking the winner's place doesn't add the second-chance prize
+            state.promote_runner_up();
+            assert_ne!(state.winner, Some(consolation_winner));
+        }
+    }
+
+    #[test]
+    fn test_runner_up_turn() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = with_tickets(
+            RaffleState {
+                draw_winner_started: true,
+                randomness: [7; 32],
+                ..test_state()
+            },
+            &[alice, bob],
+        );
+
+        // Without a claim window there is no runner-up
+        state.reselect_winner().unwrap();
+        assert_eq!(state.runner_up(), None);
+        assert!(!state.is_runner_up_turn(i64::MAX));
+
+        state.claim_window_secs = 10;
+        state.claim_deadline = Some(100);
+        let winner_index = state.reselect_winner().unwrap().unwrap();
+        let winner = state.entrants[winner_index as usize].buyer;
+        let runner_up = state.runner_up().unwrap();
+        assert_ne!(runner_up, winner);
+
+        assert!(!state.is_runner_up_turn(99));
+        assert!(state.is_runner_up_turn(100));
+        assert!(state.is_claim_window_expired(100));
+        // Once the winner started claiming the prize stays theirs
+        state.claimed_bps = 1;
+        assert!(!state.is_runner_up_turn(100));
+
+        state.promote_runner_up();
+        assert_eq!(
+            state.entrants[state.winner_index.unwrap() as usize].buyer,
+            runner_up
+        );
+        assert_eq!(state.winner, Some(runner_up));
+        assert_eq!(state.runner_up_index, None);
+    }
+
+    #[test]
+    fn test_redraw_excludes_previous_and_consolation_winners() {
+        let (alice, bob, carol, dave) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        for byte in 0..=u8::MAX {
+            let mut state = with_tickets(
+                RaffleState {
+                    draw_winner_started: true,
+                    randomness: [byte; 32],
+                    claim_window_secs: 10,
+                    second_chance_prize_bps: 1_000,
+                    ..test_state()
+                },
+                &[alice, bob, carol, dave, alice],
+            );
+            state.reselect_winner().unwrap();
+            let consolation_index = state.consolation_winner_index;
+            let consolation_winner = state.consolation_winner().unwrap();
+
+            // As redraw_winner leaves it for the callback, with new randomness
+            state.previous_winner = state.winner.take();
+            state.randomness = hash(&state.randomness).to_bytes();
+            state.reselect_winner().unwrap();
+            assert_ne!(state.winner, state.previous_winner);
+            assert_ne!(state.winner, Some(consolation_winner));
+            assert_eq!(state.consolation_winner_index, consolation_index);
+            if let Some(runner_up) = state.runner_up() {
+                assert_ne!(Some(runner_up), state.winner);
+                assert_ne!(Some(runner_up), state.previous_winner);
+                assert_ne!(runner_up, consolation_winner);
+            }
+        }
+    }
+
+    #[test]
+    fn test_distribution_account_space() {
+        let distribution = Distribution {
+            raffle_state: Pubkey::new_unique(),
+            total_tickets: 1,
+            top_holders: vec![
+                DistributionBucket {
+                    holder: Pubkey::new_unique(),
+                    tickets: 1,
+                };
+                DISTRIBUTION_TOP_N
+            ],
+            others_wallets: 1,
+            others_tickets: 1,
+        };
+
+        let mut serialized_data = Vec::new();
+        distribution.serialize(&mut serialized_data).unwrap();
+        assert_eq!(serialized_data.len(), Distribution::SPACE);
+    }
+
+    #[test]
+    fn test_purchase_log() {
+        let log = PurchaseLog {
+            raffle_state: Pubkey::new_unique(),
+            purchases: vec![
+                Purchase {
+                    first_index: 0,
+                    slot: 10,
+                },
+                Purchase {
+                    first_index: 2,
+                    slot: 12,
+                },
+                Purchase {
+                    first_index: 5,
+                    slot: 12,
+                },
+            ],
+        };
+
+        let mut serialized_data = Vec::new();
+        log.serialize(&mut serialized_data).unwrap();
+        assert_eq!(serialized_data.len(), PurchaseLog::space(3));
+
+        assert_eq!(log.purchase_of(0).unwrap().first_index, 0);
+        assert_eq!(log.purchase_of(1).unwrap().first_index, 0);
+        assert_eq!(log.purchase_of(2).unwrap().first_index, 2);
+        assert_eq!(log.purchase_of(4).unwrap().slot, 12);
+        assert_eq!(log.purchase_of(9).unwrap().first_index, 5);
+        assert_eq!(PurchaseLog::space(0), 36);
+        assert_eq!(
+            PurchaseLog {
+                raffle_state: Pubkey::new_unique(),
+                purchases: vec![],
+            }
+            .purchase_of(0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_manager_raffle_index_space() {
+        let index = ManagerRaffleIndex {
+            manager: Pubkey::new_unique(),
+            create_cooldown: false,
+            last_created: 0,
+            raffles: vec![Pubkey::new_unique(); 3],
+        };
+
+        let mut serialized_data = Vec::new();
+        index.serialize(&mut serialized_data).unwrap();
+        assert_eq!(serialized_data.len(), ManagerRaffleIndex::space(3));
+        assert_eq!(ManagerRaffleIndex::space(0), 45);
+    }
+
+    #[test]
+    fn test_manager_raffle_index_cooldown() {
+        let mut index = ManagerRaffleIndex {
+            manager: Pubkey::new_unique(),
+            create_cooldown: false,
+            last_created: 100,
+            raffles: vec![],
+        };
+
+        // Opt-in: without a cooldown back-to-back raffles are fine
+        assert!(!index.is_cooling_down(100));
+
+        index.create_cooldown = true;
+        assert!(index.is_cooling_down(100));
+        assert!(index.is_cooling_down(100 + CREATE_COOLDOWN_SECS - 1));
+        assert!(!index.is_cooling_down(100 + CREATE_COOLDOWN_SECS));
+    }
+
+    #[test]
+    fn test_top_ticket_holders() {
+        let (alice, bob, carol, dave) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let state = with_tickets(
+            RaffleState {
+                max_tickets: 8,
+                ..test_state()
+            },
+            &[dave, bob, alice, alice, bob, carol, alice, carol],
+        );
+
+        assert_eq!(
+            state.ticket_counts(),
+            vec![(dave, 1), (bob, 2), (alice, 3), (carol, 2)]
+        );
+        // sold out
+        assert!(state.is_raffle_over(0));
+        // bob and carol tie, bob bought first
+        assert_eq!(
+            state.top_ticket_holders(LOYALTY_BONUS_HOLDERS),
+            vec![(alice, 3), (bob, 2), (carol, 2)]
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let mut state = with_tickets(test_state(), &[Pubkey::new_unique()]);
+
+        assert_eq!(state.status(99), RaffleStatus::Open);
+        assert_eq!(state.status(100), RaffleStatus::Ended);
+        state.add_tickets(Pubkey::new_unique(), 1);
+        assert_eq!(state.status(0), RaffleStatus::Ended);
+        state.draw_winner_started = true;
+        assert_eq!(state.status(0), RaffleStatus::Drawing);
+        state.winner_index = Some(1);
+        assert_eq!(state.status(0), RaffleStatus::WinnerDrawn);
+        state.claimed = true;
+        assert_eq!(state.status(0), RaffleStatus::Claimed);
+        state.refunds_open = true;
+        assert_eq!(state.status(0), RaffleStatus::Refunding);
+        state.refunds_open = false;
+        state.cancelled = true;
+        assert_eq!(state.status(0), RaffleStatus::Refunding);
+    }
+
+    #[test]
+    fn test_is_minimum_missed() {
+        let mut state = with_tickets(
+            RaffleState {
+                max_tickets: 3,
+                min_tickets: 2,
+                ..test_state()
+            },
+            &[Pubkey::new_unique()],
+        );
+
+        // Only decided once the raffle has ended
+        assert!(!state.is_minimum_missed(99));
+        assert!(state.is_minimum_missed(100));
+        assert_eq!(state.status(100), RaffleStatus::Refunding);
+
+        state.add_tickets(Pubkey::new_unique(), 1);
+        assert!(!state.is_minimum_missed(100));
+        assert_eq!(state.status(100), RaffleStatus::Ended);
+    }
+
+    #[test]
+    fn test_unlocked_bps() {
+        let mut state = with_tickets(
+            RaffleState {
+                max_tickets: 1,
+                winner_index: Some(0),
+                winner: Some(Pubkey::new_unique()),
+                draw_winner_started: true,
+                ..test_state()
+            },
+            &[Pubkey::new_unique()],
+        );
+        // No schedule releases everything at once
+        assert_eq!(state.unlocked_bps(0), FULL_BPS);
+
+        state.vesting_schedule = vec![
+            VestingTranche {
+                unlock_time: 300,
+                bps: 5_000,
+            },
+            VestingTranche {
+                unlock_time: 200,
+                bps: 2_500,
+            },
+            VestingTranche {
+                unlock_time: 100,
+                bps: 2_500,
+            },
+        ];
+        assert_eq!(state.unlocked_bps(99), 0);
+        assert_eq!(state.unlocked_bps(100), 2_500);
+        assert_eq!(state.unlocked_bps(250), 5_000);
+        assert_eq!(state.unlocked_bps(300), FULL_BPS);
+    }
+
+    #[test]
+    fn test_price_curve() {
+        assert_eq!(PriceCurve::Flat.total_price(100, 7, 3), Some(300));
+        assert_eq!(PriceCurve::Flat.total_price(u64::MAX, 0, 2), None);
+
+        let curve = PriceCurve::LinearStep { increment: 10 };
+        // Tickets 0, 1 and 2 cost 100, 110 and 120
+        assert_eq!(curve.total_price(100, 0, 3), Some(330));
+        // Tickets 5 and 6 cost 150 and 160
+        assert_eq!(curve.total_price(100, 5, 2), Some(310));
+        assert_eq!(curve.total_price(100, 5, 0), Some(0));
+        // Buying one at a time adds up to the same as a batch
+        let one_by_one: u64 = (0..4)
+            .map(|sold| curve.total_price(100, sold, 1).unwrap())
+            .sum();
+        assert_eq!(Some(one_by_one), curve.total_price(100, 0, 4));
+
+        let steep = PriceCurve::LinearStep {
+            increment: u64::MAX,
+        };
+        assert_eq!(steep.total_price(1, 0, 1), Some(1));
+        assert_eq!(steep.total_price(1, 0, 2), None);
+    }
+
+    #[test]
+    fn test_purchase_price() {
+        let mut state = RaffleState {
+            ticket_price: 100,
+            max_tickets: 4,
+            total_collected: 200,
+            ..test_state()
+        };
+        state.add_tickets(Pubkey::new_unique(), 2);
+        assert_eq!(state.purchase_price(2).unwrap(), 200);
+        state.price_curve = PriceCurve::LinearStep { increment: 10 };
+        // Tickets 2 and 3 cost 120 and 130
+        assert_eq!(state.purchase_price(2).unwrap(), 250);
+
+        // A state priced past u64 fails cleanly rather than panicking
+        state.ticket_price = u64::MAX;
+        assert_eq!(
+            state.purchase_price(2).unwrap_err(),
+            RaffleError::PriceOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_purchase_price_in() {
+        let mut state = RaffleState {
+            ticket_price: 100,
+            max_tickets: 4,
+            payment_mint: Some(Pubkey::new_unique()),
+            accepted_mints: vec![
+                AcceptedMint {
+                    mint: Pubkey::new_unique(),
+                    ticket_price: 7,
+                },
+                AcceptedMint {
+                    mint: Pubkey::new_unique(),
+                    ticket_price: 3,
+                },
+            ],
+            ..test_state()
+        };
+        state.add_tickets(Pubkey::new_unique(), 1);
+
+        // Each mint has its own price; no index pays in the payment mint
+        assert_eq!(state.purchase_price_in(None, 2).unwrap(), 200);
+        assert_eq!(state.purchase_price_in(Some(0), 2).unwrap(), 14);
+        assert_eq!(state.purchase_price_in(Some(1), 3).unwrap(), 9);
+        assert_eq!(
+            state.purchase_price_in(Some(2), 1).unwrap_err(),
+            RaffleError::InvalidMintIndex.into()
+        );
+
+        state.accepted_mints[1].ticket_price = u64::MAX;
+        assert_eq!(
+            state.purchase_price_in(Some(1), 2).unwrap_err(),
+            RaffleError::PriceOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_prize_amount() {
+        let mut state = RaffleState {
+            ticket_price: 100,
+            winner_index: Some(0),
+            winner: Some(Pubkey::new_unique()),
+            draw_winner_started: true,
+            total_collected: 200,
+            keeper_bounty: 10,
+            operator_withdrawn: 20,
+            ..test_state()
+        };
+        state.add_tickets(Pubkey::new_unique(), 2);
+        assert_eq!(state.prize_amount().unwrap(), 170);
+
+        // A fixed prize replaces the revenue, which is left to the manager
+        state.revenue_mode = RevenueMode::ManagerKeepsRevenue;
+        state.fixed_prize_lamports = 500;
+        assert_eq!(state.prize_amount().unwrap(), 500);
+        assert_eq!(state.ticket_revenue().unwrap(), 170);
+        state.revenue_mode = RevenueMode::WinnerTakesPool;
+        state.fixed_prize_lamports = 0;
+
+        // No second-chance prize until a consolation winner is drawn
+        state.second_chance_prize_bps = 1_000;
+        assert_eq!(state.consolation_amount().unwrap(), 0);
+        state.consolation_winner_index = Some(1);
+        assert_eq!(state.consolation_amount().unwrap(), 17);
+        assert!(state.is_consolation_pending(0));
+        // Until the claim window lapses
+        state.claim_deadline = Some(100);
+        assert!(state.is_consolation_pending(99));
+        assert!(!state.is_consolation_pending(100));
+        state.claim_deadline = None;
+        state.consolation_claimed = true;
+        assert!(!state.is_consolation_pending(0));
+
+        // The manager's fee is a share of the same pool
+        assert_eq!(state.fee_amount().unwrap(), 0);
+        state.fee_bps = 500;
+        assert_eq!(state.fee_amount().unwrap(), 8);
+
+        // More paid out than was ever collected
+        state.total_collected = 29;
+        assert_eq!(
+            state.prize_amount().unwrap_err(),
+            RaffleError::PrizeOverflow.into()
+        );
     }
 }
@@ -1,14 +1,37 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorProvider, BN, Program } from "@coral-xyz/anchor";
-import { Connection, PublicKey, ConfirmOptions } from "@solana/web3.js";
+import { Connection, Keypair, PublicKey, ConfirmOptions, SystemProgram } from "@solana/web3.js";
 import {
   createFundedWallet,
   recoverFunds,
   solToLamports,
   assertAnchorError,
+  sleep,
 } from "./utils/test_utils";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  associatedTokenAddress,
+  closeTokenAccount,
+  createMint,
+  mintToOwner,
+  tokenBalance,
+} from "./utils/spl_token";
 import { Raffle } from "../target/types/raffle";
-import { RaffleTestHelper, RaffleState } from "./utils/raffle_helper";
+import {
+  DISTRIBUTION_TOP_N,
+  FULL_BPS,
+  MAX_ENTRANTS_PER_PAGE,
+  MAX_TICKETS_PER_PURCHASE,
+  MIN_LEAD_SECS,
+  SLOT_HASHES_MAX_ENTRIES,
+  RaffleTestHelper,
+  RaffleState,
+  RaffleStatus,
+  acceptedMintTicketsOf,
+  allowlistLeaf,
+  allowlistNode,
+} from "./utils/raffle_helper";
 import { assert } from "chai";
 
 // Due to the VRF oracle requirements, the tests must be run on devnet
@@ -53,14 +76,1524 @@ describe("raffle", () => {
     // Get the winner to claim the prize
     const raffleStateAfterDraw = await raffle.getState(pda);
     assert.isNotNull(raffleStateAfterDraw.winnerIndex);
-    const winnerPubkey = raffleStateAfterDraw.entrants[raffleStateAfterDraw.winnerIndex];
+    const winnerPubkey = raffleStateAfterDraw.entrants[raffleStateAfterDraw.winnerIndex].buyer;
+    // The resolved winner is stored, and only that wallet may claim
+    assert.isTrue(raffleStateAfterDraw.winner.equals(winnerPubkey));
+    const loser = winnerPubkey.equals(alice.publicKey) ? bob.publicKey : alice.publicKey;
+    await assertAnchorError(() => raffle.claimPrize(pda, loser), "NotWinner");
 
     await raffle.claimPrize(pda, winnerPubkey);
     await raffle.close(pda, raffleManager);
 
-    await recoverFunds(provider, raffleManager);
-    await recoverFunds(provider, alice);
-    await recoverFunds(provider, bob);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Claiming the prize emits the ticket revenue paid", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.drawWinner(pda);
+
+    const drawn = await raffle.getState(pda);
+    // claimPrize() checks PrizeClaimedEvent against the expected amount
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer, ticketPrice.muln(3));
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Raffle account stays rent exempt after the prize is claimed", async () => {
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 2);
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+
+    // The whole pool was paid out, leaving exactly the rent until close
+    const accountInfo = await connection.getAccountInfo(pda, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    assert.strictEqual(accountInfo.lamports, rent);
+
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("Raffle account grows with each new entrant up to max tickets", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Created without room for any tickets
+    const created = await connection.getAccountInfo(pda, "confirmed");
+    const emptyLen = created.data.length;
+
+    // A new entrant grows the account by one entry however many tickets it
+    // buys, a returning one not at all, with the buyer paying any extra rent
+    // on top of the tickets
+    for (const [numTickets, growth] of [
+      [2, 36],
+      [1, 0],
+    ]) {
+      const before = await connection.getAccountInfo(pda, "confirmed");
+      await raffle.buyTickets(pda, alice, numTickets);
+      const after = await connection.getAccountInfo(pda, "confirmed");
+      assert.strictEqual(after.data.length, before.data.length + growth);
+      const extraRent =
+        (await connection.getMinimumBalanceForRentExemption(after.data.length)) -
+        (await connection.getMinimumBalanceForRentExemption(before.data.length));
+      assert.strictEqual(
+        after.lamports - before.lamports,
+        ticketPrice.muln(numTickets).toNumber() + extraRent
+      );
+    }
+
+    // Sold out at exactly max_tickets; no further growth
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 1), "SoldOut");
+    const full = await connection.getAccountInfo(pda, "confirmed");
+    assert.strictEqual(full.data.length, emptyLen + 36);
+
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, alice.publicKey);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Loyalty bonus split among top holders", async () => {
+    const alice = await createFundedWallet(provider, 0.1);
+    const bob = await createFundedWallet(provider, 0.1);
+    const carol = await createFundedWallet(provider, 0.1);
+    const dave = await createFundedWallet(provider, 0.1);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 10, 120, {
+      loyaltyBonusBps: 2000,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 4);
+    await raffle.buyTickets(pda, bob, 3);
+    await raffle.buyTickets(pda, carol, 2);
+    await raffle.buyTickets(pda, dave, 1);
+
+    const drawnState = await raffle.drawWinner(pda);
+    assert.isNotNull(drawnState.winnerIndex);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+
+    // 20% of the pool is split 4:3:2 among alice, bob and carol. Dave is
+    // outside the top 3 holders, and the winner gets everything else.
+    const pool = ticketPrice.muln(10);
+    const bonusPool = pool.muln(2000).divn(10000);
+    const expected = new Map<string, BN>();
+    let bonusPaid = new BN(0);
+    const holders: [Keypair, number][] = [
+      [alice, 4],
+      [bob, 3],
+      [carol, 2],
+    ];
+    for (const [holder, count] of holders) {
+      const share = bonusPool.muln(count).divn(9);
+      expected.set(holder.publicKey.toBase58(), share);
+      bonusPaid = bonusPaid.add(share);
+    }
+    const winnerShare = (expected.get(winner.toBase58()) ?? new BN(0)).add(pool.sub(bonusPaid));
+    expected.set(winner.toBase58(), winnerShare);
+
+    const buyers = [alice, bob, carol, dave];
+    const balancesBefore = await Promise.all(
+      buyers.map((b) => connection.getBalance(b.publicKey, "confirmed"))
+    );
+    await raffle.claimPrize(pda, winner);
+    for (let i = 0; i < buyers.length; i++) {
+      const balanceAfter = await connection.getBalance(buyers[i].publicKey, "confirmed");
+      const share = expected.get(buyers[i].publicKey.toBase58()) ?? new BN(0);
+      assert.strictEqual(balanceAfter - balancesBefore[i], share.toNumber());
+    }
+
+    await raffle.close(pda, walletPayer);
+    for (const buyer of buyers) {
+      await recoverFunds(provider, buyer);
+    }
+  });
+
+  it("Snapshot ticket distribution", async () => {
+    const wallets: Keypair[] = [];
+    for (let i = 0; i < 6; i++) {
+      wallets.push(await createFundedWallet(provider, 0.01));
+    }
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 9, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Snapshots are only allowed once the raffle is over
+    await assertAnchorError(() => raffle.snapshotDistribution(pda, walletPayer), "RaffleNotOver");
+
+    const ticketsPerWallet = [3, 2, 1, 1, 1, 1];
+    for (let i = 0; i < wallets.length; i++) {
+      await raffle.buyTickets(pda, wallets[i], ticketsPerWallet[i]);
+    }
+
+    const distribution = await raffle.snapshotDistribution(pda, walletPayer);
+    assert.strictEqual(distribution.totalTickets, 9);
+    assert.strictEqual(distribution.topHolders.length, DISTRIBUTION_TOP_N);
+    for (let i = 0; i < DISTRIBUTION_TOP_N; i++) {
+      assert.isTrue(distribution.topHolders[i].holder.equals(wallets[i].publicKey));
+      assert.strictEqual(distribution.topHolders[i].tickets, ticketsPerWallet[i]);
+    }
+    // The 6th wallet falls into the "others" bucket
+    assert.strictEqual(distribution.othersWallets, 1);
+    assert.strictEqual(distribution.othersTickets, 1);
+
+    const drawnState = await raffle.drawWinner(pda);
+    assert.isNotNull(drawnState.winnerIndex);
+    await raffle.claimPrize(pda, drawnState.entrants[drawnState.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    for (const wallet of wallets) {
+      await recoverFunds(provider, wallet);
+    }
+  });
+
+  it("Prize vests tranche by tranche", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const now = Math.floor(Date.now() / 1000);
+    const vestingSchedule = [
+      { unlockTime: new BN(now - 60), bps: 4_000 },
+      { unlockTime: new BN(now + 20), bps: 6_000 },
+    ];
+    const state = await raffle.create(walletPayer, ticketPrice, 1, 120, { vestingSchedule });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.drawWinner(pda);
+
+    // The first tranche is already unlocked
+    const balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    let claimState = await raffle.claimPrize(pda, alice.publicKey);
+    assert.strictEqual(claimState.claimedBps, 4_000);
+    const firstTranche = ticketPrice.muln(4_000).divn(FULL_BPS).toNumber();
+    let balance = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balance - balanceBefore, firstTranche);
+
+    // Nothing more to claim until the second tranche unlocks, and the
+    // partially paid raffle can't be closed
+    await assertAnchorError(() => raffle.claimPrize(pda, alice.publicKey), "NoTrancheUnlocked");
+    await assertAnchorError(() => raffle.close(pda, walletPayer), "CanNotCloseActiveRaffle");
+
+    await sleep(30_000);
+    claimState = await raffle.claimPrize(pda, alice.publicKey);
+    assert.isTrue(claimState.claimed);
+    balance = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balance - balanceBefore, ticketPrice.toNumber());
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Runner-up claims once the winner's claim window lapses", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const claimWindowSecs = 15;
+    const state = await raffle.create(walletPayer, ticketPrice, 2, 120, { claimWindowSecs });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, bob, 1);
+    const drawnState = await raffle.drawWinner(pda);
+
+    // With one ticket each, whoever didn't win is the runner-up
+    assert.isNotNull(drawnState.runnerUpIndex);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    const runnerUp = drawnState.entrants[drawnState.runnerUpIndex].buyer;
+    assert.isFalse(winner.equals(runnerUp));
+
+    await assertAnchorError(() => raffle.claimPrize(pda, runnerUp), "ClaimWindowNotOver");
+
+    await sleep((claimWindowSecs + 5) * 1000);
+    const balanceBefore = await connection.getBalance(runnerUp, "confirmed");
+    const claimState = await raffle.claimPrize(pda, runnerUp);
+    assert.isTrue(claimState.claimed);
+    const balance = await connection.getBalance(runnerUp, "confirmed");
+    assert.strictEqual(balance - balanceBefore, ticketPrice.muln(2).toNumber());
+
+    // The runner-up took the winner's place
+    await assertAnchorError(() => raffle.claimPrize(pda, winner), "NotWinner");
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Winner who lets the claim window lapse is redrawn", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const carol = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const claimWindowSecs = 15;
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, { claimWindowSecs });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, bob, 1);
+    await raffle.buyTickets(pda, carol, 1);
+    const drawnState = await raffle.drawWinner(pda);
+    const previousWinner = drawnState.winner;
+
+    await assertAnchorError(() => raffle.redrawWinner(pda), "ClaimWindowNotOver");
+
+    await sleep((claimWindowSecs + 5) * 1000);
+    await assertAnchorError(() => raffle.claimPrize(pda, previousWinner), "ClaimWindowExpired");
+
+    // redrawWinner checks the new winner is another wallet
+    const redrawnState = await raffle.redrawWinner(pda);
+    await assertAnchorError(() => raffle.claimPrize(pda, previousWinner), "NotWinner");
+
+    // The new winner claims within their own claim window
+    const balanceBefore = await connection.getBalance(redrawnState.winner, "confirmed");
+    await raffle.claimPrize(pda, redrawnState.winner);
+    const balance = await connection.getBalance(redrawnState.winner, "confirmed");
+    assert.strictEqual(balance - balanceBefore, ticketPrice.muln(3).toNumber());
+    await assertAnchorError(() => raffle.redrawWinner(pda), "PrizeAlreadyClaimed");
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+    await recoverFunds(provider, carol);
+  });
+
+  it("Winner odds are reported with the draw", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 4, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Alice had a 3 in 4 chance, Bob 1 in 4; drawWinner checks the
+    // WinnerDrawnEvent's odds against whichever of them won
+    await raffle.buyTickets(pda, alice, 3);
+    await raffle.buyTickets(pda, bob, 1);
+    const drawnState = await raffle.drawWinner(pda);
+
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    await raffle.claimPrize(pda, winner);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Consolation winner claims the second-chance prize", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const carol = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const secondChancePrizeBps = 2_000;
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, {
+      secondChancePrizeBps,
+      claimWindowSecs: 120,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, bob, 1);
+    await raffle.buyTickets(pda, carol, 1);
+    // drawWinner checks the consolation winner holds another wallet's ticket
+    const drawnState = await raffle.drawWinner(pda);
+    assert.isNotNull(drawnState.consolationWinnerIndex);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    const consolationWinner = drawnState.entrants[drawnState.consolationWinnerIndex].buyer;
+
+    const pool = ticketPrice.muln(3);
+    const consolationPrize = pool.muln(secondChancePrizeBps).divn(FULL_BPS);
+    await raffle.claimPrize(pda, winner, pool.sub(consolationPrize));
+
+    // The second-chance prize is still owed, so the raffle can't be closed
+    await assertAnchorError(() => raffle.close(pda, walletPayer), "CanNotCloseActiveRaffle");
+    await assertAnchorError(() => raffle.claimConsolation(pda, winner), "NotConsolationWinner");
+
+    const balanceBefore = await connection.getBalance(consolationWinner, "confirmed");
+    await raffle.claimConsolation(pda, consolationWinner);
+    const balance = await connection.getBalance(consolationWinner, "confirmed");
+    assert.strictEqual(balance - balanceBefore, consolationPrize.toNumber());
+    await assertAnchorError(
+      () => raffle.claimConsolation(pda, consolationWinner),
+      "ConsolationAlreadyClaimed"
+    );
+
+    // Both prizes together paid out the whole pool
+    const accountInfo = await connection.getAccountInfo(pda, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    assert.strictEqual(accountInfo.lamports, rent);
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+    await recoverFunds(provider, carol);
+  });
+
+  it("Unclaimed second-chance prize goes to the manager once the claim window lapses", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const carol = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const secondChancePrizeBps = 2_000;
+    const claimWindowSecs = 15;
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, {
+      secondChancePrizeBps,
+      claimWindowSecs,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, bob, 1);
+    await raffle.buyTickets(pda, carol, 1);
+    const drawnState = await raffle.drawWinner(pda);
+    assert.isNotNull(drawnState.consolationWinnerIndex);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    const consolationWinner = drawnState.entrants[drawnState.consolationWinnerIndex].buyer;
+
+    const pool = ticketPrice.muln(3);
+    const consolationPrize = pool.muln(secondChancePrizeBps).divn(FULL_BPS);
+    await raffle.claimPrize(pda, winner, pool.sub(consolationPrize));
+    await assertAnchorError(() => raffle.close(pda, walletPayer), "CanNotCloseActiveRaffle");
+
+    // The consolation winner never claims, so once the window lapses the
+    // share stays in the raffle account and the raffle can be closed
+    await sleep((claimWindowSecs + 5) * 1000);
+    await assertAnchorError(
+      () => raffle.claimConsolation(pda, consolationWinner),
+      "ConsolationClaimWindowExpired"
+    );
+
+    const accountInfo = await connection.getAccountInfo(pda, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    const event = await raffle.close(pda, walletPayer);
+    assert.strictEqual(event.refundedLamports.toNumber(), rent + consolationPrize.toNumber());
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+    await recoverFunds(provider, carol);
+  });
+
+  it("Free-entry giveaway admits each wallet once without payment", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, new BN(0), 5, 10, { freeEntry: true });
+    const pda = raffle.state2Pda(state);
+    const lamportsBefore = await connection.getBalance(pda, "confirmed");
+
+    await raffle.buyTickets(pda, alice, 1);
+    const entered = await raffle.buyTickets(pda, bob, 1);
+
+    // Entering moved nothing into the raffle account
+    assert.isTrue(entered.totalCollected.isZero());
+    assert.strictEqual(await connection.getBalance(pda, "confirmed"), lamportsBefore);
+
+    // One entry per wallet, and each entrant signs for their own
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 1), "WalletTicketLimitExceeded");
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, walletPayer, 1, alice.publicKey),
+      "FreeEntryNotGiftable"
+    );
+
+    await sleep(12 * 1000);
+    const drawn = await raffle.drawWinner(pda);
+    // The zero prize is just marked claimed
+    const claimed = await raffle.claimPrize(pda, drawn.winner, new BN(0));
+    assert.isTrue(claimed.claimed);
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Gifted tickets enter the recipient", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Alice pays, Bob is entered
+    const aliceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    const bobBefore = await connection.getBalance(bob.publicKey, "confirmed");
+    await raffle.buyTickets(pda, alice, 2, bob.publicKey);
+    const aliceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.isAtLeast(aliceBefore - aliceAfter, ticketPrice.muln(2).toNumber());
+    assert.strictEqual(await connection.getBalance(bob.publicKey, "confirmed"), bobBefore);
+
+    // Bob holds every ticket, so is guaranteed to win and can claim
+    const drawn = await raffle.drawWinner(pda);
+    assert.isTrue(drawn.entrants[drawn.winnerIndex].buyer.equals(bob.publicKey));
+    await raffle.claimPrize(pda, bob.publicKey);
+    const bobAfter = await connection.getBalance(bob.publicKey, "confirmed");
+    assert.strictEqual(bobAfter - bobBefore, ticketPrice.muln(2).toNumber());
+
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Manager barred from entering can't buy or be gifted tickets", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 2, 120, {
+      managerCannotEnter: true,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await assertAnchorError(() => raffle.buyTickets(pda, raffleManager, 1), "ManagerCannotEnter");
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, raffleManager, 1, alice.publicKey),
+      "ManagerCannotEnter"
+    );
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, alice, 1, raffleManager.publicKey),
+      "ManagerCannotEnter"
+    );
+
+    // Everyone else still buys as usual
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, alice.publicKey);
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Manager may enter a raffle that doesn't bar it", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const state = await raffle.create(raffleManager, solToLamports(0.0001), 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, raffleManager, 2);
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, raffleManager.publicKey);
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+  });
+
+  it("Keeper bounty is paid to the draw caller", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const keeperBounty = ticketPrice.divn(2);
+    const state = await raffle.create(walletPayer, ticketPrice, 2, 120, { keeperBounty });
+    const pda = raffle.state2Pda(state);
+
+    // Alice holds every ticket, so she is guaranteed to win
+    await raffle.buyTickets(pda, alice, 2);
+
+    // The bounty leaves the pool when the draw is triggered. The keeper's own
+    // balance also pays the transaction and VRF fees, so check the pool.
+    const poolBefore = await connection.getBalance(pda, "confirmed");
+    await raffle.drawWinner(pda);
+    const poolAfter = await connection.getBalance(pda, "confirmed");
+    assert.strictEqual(poolBefore - poolAfter, keeperBounty.toNumber());
+
+    // The winner's prize is reduced by the bounty
+    const balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimPrize(pda, alice.publicKey);
+    const balanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(
+      balanceAfter - balanceBefore,
+      ticketPrice.muln(2).sub(keeperBounty).toNumber()
+    );
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Slot-hashes raffle draws without the VRF", async () => {
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+      randomnessSource: { slotHashes: {} },
+    });
+    const pda = raffle.state2Pda(state);
+    await raffle.buyTickets(pda, walletPayer, 2);
+
+    // The VRF draw is for the other randomness source
+    await assertAnchorError(() => raffle.drawWinner(pda), "WrongRandomnessSource");
+
+    // The randomness can only be read from the real SlotHashes sysvar
+    await assertAnchorError(
+      () =>
+        program.methods
+          .drawWinnerFromSlotHashes()
+          .accounts({
+            caller: provider.wallet.publicKey,
+            // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+            raffleState: pda,
+            slotHashes: PublicKey.unique(),
+          })
+          .rpc({ commitment: "confirmed" }),
+      "ConstraintAddress",
+      "slot_hashes"
+    );
+
+    // The first call commits to a slot that hasn't been produced yet
+    const committed = await raffle.drawWinnerFromSlotHashes(pda);
+    await assertAnchorError(() => raffle.drawWinnerFromSlotHashes(pda), "CommitSlotNotReached");
+
+    // Once it has, the next call draws the winner from its hash
+    while ((await connection.getSlot("confirmed")) <= committed.commitSlot.toNumber()) {
+      await sleep(400);
+    }
+    const drawn = await raffle.drawWinnerFromSlotHashes(pda);
+    assert.isTrue(drawn.commitSlot.eq(committed.commitSlot));
+    assert.isNotNull(drawn.winnerIndex);
+
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("Last purchase of an auto-draw raffle commits the slot-hashes draw", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+      randomnessSource: { slotHashes: {} },
+      autoDraw: true,
+    });
+    const pda = raffle.state2Pda(state);
+
+    const partial = await raffle.buyTickets(pda, alice, 1);
+    assert.isFalse(partial.drawWinnerStarted);
+    assert.isNull(partial.commitSlot);
+
+    // The last ticket starts the draw, so a keeper only has to finish it
+    const soldOut = await raffle.buyTickets(pda, alice, 1);
+    assert.isTrue(soldOut.drawWinnerStarted);
+    assert.isNotNull(soldOut.commitSlot);
+
+    while ((await connection.getSlot("confirmed")) <= soldOut.commitSlot.toNumber()) {
+      await sleep(400);
+    }
+    const drawn = await raffle.drawWinnerFromSlotHashes(pda);
+    assert.isTrue(drawn.winner.equals(alice.publicKey));
+
+    await raffle.claimPrize(pda, alice.publicKey);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Slot-hashes raffle recommits once its slot expired", async () => {
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+      randomnessSource: { slotHashes: {} },
+    });
+    const pda = raffle.state2Pda(state);
+    await raffle.buyTickets(pda, walletPayer, 2);
+
+    // Nobody draws until the committed slot falls out of the sysvar
+    const committed = await raffle.drawWinnerFromSlotHashes(pda);
+    const expiredAt = committed.commitSlot.toNumber() + SLOT_HASHES_MAX_ENTRIES;
+    while ((await connection.getSlot("confirmed")) <= expiredAt) {
+      await sleep(10 * 1000);
+    }
+
+    // The stale commitment is replaced rather than stranding the raffle
+    const refreshed = await raffle.drawWinnerFromSlotHashes(pda);
+    assert.isTrue(refreshed.commitSlot.gt(committed.commitSlot));
+
+    // And the draw picks up the new one
+    while ((await connection.getSlot("confirmed")) <= refreshed.commitSlot.toNumber()) {
+      await sleep(400);
+    }
+    const drawn = await raffle.drawWinnerFromSlotHashes(pda);
+    assert.isTrue(drawn.commitSlot.eq(refreshed.commitSlot));
+    assert.isNotNull(drawn.winnerIndex);
+
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("Manager withdraws operator funds within the cap", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 4, 120, {
+      operatorWithdrawalCapBps: 2_500,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 4);
+
+    // A quarter of the 4 tickets collected so far
+    const cap = ticketPrice;
+    await raffle.withdrawOperatorFunds(pda, raffleManager, cap.divn(2));
+    await assertAnchorError(
+      () => raffle.withdrawOperatorFunds(pda, raffleManager, cap.divn(2).addn(1)),
+      "OperatorWithdrawalCapExceeded"
+    );
+    await assertAnchorError(
+      () => raffle.withdrawOperatorFunds(pda, alice, new BN(1)),
+      "OnlyRaffleManagerCanWithdraw"
+    );
+    await raffle.withdrawOperatorFunds(pda, raffleManager, cap.divn(2));
+
+    // The prize is what's left of the pool
+    await raffle.drawWinner(pda);
+    await assertAnchorError(
+      () => raffle.withdrawOperatorFunds(pda, raffleManager, new BN(1)),
+      "DrawAlreadyStarted"
+    );
+    const balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimPrize(pda, alice.publicKey);
+    const balanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balanceAfter - balanceBefore, ticketPrice.muln(4).sub(cap).toNumber());
+
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Manager keeps the revenue of a fixed-prize raffle", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const fixedPrizeLamports = solToLamports(0.001);
+    const state = await raffle.create(raffleManager, ticketPrice, 2, 120, { fixedPrizeLamports });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await assertAnchorError(() => raffle.withdrawRevenue(pda, raffleManager), "WinnerNotYetDrawn");
+    await raffle.buyTickets(pda, bob, 1);
+    const drawn = await raffle.drawWinner(pda);
+
+    await assertAnchorError(
+      () => raffle.withdrawRevenue(pda, alice),
+      "OnlyRaffleManagerCanWithdraw"
+    );
+    // withdrawRevenue checks the 2 ticket payments were withdrawn
+    await raffle.withdrawRevenue(pda, raffleManager);
+    await assertAnchorError(
+      () => raffle.withdrawRevenue(pda, raffleManager),
+      "RevenueAlreadyWithdrawn"
+    );
+
+    // The winner is paid the fixed prize, which leaves only the rent
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer, fixedPrizeLamports);
+    const accountInfo = await connection.getAccountInfo(pda, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    assert.strictEqual(accountInfo.lamports, rent);
+
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Winner-takes-pool raffle has no revenue for the manager", async () => {
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 2);
+    await raffle.drawWinner(pda);
+    await assertAnchorError(
+      () => raffle.withdrawRevenue(pda, walletPayer),
+      "RevenueNotWithdrawable"
+    );
+
+    await raffle.claimPrize(pda, walletPayer.publicKey, ticketPrice.muln(2));
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("Refund bond tops up refunds and returns to the manager", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const refundBond = ticketPrice.divn(2);
+
+    // Cancelled: the minimum is missed after the manager withdrew half the pool
+    const cancelled = raffle.state2Pda(
+      await raffle.create(raffleManager, ticketPrice, 4, 10, {
+        minTickets: 3,
+        operatorWithdrawalCapBps: 5_000,
+        refundBond,
+      })
+    );
+    await raffle.buyTickets(cancelled, alice, 1);
+    await raffle.buyTickets(cancelled, bob, 1);
+    await raffle.withdrawOperatorFunds(cancelled, raffleManager, ticketPrice);
+    await sleep(12 * 1000);
+
+    // Alice's share of the withdrawal is covered by the bond in full...
+    let balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    let refundState = await raffle.claimRefund(cancelled, alice);
+    let balanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balanceAfter - balanceBefore, ticketPrice.toNumber());
+    assert.isTrue(refundState.refundBond.isZero());
+
+    // ...which leaves nothing to cover Bob's
+    balanceBefore = await connection.getBalance(bob.publicKey, "confirmed");
+    refundState = await raffle.claimRefund(cancelled, bob);
+    balanceAfter = await connection.getBalance(bob.publicKey, "confirmed");
+    assert.strictEqual(balanceAfter - balanceBefore, ticketPrice.divn(2).toNumber());
+    await raffle.close(cancelled, raffleManager);
+
+    // Settled normally: the whole bond comes back with the rent
+    const settled = raffle.state2Pda(
+      await raffle.create(raffleManager, ticketPrice, 1, 120, { refundBond })
+    );
+    await raffle.buyTickets(settled, alice, 1);
+    await raffle.drawWinner(settled);
+    await raffle.claimPrize(settled, alice.publicKey);
+    const accountInfo = await connection.getAccountInfo(settled, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    const event = await raffle.close(settled, raffleManager);
+    assert.strictEqual(event.refundedLamports.toNumber(), rent + refundBond.toNumber());
+
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Refunds stay closed during the grace period after an undrawn raffle expires", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 3, 10);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.buyTickets(pda, bob, 1);
+
+    // Nobody draws on time, but the day-long grace period keeps refunds
+    // closed well past the end time
+    await sleep(12 * 1000);
+    await assertAnchorError(
+      () => raffle.openRefundsAfterExpiry(pda),
+      "RefundGracePeriodNotOver"
+    );
+    await assertAnchorError(() => raffle.claimRefund(pda, alice), "RefundsNotOpen");
+
+    // A late draw still goes ahead
+    await raffle.drawWinner(pda);
+    await assertAnchorError(() => raffle.openRefundsAfterExpiry(pda), "DrawAlreadyStarted");
+
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Cancelled raffle stops sales and refunds entrants", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 5, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 2);
+
+    await assertAnchorError(() => raffle.cancel(pda, alice), "OnlyRaffleManagerCanCancel");
+    await raffle.cancel(pda, raffleManager);
+    assert.deepEqual(await raffle.getStatusesBatch([pda]), [RaffleStatus.Refunding]);
+
+    await assertAnchorError(() => raffle.cancel(pda, raffleManager), "RaffleCancelled");
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 1), "RaffleCancelled");
+    await assertAnchorError(() => raffle.drawWinner(pda), "RaffleCancelled");
+
+    // Alice's refund is still pending, so the raffle can't be closed yet
+    await assertAnchorError(() => raffle.close(pda, raffleManager), "CanNotCloseActiveRaffle");
+
+    const balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimRefund(pda, alice);
+    const balanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balanceAfter - balanceBefore, ticketPrice.muln(2).toNumber());
+
+    // Refunds remove the entrant's tickets, so once everyone is refunded the
+    // raffle is empty and can be closed
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Paused raffle blocks sales until unpaused", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(raffleManager, solToLamports(0.0001), 3, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+
+    await assertAnchorError(() => raffle.setPaused(pda, alice, true), "OnlyRaffleManagerCanPause");
+    await assertAnchorError(() => raffle.setPaused(pda, raffleManager, false), "RaffleNotPaused");
+    await raffle.setPaused(pda, raffleManager, true);
+    await assertAnchorError(() => raffle.setPaused(pda, raffleManager, true), "RafflePaused");
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 1), "RafflePaused");
+
+    await raffle.setPaused(pda, raffleManager, false);
+    await raffle.buyTickets(pda, alice, 2);
+
+    // Sold out, so the raffle finalizes as usual
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, alice.publicKey);
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Manager extends the end time of an underselling raffle", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(raffleManager, solToLamports(0.0001), 5, 120, {
+      minTickets: 2,
+    });
+    const pda = raffle.state2Pda(state);
+
+    // The raffle keeps its address, which is derived from the original end time
+    const newEndTime = state.endTime.addn(600);
+    await assertAnchorError(
+      () => raffle.extendEndTime(pda, alice, newEndTime),
+      "OnlyRaffleManagerCanExtend"
+    );
+    await assertAnchorError(
+      () => raffle.extendEndTime(pda, raffleManager, state.endTime),
+      "EndTimeNotExtended"
+    );
+    const extended = await raffle.extendEndTime(pda, raffleManager, newEndTime);
+    assert.isTrue(raffle.state2Pda(extended).equals(pda));
+
+    // Still capped at 30 days from creation
+    const pastCap = extended.createdAt.addn(30 * 24 * 60 * 60 + 1);
+    await assertAnchorError(
+      () => raffle.extendEndTime(pda, raffleManager, pastCap),
+      "MaxRaffleLengthExceeded"
+    );
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.cancel(pda, raffleManager);
+    await raffle.claimRefund(pda, alice);
+    await raffle.close(pda, raffleManager);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Manager hands the raffle to a new manager", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const newManager = await createFundedWallet(provider, 0.01);
+    const mallory = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(raffleManager, solToLamports(0.0001), 5, 120);
+    const pda = raffle.state2Pda(state);
+
+    await assertAnchorError(
+      () => raffle.transferManager(pda, mallory, mallory.publicKey),
+      "OnlyRaffleManagerCanTransfer"
+    );
+    await raffle.transferManager(pda, raffleManager, newManager.publicKey);
+    await assertAnchorError(() => raffle.acceptManager(pda, mallory), "NotPendingManager");
+    const transferred = await raffle.acceptManager(pda, newManager);
+
+    // The raffle keeps its address, which is derived from its creator
+    assert.isTrue(raffle.state2Pda(transferred).equals(pda));
+
+    // Only the new manager holds manager-only instructions and gets the rent
+    await assertAnchorError(() => raffle.cancel(pda, raffleManager), "OnlyRaffleManagerCanCancel");
+    await raffle.cancel(pda, newManager);
+    await raffle.close(pda, newManager);
+
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, newManager);
+    await recoverFunds(provider, mallory);
+  });
+
+  it("Token raffle is paid in its SPL token", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const mint = await createMint(connection, walletPayer);
+    const ticketPrice = new BN(1_000_000); // 1 token at 6 decimals
+    const aliceTokens = await mintToOwner(
+      connection,
+      walletPayer,
+      mint,
+      alice.publicKey,
+      ticketPrice.muln(2)
+    );
+    const bobTokens = await mintToOwner(connection, walletPayer, mint, bob.publicKey, ticketPrice);
+
+    // Both are paid in lamports, so token raffles don't support them
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, ticketPrice, 3, 120, {
+          paymentMint: mint,
+          loyaltyBonusBps: 100,
+        }),
+      "UnsupportedForTokenRaffle"
+    );
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, ticketPrice, 3, 120, {
+          paymentMint: mint,
+          keeperBounty: new BN(1),
+        }),
+      "UnsupportedForTokenRaffle"
+    );
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, ticketPrice, 3, 120, {
+          paymentMint: mint,
+          fixedPrizeLamports: new BN(1),
+        }),
+      "UnsupportedForTokenRaffle"
+    );
+
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, { paymentMint: mint });
+    const pda = raffle.state2Pda(state);
+    const escrow = associatedTokenAddress(pda, mint);
+
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.buyTickets(pda, bob, 1);
+    assert.isTrue((await tokenBalance(connection, escrow)).eq(ticketPrice.muln(3)));
+    assert.isTrue((await tokenBalance(connection, aliceTokens)).isZero());
+    assert.isTrue((await tokenBalance(connection, bobTokens)).isZero());
+
+    // Neither buyer keeps a token account, so claiming has to create the
+    // winner's one
+    await closeTokenAccount(connection, alice, aliceTokens);
+    await closeTokenAccount(connection, bob, bobTokens);
+
+    // Selling the last ticket ended the raffle
+    const drawnState = await raffle.drawWinner(pda);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    const winnerTokens = associatedTokenAddress(winner, mint);
+    assert.isNull(await connection.getAccountInfo(winnerTokens, "confirmed"));
+    await raffle.claimPrize(pda, winner);
+    assert.isTrue((await tokenBalance(connection, winnerTokens)).eq(ticketPrice.muln(3)));
+
+    // The escrow is closed along with the raffle
+    await raffle.close(pda, walletPayer);
+    assert.isNull(await connection.getAccountInfo(escrow, "confirmed"));
+
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Token raffle sells tickets in its accepted mints too", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const mint = await createMint(connection, walletPayer);
+    const mintB = await createMint(connection, walletPayer);
+    const mintC = await createMint(connection, walletPayer);
+    const ticketPrice = new BN(1_000_000);
+    const acceptedMints = [
+      { mint: mintB, ticketPrice: new BN(2_000_000) },
+      { mint: mintC, ticketPrice: new BN(500_000) },
+    ];
+    const mints = [mint, mintB, mintC];
+    const prices = [ticketPrice, acceptedMints[0].ticketPrice, acceptedMints[1].ticketPrice];
+    // Alice pays in the payment mint and mint B, Bob in mint C. Neither has a
+    // token account for the other mints, so whoever wins has the missing
+    // ones created at claim.
+    await mintToOwner(connection, walletPayer, mint, alice.publicKey, prices[0]);
+    await mintToOwner(connection, walletPayer, mintB, alice.publicKey, prices[1]);
+    await mintToOwner(connection, walletPayer, mintC, bob.publicKey, prices[2]);
+
+    // Accepted mints must be distinct from the payment mint
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, ticketPrice, 3, 120, {
+          paymentMint: mint,
+          acceptedMints: [{ mint, ticketPrice }],
+        }),
+      "InvalidAcceptedMints"
+    );
+    // A SOL raffle has no escrow to pay the other mints' tickets into
+    await assertAnchorError(
+      () => raffle.create(walletPayer, solToLamports(0.0001), 3, 120, { acceptedMints }),
+      "UnsupportedForAcceptedMints"
+    );
+
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, {
+      paymentMint: mint,
+      acceptedMints,
+    });
+    const pda = raffle.state2Pda(state);
+    const escrows = mints.map((m) => associatedTokenAddress(pda, m));
+
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, alice, 1, null, [], acceptedMints.length),
+      "InvalidMintIndex"
+    );
+
+    // Each mint's tickets are paid at its own price into its own escrow
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, alice, 1, null, [], 0);
+    const soldState = await raffle.buyTickets(pda, bob, 1, null, [], 1);
+    for (let i = 0; i < mints.length; i++) {
+      assert.isTrue((await tokenBalance(connection, escrows[i])).eq(prices[i]));
+    }
+    assert.isTrue(soldState.totalCollected.eq(ticketPrice));
+
+    // The winner is paid every escrow, whichever mint their tickets were in
+    const drawnState = await raffle.drawWinner(pda);
+    const winner = drawnState.entrants[drawnState.winnerIndex].buyer;
+    await raffle.claimPrize(pda, winner);
+    for (let i = 0; i < mints.length; i++) {
+      const winnerTokens = associatedTokenAddress(winner, mints[i]);
+      assert.isTrue((await tokenBalance(connection, winnerTokens)).eq(prices[i]));
+    }
+
+    // The accepted mints' escrows are closed along with the raffle
+    await raffle.close(pda, walletPayer);
+    for (const escrow of escrows) {
+      assert.isNull(await connection.getAccountInfo(escrow, "confirmed"));
+    }
+
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Refunds of a multi-mint raffle are paid in the mint each ticket was paid in", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const mint = await createMint(connection, walletPayer);
+    const mintB = await createMint(connection, walletPayer);
+    const mintC = await createMint(connection, walletPayer);
+    const ticketPrice = new BN(1_000_000);
+    const priceB = new BN(2_000_000);
+    const priceC = new BN(500_000);
+    // Alice pays in the payment mint and mint B, Bob twice in mint C
+    const aliceTokens = await mintToOwner(
+      connection,
+      walletPayer,
+      mint,
+      alice.publicKey,
+      ticketPrice
+    );
+    const aliceTokensB = await mintToOwner(connection, walletPayer, mintB, alice.publicKey, priceB);
+    const bobTokensC = await mintToOwner(
+      connection,
+      walletPayer,
+      mintC,
+      bob.publicKey,
+      priceC.muln(2)
+    );
+
+    const state = await raffle.create(walletPayer, ticketPrice, 4, 120, {
+      paymentMint: mint,
+      acceptedMints: [
+        { mint: mintB, ticketPrice: priceB },
+        { mint: mintC, ticketPrice: priceC },
+      ],
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, alice, 1, null, [], 0);
+    const soldState = await raffle.buyTickets(pda, bob, 2, null, [], 1);
+    assert.deepEqual(acceptedMintTicketsOf(soldState, alice.publicKey).slice(0, 2), [1, 0]);
+    assert.deepEqual(acceptedMintTicketsOf(soldState, bob.publicKey).slice(0, 2), [0, 2]);
+    await raffle.cancel(pda, walletPayer);
+
+    // Bob's emptied mint C account is gone; the refund creates it again
+    await closeTokenAccount(connection, bob, bobTokensC);
+    await raffle.claimRefund(pda, alice);
+    await raffle.claimRefund(pda, bob);
+    assert.isTrue((await tokenBalance(connection, aliceTokens)).eq(ticketPrice));
+    assert.isTrue((await tokenBalance(connection, aliceTokensB)).eq(priceB));
+    assert.isTrue((await tokenBalance(connection, bobTokensC)).eq(priceC.muln(2)));
+    // Bob paid only in mint C, so needs no account for the other mints
+    for (const m of [mint, mintB]) {
+      const bobTokens = associatedTokenAddress(bob.publicKey, m);
+      assert.isNull(await connection.getAccountInfo(bobTokens, "confirmed"));
+    }
+    for (const m of [mint, mintB, mintC]) {
+      assert.isTrue((await tokenBalance(connection, associatedTokenAddress(pda, m))).isZero());
+    }
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("NFT prize goes to the winner and the pool to the manager", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const nftMint = await createMint(connection, raffleManager, 0);
+    const managerNft = await mintToOwner(
+      connection,
+      raffleManager,
+      nftMint,
+      raffleManager.publicKey,
+      new BN(1)
+    );
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.depositPrizeNft(pda, raffleManager, nftMint);
+    assert.isTrue((await tokenBalance(connection, managerNft)).isZero());
+
+    await raffle.buyTickets(pda, alice, 2);
+    const drawnState = await raffle.drawWinner(pda);
+    assert.isTrue(drawnState.entrants[drawnState.winnerIndex].buyer.equals(alice.publicKey));
+
+    // The NFT is still in escrow, so the raffle can't be closed yet
+    await assertAnchorError(() => raffle.close(pda, raffleManager), "CanNotCloseActiveRaffle");
+
+    // The winner gets the NFT, in a token account created for them
+    await raffle.claimPrize(pda, alice.publicKey);
+    const aliceNft = associatedTokenAddress(alice.publicKey, nftMint);
+    assert.strictEqual((await tokenBalance(connection, aliceNft)).toNumber(), 1);
+
+    // The ticket payments stay in the raffle account and go to the manager,
+    // along with the rent of the emptied NFT escrow
+    const prizeNftEscrow = associatedTokenAddress(pda, nftMint);
+    const accountInfo = await connection.getAccountInfo(pda, "confirmed");
+    const rent = await connection.getMinimumBalanceForRentExemption(accountInfo.data.length);
+    const escrowRent = await connection.getBalance(prizeNftEscrow, "confirmed");
+    const event = await raffle.close(pda, raffleManager);
+    assert.strictEqual(
+      event.refundedLamports.toNumber(),
+      rent + ticketPrice.muln(2).toNumber() + escrowRent
+    );
+    assert.isNull(await connection.getAccountInfo(prizeNftEscrow, "confirmed"));
+
+    await closeTokenAccount(connection, raffleManager, managerNft);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Prize tokens are claimed along with the lamport prize", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 0.01);
+    const prizeMint = await createMint(connection, raffleManager, 6);
+    const prizeTokens = new BN(5_000_000);
+    const managerTokens = await mintToOwner(
+      connection,
+      raffleManager,
+      prizeMint,
+      raffleManager.publicKey,
+      prizeTokens
+    );
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(raffleManager, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.depositPrizeTokens(pda, raffleManager, prizeMint, prizeTokens);
+    assert.isTrue((await tokenBalance(connection, managerTokens)).isZero());
+
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.drawWinner(pda);
+
+    // A claim can't be pointed at another token account than the escrow
+    const aliceTokens = associatedTokenAddress(alice.publicKey, prizeMint);
+    await assertAnchorError(
+      () =>
+        program.methods
+          .claimPrize()
+          .accounts({
+            winner: alice.publicKey,
+            // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+            raffleState: pda,
+            winnerPrizeTokenAccount: aliceTokens,
+            prizeTokenEscrow: managerTokens,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            payer: provider.wallet.publicKey,
+            prizeTokenMint: prizeMint,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc({ commitment: "confirmed" }),
+      "InvalidEscrow"
+    );
+
+    // The winner gets both parts of the prize in the same claim
+    const lamportsBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimPrize(pda, alice.publicKey, ticketPrice.muln(2));
+    assert.strictEqual(
+      await connection.getBalance(alice.publicKey, "confirmed"),
+      lamportsBefore + ticketPrice.muln(2).toNumber()
+    );
+    assert.isTrue((await tokenBalance(connection, aliceTokens)).eq(prizeTokens));
+
+    // The emptied escrow is closed with the raffle
+    await raffle.close(pda, raffleManager);
+    const prizeTokenEscrow = associatedTokenAddress(pda, prizeMint);
+    assert.isNull(await connection.getAccountInfo(prizeTokenEscrow, "confirmed"));
+
+    await closeTokenAccount(connection, raffleManager, managerTokens);
+    await recoverFunds(provider, raffleManager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Raffle below its minimum refunds instead of drawing", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+
+    // Reached: the minimum is met, so the raffle draws as usual
+    const reached = raffle.state2Pda(
+      await raffle.create(walletPayer, ticketPrice, 2, 120, { minTickets: 2 })
+    );
+    await raffle.buyTickets(reached, alice, 1);
+    await raffle.buyTickets(reached, bob, 1);
+    await raffle.drawWinner(reached);
+
+    // Not reached: only 2 of 3 tickets are sold by the end time
+    const missed = raffle.state2Pda(
+      await raffle.create(walletPayer, ticketPrice, 4, 10, { minTickets: 3 })
+    );
+    await raffle.buyTickets(missed, alice, 1);
+    await raffle.buyTickets(missed, bob, 1);
+    await assertAnchorError(() => raffle.claimRefund(missed, alice), "RefundsNotOpen");
+    await sleep(12 * 1000);
+
+    await assertAnchorError(() => raffle.drawWinner(missed), "MinimumNotReached");
+    assert.deepEqual(await raffle.getStatusesBatch([missed]), [RaffleStatus.Refunding]);
+    const balanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimRefund(missed, alice);
+    const balanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(balanceAfter - balanceBefore, ticketPrice.toNumber());
+    await assertAnchorError(() => raffle.claimRefund(missed, alice), "NoTicketsToRefund");
+    await raffle.claimRefund(missed, bob);
+
+    const drawn = await raffle.getState(reached);
+    await raffle.claimPrize(reached, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(reached, walletPayer);
+    await raffle.close(missed, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Purchases are logged by slot", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 5, 120, { recordPurchases: true });
+    const pda = raffle.state2Pda(state);
+    const purchaseLogPda = raffle.purchaseLogPda(pda);
+
+    const slotBefore = await connection.getSlot("confirmed");
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.buyTickets(pda, bob, 3);
+    const slotAfter = await connection.getSlot("confirmed");
+
+    // One record per purchase, not per ticket
+    const { purchases } = await raffle.getPurchaseLog(pda);
+    assert.deepEqual(purchases.map((purchase) => purchase.firstIndex), [0, 2]);
+    assert.isAtLeast(purchases[0].slot.toNumber(), slotBefore);
+    assert.isAtLeast(purchases[1].slot.toNumber(), purchases[0].slot.toNumber());
+    assert.isAtMost(purchases[1].slot.toNumber(), slotAfter);
+
+    // The log is closed along with the raffle
+    await raffle.drawWinner(pda);
+    const drawnState = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawnState.entrants[drawnState.winnerIndex].buyer);
+    const purchaseLogRent = await connection.getBalance(purchaseLogPda, "confirmed");
+    const event = await raffle.close(pda, walletPayer);
+    assert.isAtLeast(event.refundedLamports.toNumber(), purchaseLogRent);
+    assert.isNull(await connection.getAccountInfo(purchaseLogPda, "confirmed"));
+
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Batched raffle statuses", async () => {
+    const ticketPrice = solToLamports(0.0001);
+    const open = raffle.state2Pda(await raffle.create(walletPayer, ticketPrice, 2, 120));
+    const ended = raffle.state2Pda(await raffle.create(walletPayer, ticketPrice, 1, 120));
+    const claimed = raffle.state2Pda(await raffle.create(walletPayer, ticketPrice, 1, 180));
+
+    // Selling the only ticket ends a raffle
+    await raffle.buyTickets(ended, walletPayer, 1);
+    await raffle.buyTickets(claimed, walletPayer, 1);
+    await raffle.drawWinner(claimed);
+    await raffle.claimPrize(claimed, walletPayer.publicKey);
+
+    assert.deepEqual(await raffle.getStatusesBatch([open, ended, claimed]), [
+      RaffleStatus.Open,
+      RaffleStatus.Ended,
+      RaffleStatus.Claimed,
+    ]);
+
+    await raffle.close(claimed, walletPayer);
+    assert.deepEqual(await raffle.getStatusesBatch([claimed]), [RaffleStatus.Closed]);
+
+    await raffle.close(open, walletPayer);
+    await raffle.drawWinner(ended);
+    await raffle.claimPrize(ended, walletPayer.publicKey);
+    await raffle.close(ended, walletPayer);
+  });
+
+  it("Raffle progress reports tickets and time left", async () => {
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 5, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 2);
+
+    const progress = await raffle.getRaffleProgress(pda);
+    assert.strictEqual(progress.ticketsSold, 2);
+    assert.strictEqual(progress.ticketsRemaining, 3);
+    assert.isAbove(progress.secondsRemaining.toNumber(), 0);
+    assert.isAtMost(progress.secondsRemaining.toNumber(), 120);
+    assert.isFalse(progress.isOver);
+    assert.isFalse(progress.winnerDrawn);
+
+    // Selling out ends the raffle early, with time still left on the clock
+    await raffle.buyTickets(pda, walletPayer, 3);
+    await raffle.drawWinner(pda);
+    const drawnProgress = await raffle.getRaffleProgress(pda);
+    assert.strictEqual(drawnProgress.ticketsRemaining, 0);
+    assert.isTrue(drawnProgress.isOver);
+    assert.isTrue(drawnProgress.winnerDrawn);
+
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("Claim eligibility reports the winner before and after claiming", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, alice, 1);
+
+    const undrawn = await raffle.checkClaimEligibility(pda, walletPayer.publicKey);
+    assert.isFalse(undrawn.drawn);
+    assert.isFalse(undrawn.isWinner);
+    assert.isFalse(undrawn.alreadyClaimed);
+    assert.isTrue(undrawn.prizeAmount.eq(ticketPrice.muln(2)));
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    const winner = drawn.entrants[drawn.winnerIndex].buyer;
+    const loser = winner.equals(alice.publicKey) ? walletPayer.publicKey : alice.publicKey;
+
+    const winnerEligibility = await raffle.checkClaimEligibility(pda, winner);
+    assert.isTrue(winnerEligibility.drawn);
+    assert.isTrue(winnerEligibility.isWinner);
+    assert.isFalse(winnerEligibility.alreadyClaimed);
+    const loserEligibility = await raffle.checkClaimEligibility(pda, loser);
+    assert.isTrue(loserEligibility.drawn);
+    assert.isFalse(loserEligibility.isWinner);
+
+    await raffle.claimPrize(pda, winner);
+    const claimed = await raffle.checkClaimEligibility(pda, winner);
+    assert.isTrue(claimed.isWinner);
+    assert.isTrue(claimed.alreadyClaimed);
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Wallet ticket count is read without fetching the entrants", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 5, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1);
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, alice, 2);
+
+    assert.deepEqual(await raffle.getMyTickets(pda, alice.publicKey), {
+      tickets: 3,
+      totalTickets: 4,
+    });
+    // A wallet that never entered
+    assert.deepEqual(await raffle.getMyTickets(pda, bob.publicKey), {
+      tickets: 0,
+      totalTickets: 4,
+    });
+
+    // And the odds that follow from them, in basis points
+    assert.strictEqual(await raffle.getMyOdds(pda, alice.publicKey), 7_500);
+    assert.strictEqual(await raffle.getMyOdds(pda, walletPayer.publicKey), 2_500);
+    assert.strictEqual(await raffle.getMyOdds(pda, bob.publicKey), 0);
+
+    await raffle.cancel(pda, walletPayer);
+    await raffle.claimRefund(pda, alice);
+    await raffle.claimRefund(pda, walletPayer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Entrants are read page by page", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 5, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, alice, 3);
+    await raffle.buyTickets(pda, walletPayer, 1);
+
+    // One entry per wallet, in order of first purchase
+    const page = await raffle.getEntrantsPage(pda, 0, 2);
+    assert.strictEqual(page.totalEntrants, 2);
+    assert.strictEqual(page.entrants.length, 2);
+    assert.isTrue(page.entrants[0].buyer.equals(walletPayer.publicKey));
+    assert.strictEqual(page.entrants[0].count, 2);
+    assert.isTrue(page.entrants[1].buyer.equals(alice.publicKey));
+    assert.strictEqual(page.entrants[1].count, 3);
+
+    // Clamped to the end of the list
+    const lastPage = await raffle.getEntrantsPage(pda, 1, MAX_ENTRANTS_PER_PAGE);
+    assert.strictEqual(lastPage.entrants.length, 1);
+
+    // A start past the end gives an empty page
+    const emptyPage = await raffle.getEntrantsPage(pda, 2, 2);
+    assert.strictEqual(emptyPage.entrants.length, 0);
+    assert.strictEqual(emptyPage.totalEntrants, 2);
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.winner);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Name and metadata URI are stored at creation", async () => {
+    const name = "Community raffle #1";
+    const uri = "https://example.com/raffles/1.json";
+    // create() checks both in the stored state and RaffleCreatedEvent
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, { name, uri });
+    await raffle.close(raffle.state2Pda(state), walletPayer);
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          name: "n".repeat(65), // above MAX_NAME_LEN
+        }),
+      "MetadataTooLong"
+    );
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          uri: "u".repeat(201), // above MAX_URI_LEN
+        }),
+      "MetadataTooLong"
+    );
+  });
+
+  it("Terms hash is bound at creation", async () => {
+    // Stand-in for the hash of an off-chain terms document
+    const termsHash = Array.from({ length: 32 }, (_, i) => i + 1);
+    // create() checks the hash in both the stored state and RaffleCreatedEvent
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, { termsHash });
+    assert.deepEqual(state.termsHash, termsHash);
+
+    await raffle.close(raffle.state2Pda(state), walletPayer);
+  });
+
+  it("Raffles need a minimum lead time", async () => {
+    await assertAnchorError(
+      () => raffle.create(walletPayer, solToLamports(0.0001), 2, 1),
+      "LeadTimeTooShort"
+    );
+
+    // Leave some slack for the cluster clock lagging behind ours
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, MIN_LEAD_SECS + 10);
+    await raffle.close(raffle.state2Pda(state), walletPayer);
   });
 
   it("createRaffle negative tests", async () => {
@@ -97,6 +1630,11 @@ describe("raffle", () => {
       "MaxTicketsIsZero"
     );
 
+    await assertAnchorError(
+      () => raffle.create(walletPayer, solToLamports(0.0001), 2, 120, { minTickets: 3 }),
+      "MinTicketsExceedsMax"
+    );
+
     // Ticket price too low (set below 100_000 lamports)
     await assertAnchorError(
       () =>
@@ -119,12 +1657,112 @@ describe("raffle", () => {
         ),
       "RaffleTooLarge"
     );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          maxPrize: solToLamports(0.0001).muln(2).subn(1), // 1 lamport below 2 tickets
+        }),
+      "PrizeCapExceeded"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          // the second ticket's price overflows u64
+          priceCurve: { linearStep: { increment: new BN("18446744073709551615") } },
+        }),
+      "RaffleTooLarge"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          loyaltyBonusBps: 5001, // above MAX_LOYALTY_BONUS_BPS
+        }),
+      "LoyaltyBonusTooHigh"
+    );
+
+    const unlockTime = new BN(Math.floor(Date.now() / 1000) + 60);
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          // tranches must add up to FULL_BPS
+          vestingSchedule: [
+            { unlockTime, bps: 5_000 },
+            { unlockTime, bps: 4_000 },
+          ],
+        }),
+      "InvalidVestingSchedule"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          keeperBounty: solToLamports(0.0001).addn(1), // above ticket price
+        }),
+      "KeeperBountyTooHigh"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          operatorWithdrawalCapBps: 5_001, // above 50%
+        }),
+      "OperatorWithdrawalCapTooHigh"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          secondChancePrizeBps: 5_001, // above MAX_SECOND_CHANCE_PRIZE_BPS
+        }),
+      "SecondChancePrizeTooHigh"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          secondChancePrizeBps: 1_000, // without a claimWindowSecs
+        }),
+      "SecondChancePrizeNeedsClaimWindow"
+    );
+
+    await assertAnchorError(
+      () =>
+        raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+          freeEntry: true, // but not free
+        }),
+      "InvalidFreeEntry"
+    );
+
+    // 10_000 tickets would need about 2.5 SOL of rent, but buyers pay for
+    // the growth, so only the starting size is paid up front...
+    const underfunded = await createFundedWallet(provider, 0.02);
+    const large = raffle.state2Pda(
+      await raffle.create(underfunded, solToLamports(0.0001), 10_000, 120)
+    );
+    await raffle.cancel(large, underfunded);
+    await raffle.close(large, underfunded);
+
+    // ...along with the deposits moved into the raffle
+    await assertAnchorError(
+      () =>
+        raffle.create(underfunded, solToLamports(0.0001), 2, 120, {
+          refundBond: solToLamports(0.02),
+        }),
+      "InsufficientRentBalance"
+    );
+    await recoverFunds(provider, underfunded);
   });
 
   it("buyTickets negative tests", async () => {
     const state = await raffle.create(walletPayer, solToLamports(0.0001), 1, 120);
     const pda = raffle.state2Pda(state);
 
+    // A purchase must buy at least one ticket
+    await assertAnchorError(() => raffle.buyTickets(pda, walletPayer, 0), "NumberOfTicketsIsZero");
+
     // Buy 2 tickets when only 1 is available
     await assertAnchorError(() => raffle.buyTickets(pda, walletPayer, 2), "InsufficientTickets");
 
@@ -132,6 +1770,31 @@ describe("raffle", () => {
     await raffle.buyTickets(pda, walletPayer, 1);
 
     // Try to buy another ticket after raffle is full
+    await assertAnchorError(() => raffle.buyTickets(pda, walletPayer, 1), "SoldOut");
+
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("buyTickets rejects an underfunded buyer", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.01), 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Alice's fee leaves her short of the ticket price
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 1), "InsufficientFunds");
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("buyTickets rejected after the end time with tickets left", async () => {
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 10);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await sleep(12 * 1000);
     await assertAnchorError(() => raffle.buyTickets(pda, walletPayer, 1), "RaffleHasEnded");
 
     await raffle.drawWinner(pda);
@@ -139,6 +1802,173 @@ describe("raffle", () => {
     await raffle.close(pda, walletPayer);
   });
 
+  it("buyTickets limited to a batch size per call", async () => {
+    const state = await raffle.create(
+      walletPayer,
+      solToLamports(0.0001),
+      MAX_TICKETS_PER_PURCHASE,
+      120
+    );
+    const pda = raffle.state2Pda(state);
+
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, walletPayer, MAX_TICKETS_PER_PURCHASE + 1),
+      "BatchTooLarge"
+    );
+    // A full batch fits in one call
+    await raffle.buyTickets(pda, walletPayer, MAX_TICKETS_PER_PURCHASE);
+
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
+  it("buyTickets limited per wallet", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 4, 120, {
+      maxTicketsPerWallet: 2,
+    });
+    const pda = raffle.state2Pda(state);
+
+    // Exactly at the limit, over two purchases
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, walletPayer, 1),
+      "WalletTicketLimitExceeded"
+    );
+
+    // Another wallet has its own limit, checked against the whole purchase
+    await assertAnchorError(() => raffle.buyTickets(pda, alice, 3), "WalletTicketLimitExceeded");
+    await raffle.buyTickets(pda, alice, 2);
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Linear price curve charges escalating ticket prices", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const increment = solToLamports(0.00001);
+    const state = await raffle.create(walletPayer, ticketPrice, 5, 120, {
+      priceCurve: { linearStep: { increment } },
+    });
+    const pda = raffle.state2Pda(state);
+
+    // Tickets 0, 1 and 2 cost the base price plus 0, 1 and 2 increments
+    const batch = await raffle.buyTickets(pda, alice, 3);
+    assert.isTrue(batch.totalCollected.eq(ticketPrice.muln(3).add(increment.muln(3))));
+    // Tickets 3 and 4 continue from where the batch left off
+    const soldOut = await raffle.buyTickets(pda, walletPayer, 2);
+    assert.isTrue(soldOut.totalCollected.eq(ticketPrice.muln(5).add(increment.muln(10))));
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer, soldOut.totalCollected);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("Collected total matches the lamports paid in over several batches", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 10, 120);
+    const pda = raffle.state2Pda(state);
+    const lamportsBefore = await connection.getBalance(pda, "confirmed");
+
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.buyTickets(pda, bob, 3);
+    await raffle.buyTickets(pda, alice, 1);
+    const sold = await raffle.buyTickets(pda, walletPayer, 4);
+
+    // Every ticket payment lands in the raffle account
+    const lamports = await connection.getBalance(pda, "confirmed");
+    assert.isTrue(sold.totalCollected.eqn(lamports - lamportsBefore));
+
+    const drawn = await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+  });
+
+  it("Raffle created at its prize cap tracks the collected pool", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const ticketPrice = solToLamports(0.0001);
+    const state = await raffle.create(walletPayer, ticketPrice, 3, 120, {
+      maxPrize: ticketPrice.muln(3), // exactly at the cap
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await raffle.buyTickets(pda, alice, 2);
+    const sold = await raffle.getState(pda);
+    assert.isTrue(sold.totalCollected.eq(ticketPrice.muln(3)));
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+  });
+
+  it("buyTickets gated by a Merkle allowlist", async () => {
+    const alice = await createFundedWallet(provider, 0.01);
+    const bob = await createFundedWallet(provider, 0.01);
+    const mallory = await createFundedWallet(provider, 0.01);
+    const aliceLeaf = allowlistLeaf(alice.publicKey);
+    const bobLeaf = allowlistLeaf(bob.publicKey);
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120, {
+      allowlistRoot: allowlistNode(aliceLeaf, bobLeaf),
+    });
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, alice, 1, null, [bobLeaf]);
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, mallory, 1, null, [bobLeaf]),
+      "NotOnAllowlist"
+    );
+    await assertAnchorError(() => raffle.buyTickets(pda, mallory, 1), "NotOnAllowlist");
+    // Gifting doesn't get around the allowlist either, the entrant must be on it
+    await assertAnchorError(
+      () => raffle.buyTickets(pda, bob, 1, mallory.publicKey, [aliceLeaf]),
+      "NotOnAllowlist"
+    );
+    await raffle.buyTickets(pda, bob, 1, null, [aliceLeaf]);
+
+    await raffle.drawWinner(pda);
+    const drawn = await raffle.getState(pda);
+    await raffle.claimPrize(pda, drawn.entrants[drawn.winnerIndex].buyer);
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, bob);
+    await recoverFunds(provider, mallory);
+  });
+
+  it("buyTickets rejected after the sales cutoff", async () => {
+    // Sales stop 100 slots (40 seconds) before the end, 20 seconds from now
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 5, 60, {
+      salesCutoffSlots: 100,
+    });
+    const pda = raffle.state2Pda(state);
+
+    // Still inside the sales window
+    await raffle.buyTickets(pda, walletPayer, 1);
+
+    // Past the cutoff, but before end_time
+    await sleep(30_000);
+    await assertAnchorError(() => raffle.buyTickets(pda, walletPayer, 1), "SalesCutoffReached");
+
+    // Once end_time passes the raffle is over and can be drawn as usual
+    await sleep(15_000);
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
   it("drawWinner negative tests", async () => {
     const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 120);
     const pda = raffle.state2Pda(state);
@@ -182,6 +2012,27 @@ describe("raffle", () => {
     await raffle.close(pda, walletPayer);
   });
 
+  it("drawWinner waits out the draw delay", async () => {
+    const drawDelaySecs = 15;
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 2, 10, {
+      drawDelaySecs,
+    });
+    const pda = raffle.state2Pda(state);
+
+    // Selling out doesn't skip the delay
+    await raffle.buyTickets(pda, walletPayer, 2);
+    await assertAnchorError(() => raffle.drawWinner(pda), "RaffleNotOver");
+
+    // Past the end time but still inside the delay
+    await sleep(12 * 1000);
+    await assertAnchorError(() => raffle.drawWinner(pda), "RaffleNotOver");
+
+    await sleep((drawDelaySecs + 2) * 1000);
+    await raffle.drawWinner(pda);
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
   it("drawWinnerCallback negative tests", async () => {
     const state = await raffle.create(walletPayer, solToLamports(0.0001), 1, 120);
     const pda = raffle.state2Pda(state);
@@ -200,6 +2051,10 @@ describe("raffle", () => {
       () => raffle.drawWinnerCallback(pda, [drawWinnerIx]),
       "CallbackNotInvokedByVRF"
     );
+    // Rejected before anything was written
+    const rejected = await raffle.getState(pda);
+    assert.isNull(rejected.winnerIndex);
+    assert.isFalse(rejected.drawWinnerStarted);
 
     // Successful drawWinner call and callback if we get past the next line
     await raffle.drawWinner(pda);
@@ -235,6 +2090,105 @@ describe("raffle", () => {
     await recoverFunds(provider, mallory);
   });
 
+  it("claimPrizeTo sends the prize to the winner's destination", async () => {
+    const alice = await createFundedWallet(provider, 0.1);
+    const mallory = await createFundedWallet(provider, 0.1);
+    const destination = Keypair.generate().publicKey;
+    const ticketPrice = solToLamports(0.0001);
+
+    const state = await raffle.create(walletPayer, ticketPrice, 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    // Alice holds every ticket, so she is guaranteed to win
+    await raffle.buyTickets(pda, alice, 2);
+    await raffle.drawWinner(pda);
+
+    // Only the winner can redirect the prize
+    await assertAnchorError(
+      () => raffle.claimPrizeTo(pda, mallory, mallory.publicKey),
+      "NotWinner"
+    );
+
+    const aliceBalanceBefore = await connection.getBalance(alice.publicKey, "confirmed");
+    await raffle.claimPrizeTo(pda, alice, destination);
+
+    // The whole pool lands in the destination, not in alice's wallet
+    const destinationBalance = await connection.getBalance(destination, "confirmed");
+    assert.strictEqual(destinationBalance, ticketPrice.muln(2).toNumber());
+    const aliceBalanceAfter = await connection.getBalance(alice.publicKey, "confirmed");
+    assert.strictEqual(aliceBalanceAfter, aliceBalanceBefore);
+
+    // The redirected prize is settled, so it can't also be paid to alice
+    await assertAnchorError(() => raffle.claimPrize(pda, alice.publicKey), "PrizeAlreadyClaimed");
+
+    await raffle.close(pda, walletPayer);
+    await recoverFunds(provider, alice);
+    await recoverFunds(provider, mallory);
+  });
+
+  it("A fee splits the prize pool between the manager and the winner", async () => {
+    const manager = await createFundedWallet(provider, 0.05);
+    const alice = await createFundedWallet(provider, 2.1);
+    const ticketPrice = solToLamports(0.5);
+
+    await assertAnchorError(
+      () => raffle.create(manager, ticketPrice, 2, 120, { feeBps: 1_001 }),
+      "FeeTooHigh"
+    );
+
+    // A 5% fee on a 1 SOL pool: 0.05 SOL to the manager, 0.95 SOL to alice
+    const withFee = raffle.state2Pda(
+      await raffle.create(manager, ticketPrice, 2, 120, { feeBps: 500 })
+    );
+    await raffle.buyTickets(withFee, alice, 2);
+    await raffle.drawWinner(withFee);
+    let destination = Keypair.generate().publicKey;
+    let managerBefore = await connection.getBalance(manager.publicKey, "confirmed");
+    await raffle.claimPrizeTo(withFee, alice, destination);
+    let managerAfter = await connection.getBalance(manager.publicKey, "confirmed");
+    assert.strictEqual(managerAfter - managerBefore, solToLamports(0.05).toNumber());
+    assert.strictEqual(
+      await connection.getBalance(destination, "confirmed"),
+      solToLamports(0.95).toNumber()
+    );
+
+    // Without a fee the winner takes the whole pool, as before
+    const withoutFee = raffle.state2Pda(await raffle.create(manager, ticketPrice, 2, 120));
+    await raffle.buyTickets(withoutFee, alice, 2);
+    await raffle.drawWinner(withoutFee);
+    destination = Keypair.generate().publicKey;
+    managerBefore = await connection.getBalance(manager.publicKey, "confirmed");
+    await raffle.claimPrizeTo(withoutFee, alice, destination);
+    managerAfter = await connection.getBalance(manager.publicKey, "confirmed");
+    assert.strictEqual(managerAfter, managerBefore);
+    assert.strictEqual(
+      await connection.getBalance(destination, "confirmed"),
+      solToLamports(1).toNumber()
+    );
+
+    await raffle.close(withFee, manager);
+    await raffle.close(withoutFee, manager);
+    await recoverFunds(provider, manager);
+    await recoverFunds(provider, alice);
+  });
+
+  it("closeRaffle returns the rent to the manager", async () => {
+    const raffleManager = await createFundedWallet(provider, 0.05);
+    const state = await raffle.create(raffleManager, solToLamports(0.0001), 2, 120);
+    const pda = raffle.state2Pda(state);
+
+    // The provider wallet pays the fees, so the manager's balance only moves
+    // by the refund
+    const rent = await connection.getBalance(pda, "confirmed");
+    const balanceBefore = await connection.getBalance(raffleManager.publicKey, "confirmed");
+    const event = await raffle.close(pda, raffleManager);
+    const balanceAfter = await connection.getBalance(raffleManager.publicKey, "confirmed");
+    assert.strictEqual(event.refundedLamports.toNumber(), rent);
+    assert.strictEqual(balanceAfter - balanceBefore, rent);
+
+    await recoverFunds(provider, raffleManager);
+  });
+
   it("closeRaffle negative tests", async () => {
     const manager = await createFundedWallet(provider, 0.1);
     const notManager = await createFundedWallet(provider, 0.1);
@@ -255,12 +2209,129 @@ describe("raffle", () => {
     // buy the last ticket to end the raffle
     await raffle.buyTickets(pda, notManager, 1);
     await raffle.drawWinner(pda);
+
+    // The close guard keeps a drawn-but-unclaimed prize from being closed out
+    await assertAnchorError(() => raffle.close(pda, manager), "CanNotCloseActiveRaffle");
+
     await raffle.claimPrize(pda, notManager.publicKey);
     await raffle.close(pda, manager);
+
+    // Claiming a closed raffle means it was already settled
+    await assertAnchorError(
+      () => raffle.claimPrize(pda, notManager.publicKey),
+      "AccountNotInitialized",
+      "raffle_state"
+    );
+    await recoverFunds(provider, manager);
+    await recoverFunds(provider, notManager);
+  });
+
+  it("closeRaffle by the close authority", async () => {
+    const manager = await createFundedWallet(provider, 0.05);
+    const dao = await createFundedWallet(provider, 0.01);
+    const notManager = await createFundedWallet(provider, 0.01);
+    const state = await raffle.create(manager, solToLamports(0.0001), 2, 120, {
+      closeAuthority: dao.publicKey,
+    });
+    const pda = raffle.state2Pda(state);
+
+    await assertAnchorError(
+      () => raffle.close(pda, notManager),
+      "OnlyRaffleManagerOrProgramOwnerCanClose"
+    );
+
+    // The rent still goes to the manager
+    const event = await raffle.close(pda, dao);
+    assert.isTrue(event.raffleManager.equals(manager.publicKey));
+
     await recoverFunds(provider, manager);
+    await recoverFunds(provider, dao);
     await recoverFunds(provider, notManager);
   });
 
+  it("Manager index lists the manager's open raffles", async () => {
+    const manager = await createFundedWallet(provider, 0.1);
+    await raffle.createManagerIndex(manager);
+
+    const options = { listInManagerIndex: true };
+    const first = raffle.state2Pda(
+      await raffle.create(manager, solToLamports(0.0001), 2, 120, options)
+    );
+    const second = raffle.state2Pda(
+      await raffle.create(manager, solToLamports(0.0001), 3, 120, options)
+    );
+    let { raffles } = await raffle.getManagerIndex(manager.publicKey);
+    assert.deepEqual(raffles.map((pda) => pda.toBase58()), [first.toBase58(), second.toBase58()]);
+
+    // Closing a raffle drops it from the index
+    await raffle.close(first, manager);
+    ({ raffles } = await raffle.getManagerIndex(manager.publicKey));
+    assert.deepEqual(raffles.map((pda) => pda.toBase58()), [second.toBase58()]);
+    await raffle.close(second, manager);
+    ({ raffles } = await raffle.getManagerIndex(manager.publicKey));
+    assert.strictEqual(raffles.length, 0);
+
+    await recoverFunds(provider, manager);
+  });
+
+  it("Manager index cooldown spaces out the manager's raffles", async () => {
+    const manager = await createFundedWallet(provider, 0.1);
+    const createCooldownSecs = 10; // CREATE_COOLDOWN_SECS
+    await raffle.createManagerIndex(manager, true);
+
+    const options = { listInManagerIndex: true };
+    const first = raffle.state2Pda(
+      await raffle.create(manager, solToLamports(0.0001), 2, 120, options)
+    );
+
+    // Back to back is too soon, listed or not
+    await assertAnchorError(
+      () => raffle.create(manager, solToLamports(0.0001), 4, 120),
+      "CreateCooldownActive"
+    );
+    await sleep((createCooldownSecs + 2) * 1000);
+    const unlisted = raffle.state2Pda(await raffle.create(manager, solToLamports(0.0001), 4, 120));
+
+    // The unlisted raffle restarted the cooldown
+    await assertAnchorError(
+      () => raffle.create(manager, solToLamports(0.0001), 3, 120, options),
+      "CreateCooldownActive"
+    );
+    await sleep((createCooldownSecs + 2) * 1000);
+    const second = raffle.state2Pda(
+      await raffle.create(manager, solToLamports(0.0001), 3, 120, options)
+    );
+    const { raffles } = await raffle.getManagerIndex(manager.publicKey);
+    assert.deepEqual(raffles.map((pda) => pda.toBase58()), [first.toBase58(), second.toBase58()]);
+
+    await raffle.close(first, manager);
+    await raffle.close(second, manager);
+    await raffle.close(unlisted, manager);
+    await recoverFunds(provider, manager);
+  });
+
+  it("repairWinnerIndex rejects a valid winner index", async () => {
+    // An out-of-range winner index can't be produced through the program, so
+    // the repair itself is covered by the RaffleState::reselect_winner unit test.
+    const state = await raffle.create(walletPayer, solToLamports(0.0001), 1, 120);
+    const pda = raffle.state2Pda(state);
+
+    await raffle.buyTickets(pda, walletPayer, 1);
+    await assertAnchorError(
+      () => raffle.repairWinnerIndex(pda, walletPayer),
+      "WinnerIndexNotCorrupted"
+    );
+
+    await raffle.drawWinner(pda);
+    await assertAnchorError(
+      () => raffle.repairWinnerIndex(pda, walletPayer),
+      "WinnerIndexNotCorrupted"
+    );
+
+    await raffle.claimPrize(pda, walletPayer.publicKey);
+    await raffle.close(pda, walletPayer);
+  });
+
   it("closeRaffle by program owner", async () => {
     const upgradeAuthority = await raffle.getProgramUpgradeAuthority();
 
@@ -1,35 +1,372 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program, BN, EventParser } from "@coral-xyz/anchor";
 import {
+  AccountMeta,
   Connection,
   Keypair,
   PublicKey,
+  SystemProgram,
   TransactionInstruction,
   TransactionSignature,
 } from "@solana/web3.js";
 import { Raffle } from "../../target/types/raffle";
-import { printLogs, vrf_random_u64 } from "./test_utils";
+import { printLogs, select_winner_index } from "./test_utils";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  associatedTokenAddress,
+  TOKEN_PROGRAM_ID,
+  tokenBalance,
+} from "./spl_token";
 import { assert } from "chai";
+import { createHash } from "crypto";
 
 const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
   "BPFLoaderUpgradeab1e11111111111111111111111"
 );
 
+/** Mirrors LOYALTY_BONUS_HOLDERS in the program. */
+export const LOYALTY_BONUS_HOLDERS = 3;
+/** Mirrors DISTRIBUTION_TOP_N in the program. */
+export const DISTRIBUTION_TOP_N = 5;
+/** Mirrors FULL_BPS in the program. */
+export const FULL_BPS = 10_000;
+/** Mirrors MIN_LEAD_SECS in the program. */
+export const MIN_LEAD_SECS = 5;
+/** Mirrors REFUND_GRACE_PERIOD_SECS in the program. */
+export const REFUND_GRACE_PERIOD_SECS = 24 * 60 * 60;
+/** Mirrors COMMIT_DELAY_SLOTS in the program. */
+export const COMMIT_DELAY_SLOTS = 10;
+/** Number of recent slots the SlotHashes sysvar remembers. */
+export const SLOT_HASHES_MAX_ENTRIES = 512;
+/** Mirrors MAX_ENTRANTS_PER_PAGE in the program. */
+export const MAX_ENTRANTS_PER_PAGE = 28;
+/** Mirrors MAX_TICKETS_PER_PURCHASE in the program. */
+export const MAX_TICKETS_PER_PURCHASE = 256;
+
+/** Mirrors the RaffleStatus enum in the program. */
+export enum RaffleStatus {
+  Open = 0,
+  Ended = 1,
+  Drawing = 2,
+  WinnerDrawn = 3,
+  Claimed = 4,
+  Closed = 5,
+  Refunding = 6,
+}
+
 export interface RaffleState {
   raffleManager: PublicKey;
+  creator: PublicKey;
+  pendingManager: PublicKey | null;
+  closeAuthority: PublicKey | null;
   ticketPrice: BN;
+  priceCurve: PriceCurve;
+  paymentMint: PublicKey | null;
+  acceptedMints: AcceptedMint[];
+  prizeNftMint: PublicKey | null;
+  prizeTokenMint: PublicKey | null;
+  prizeTokenAmount: BN;
   maxTickets: number;
+  minTickets: number;
+  maxTicketsPerWallet: number;
   endTime: BN;
+  originalEndTime: BN;
+  createdAt: BN;
   winnerIndex: number | null;
+  winner: PublicKey | null;
+  previousWinner: PublicKey | null;
+  runnerUpIndex: number | null;
+  consolationWinnerIndex: number | null;
+  claimWindowSecs: number;
+  drawDelaySecs: number;
+  managerCannotEnter: boolean;
+  autoDraw: boolean;
+  claimDeadline: BN | null;
   drawWinnerStarted: boolean;
+  randomnessSource: RandomnessSource;
+  commitSlot: BN | null;
   claimed: boolean;
-  entrants: PublicKey[];
+  consolationClaimed: boolean;
+  refundsOpen: boolean;
+  cancelled: boolean;
+  paused: boolean;
+  freeEntry: boolean;
+  recordsPurchases: boolean;
+  listedInManagerIndex: boolean;
+  loyaltyBonusBps: number;
+  secondChancePrizeBps: number;
+  feeBps: number;
+  totalCollected: BN;
+  keeperBounty: BN;
+  operatorWithdrawalCapBps: number;
+  operatorWithdrawn: BN;
+  refundBond: BN;
+  revenueMode: RevenueMode;
+  fixedPrizeLamports: BN;
+  revenueWithdrawn: boolean;
+  salesCutoffTime: BN;
+  termsHash: number[];
+  name: string;
+  uri: string;
+  allowlistRoot: number[] | null;
+  randomness: number[];
+  vestingSchedule: VestingTranche[];
+  claimedBps: number;
+  ticketsSold: number;
+  acceptedMintTickets: AcceptedMintTickets[];
+  entrants: EntrantEntry[];
+}
+
+/** A wallet holding tickets in a raffle, and how many it holds. */
+export interface EntrantEntry {
+  buyer: PublicKey;
+  count: number;
+}
+
+/** A wallet's tickets paid in each of a raffle's accepted mints, by index. */
+export interface AcceptedMintTickets {
+  buyer: PublicKey;
+  counts: number[];
+}
+
+/** How the ticket price moves as tickets sell; exactly one variant is set. */
+export interface PriceCurve {
+  flat?: Record<string, never>;
+  linearStep?: { increment: BN };
+}
+
+/**
+ * Total price of numTickets tickets bought once sold were already sold, as
+ * computed by PriceCurve::total_price on chain.
+ */
+export function curvePrice(state: RaffleState, sold: number, numTickets: number): BN {
+  const flat = state.ticketPrice.muln(numTickets);
+  if (!state.priceCurve.linearStep) {
+    return flat;
+  }
+  const steps = numTickets * sold + (numTickets * (numTickets - 1)) / 2;
+  return flat.add(state.priceCurve.linearStep.increment.muln(steps));
+}
+
+/** Who the ticket revenue goes to; exactly one variant is set. */
+export interface RevenueMode {
+  winnerTakesPool?: Record<string, never>;
+  managerKeepsRevenue?: Record<string, never>;
+}
+
+/** Where the randomness for the draw comes from; exactly one variant is set. */
+export interface RandomnessSource {
+  vrf?: Record<string, never>;
+  slotHashes?: Record<string, never>;
+}
+
+export interface VestingTranche {
+  unlockTime: BN;
+  bps: number;
+}
+
+/** A mint a token raffle also sells tickets in, and its ticket price there. */
+export interface AcceptedMint {
+  mint: PublicKey;
+  ticketPrice: BN;
+}
+
+/** Optional raffle settings for {@link RaffleTestHelper.create}. */
+export interface CreateRaffleOptions {
+  /** Tickets that must be sold for the raffle to be drawn (default 0). */
+  minTickets?: number;
+  /** Share of the prize pool split among the top ticket holders (default 0). */
+  loyaltyBonusBps?: number;
+  /** Stop ticket sales this many slots before the end time (default 0). */
+  salesCutoffSlots?: number;
+  /** Hash of the off-chain terms document (default all zeros). */
+  termsHash?: number[];
+  /** Tranches in which the winner's share unlocks (default none). */
+  vestingSchedule?: VestingTranche[];
+  /** Lamports paid to the first draw_winner caller (default 0). */
+  keeperBounty?: BN;
+  /** Share of ticket payments the manager may withdraw before the draw (default 0). */
+  operatorWithdrawalCapBps?: number;
+  /** Seconds the winner has to claim before the runner-up may (default 0). */
+  claimWindowSecs?: number;
+  /** Lamports the manager posts to top up refunds (default 0). */
+  refundBond?: BN;
+  /** Most tickets any one wallet may hold (default 0, no limit). */
+  maxTicketsPerWallet?: number;
+  /** Merkle root of the wallets allowed to enter (default none, anyone). */
+  allowlistRoot?: number[];
+  /** Cap on ticketPrice * maxTickets (default 0, no cap). */
+  maxPrize?: BN;
+  /** How the ticket price moves as tickets sell (default flat). */
+  priceCurve?: PriceCurve;
+  /** Name shown by frontends (default empty). */
+  name?: string;
+  /** URI of off-chain metadata (default empty). */
+  uri?: string;
+  /** Share of the prize pool paid to a consolation winner (default 0). */
+  secondChancePrizeBps?: number;
+  /** Free giveaway with one entry per wallet; needs a zero ticketPrice (default false). */
+  freeEntry?: boolean;
+  /** Fixed prize in lamports; the manager keeps the ticket revenue (default 0, winner takes it). */
+  fixedPrizeLamports?: BN;
+  /** SPL token mint tickets are paid in, making it a token raffle (default SOL). */
+  paymentMint?: PublicKey;
+  /** Other mints a token raffle also sells tickets in, at their own prices (default none). */
+  acceptedMints?: AcceptedMint[];
+  /** Share of the prize pool paid to the manager with the first claim (default 0). */
+  feeBps?: number;
+  /** Record every purchase in a purchase log (default false). */
+  recordPurchases?: boolean;
+  /** Where the randomness for the draw comes from (default the VRF). */
+  randomnessSource?: RandomnessSource;
+  /** Wallet that may close the raffle besides the manager (default none). */
+  closeAuthority?: PublicKey;
+  /** Seconds after the end time before the raffle can be drawn (default 0). */
+  drawDelaySecs?: number;
+  /** Bar the manager from buying tickets or being gifted any (default false). */
+  managerCannotEnter?: boolean;
+  /** Start the draw with the purchase that sells out the raffle (default false). */
+  autoDraw?: boolean;
+  /** List the raffle in the creator's manager index (default false). */
+  listInManagerIndex?: boolean;
+}
+
+export interface RaffleProgress {
+  ticketsSold: number;
+  ticketsRemaining: number;
+  secondsRemaining: BN;
+  isOver: boolean;
+  winnerDrawn: boolean;
+}
+
+export interface ClaimEligibility {
+  drawn: boolean;
+  isWinner: boolean;
+  alreadyClaimed: boolean;
+  prizeAmount: BN;
+}
+
+export interface WalletTickets {
+  tickets: number;
+  totalTickets: number;
+}
+
+export interface EntrantsPage {
+  entrants: EntrantEntry[];
+  totalEntrants: number;
+}
+
+export interface PurchaseLog {
+  raffleState: PublicKey;
+  purchases: { firstIndex: number; slot: BN }[];
+}
+
+export interface ManagerRaffleIndex {
+  manager: PublicKey;
+  createCooldown: boolean;
+  lastCreated: BN;
+  raffles: PublicKey[];
+}
+
+export interface Distribution {
+  raffleState: PublicKey;
+  totalTickets: number;
+  topHolders: { holder: PublicKey; tickets: number }[];
+  othersWallets: number;
+  othersTickets: number;
+}
+
+interface RaffleCreatedEvent {
+  raffleState: PublicKey;
+  raffleManager: PublicKey;
+  ticketPrice: BN;
+  paymentMint: PublicKey | null;
+  maxTickets: number;
+  endTime: BN;
+  termsHash: number[];
+  name: string;
+  uri: string;
+}
+
+interface RaffleReadyToDrawEvent {
+  raffleState: PublicKey;
+  commitSlot: BN | null;
+}
+
+interface RaffleCancelledEvent {
+  raffleState: PublicKey;
+  raffleManager: PublicKey;
+  ticketsSold: number;
+}
+
+interface RafflePausedEvent {
+  raffleState: PublicKey;
+  raffleManager: PublicKey;
+}
+
+interface ManagerTransferredEvent {
+  raffleState: PublicKey;
+  oldManager: PublicKey;
+  newManager: PublicKey;
+}
+
+interface PrizeNftDepositedEvent {
+  raffleState: PublicKey;
+  prizeNftMint: PublicKey;
+}
+
+interface PrizeTokensDepositedEvent {
+  raffleState: PublicKey;
+  prizeTokenMint: PublicKey;
+  amount: BN;
+}
+
+interface EndTimeExtendedEvent {
+  raffleState: PublicKey;
+  oldEndTime: BN;
+  newEndTime: BN;
+}
+
+interface PrizeClaimedEvent {
+  raffleState: PublicKey;
+  winner: PublicKey;
+  prizeAmount: BN;
+  prizeTokenAmount: BN;
+  feeAmount: BN;
+}
+
+interface RevenueWithdrawnEvent {
+  raffleState: PublicKey;
+  raffleManager: PublicKey;
+  amount: BN;
+}
+
+interface ConsolationClaimedEvent {
+  raffleState: PublicKey;
+  consolationWinner: PublicKey;
+  amount: BN;
+}
+
+export interface RaffleClosedEvent {
+  raffleState: PublicKey;
+  raffleManager: PublicKey;
+  refundedLamports: BN;
+}
+
+interface WinnerRedrawRequestedEvent {
+  raffleState: PublicKey;
+  previousWinner: PublicKey | null;
 }
 
 interface WinnerDrawnEvent {
   raffleState: PublicKey;
   winner: PublicKey;
-  randomness: number[]; // Randomness from VRF (32 bytes)
+  runnerUp: PublicKey | null;
+  consolationWinner: PublicKey | null;
+  winnerTicketCount: number;
+  totalTickets: number;
+  randomness: number[]; // Randomness as delivered by the VRF or slot hash (32 bytes)
+  drawSeed: number[]; // randomness bound to the raffle, see drawSeed() (32 bytes)
 }
 
 /**
@@ -48,44 +385,238 @@ export class RaffleTestHelper {
   }
 
   /**
-   * Creates a new raffle.
+   * Creates an empty index of a manager's raffles.
+   * @param manager The manager's keypair, which must sign and pays the rent.
+   * @param createCooldown Opt in to the program's cooldown between raffles.
+   * @returns The created manager raffle index.
+   */
+  async createManagerIndex(manager: Keypair, createCooldown = false): Promise<ManagerRaffleIndex> {
+    const sig: TransactionSignature = await this.program.methods
+      .createManagerIndex(createCooldown)
+      .accounts({ manager: manager.publicKey })
+      .signers([manager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("createManagerIndex", this.connection, sig);
+
+    const managerIndex = await this.getManagerIndex(manager.publicKey);
+    assert.isTrue(managerIndex.manager.equals(manager.publicKey));
+    assert.strictEqual(managerIndex.createCooldown, createCooldown);
+    assert.isTrue(managerIndex.lastCreated.isZero());
+    assert.strictEqual(managerIndex.raffles.length, 0);
+
+    return managerIndex;
+  }
+
+  /**
+   * Creates a new raffle, first creating the owner's manager index if it has
+   * none yet.
    * @param raffleOwner The keypair that will own and manage the raffle.
-   * @param ticketPrice Price per ticket in lamports.
+   * @param ticketPrice Price per ticket in lamports, or in base units of the
+   *        payment mint for token raffles.
    * @param maxTickets Maximum number of tickets that can be sold.
    * @param deltaToEndSecs Number of seconds from now until the raffle ends.
+   * @param options Optional raffle settings; unset values use their defaults.
    * @returns The created raffle state.
    */
   async create(
     raffleOwner: Keypair,
     ticketPrice: BN,
     maxTickets: number,
-    deltaToEndSecs: number
+    deltaToEndSecs: number,
+    options: CreateRaffleOptions = {}
   ): Promise<RaffleState> {
+    const minTickets = options.minTickets ?? 0;
+    const loyaltyBonusBps = options.loyaltyBonusBps ?? 0;
+    const salesCutoffSlots = options.salesCutoffSlots ?? 0;
+    const termsHash = options.termsHash ?? Array<number>(32).fill(0);
+    const vestingSchedule = options.vestingSchedule ?? [];
+    const keeperBounty = options.keeperBounty ?? new BN(0);
+    const operatorWithdrawalCapBps = options.operatorWithdrawalCapBps ?? 0;
+    const claimWindowSecs = options.claimWindowSecs ?? 0;
+    const refundBond = options.refundBond ?? new BN(0);
+    const maxTicketsPerWallet = options.maxTicketsPerWallet ?? 0;
+    const allowlistRoot = options.allowlistRoot ?? null;
+    const maxPrize = options.maxPrize ?? new BN(0);
+    const priceCurve = options.priceCurve ?? { flat: {} };
+    const name = options.name ?? "";
+    const uri = options.uri ?? "";
+    const secondChancePrizeBps = options.secondChancePrizeBps ?? 0;
+    const freeEntry = options.freeEntry ?? false;
+    const fixedPrizeLamports = options.fixedPrizeLamports ?? new BN(0);
+    const revenueMode: RevenueMode = fixedPrizeLamports.isZero()
+      ? { winnerTakesPool: {} }
+      : { managerKeepsRevenue: {} };
+    const paymentMint = options.paymentMint ?? null;
+    const acceptedMints = options.acceptedMints ?? [];
+    const recordPurchases = options.recordPurchases ?? false;
+    const randomnessSource = options.randomnessSource ?? { vrf: {} };
+    const closeAuthority = options.closeAuthority ?? null;
+    const drawDelaySecs = options.drawDelaySecs ?? 0;
+    const managerCannotEnter = options.managerCannotEnter ?? false;
+    const autoDraw = options.autoDraw ?? false;
+    const listInManagerIndex = options.listInManagerIndex ?? false;
+    const feeBps = options.feeBps ?? 0;
     const now = Math.floor(Date.now() / 1000);
     const endTime = new BN(now + deltaToEndSecs);
     const [pda, bump] = this.pda(raffleOwner.publicKey, ticketPrice, maxTickets, endTime);
     console.log(`Raffle PDA: ${pda.toBase58()}, bump: ${bump}`);
 
+    // Every raffle is counted in its manager's index
+    const managerIndex = this.managerIndexPda(raffleOwner.publicKey);
+    if ((await this.connection.getAccountInfo(managerIndex, "confirmed")) === null) {
+      await this.createManagerIndex(raffleOwner);
+    }
+
     const sig: TransactionSignature = await this.program.methods
-      .createRaffle(ticketPrice, maxTickets, endTime)
+      .createRaffle(
+        ticketPrice,
+        maxTickets,
+        minTickets,
+        endTime,
+        loyaltyBonusBps,
+        new BN(salesCutoffSlots),
+        termsHash,
+        vestingSchedule,
+        keeperBounty,
+        operatorWithdrawalCapBps,
+        claimWindowSecs,
+        refundBond,
+        maxTicketsPerWallet,
+        allowlistRoot,
+        maxPrize,
+        priceCurve,
+        name,
+        uri,
+        secondChancePrizeBps,
+        freeEntry,
+        revenueMode,
+        fixedPrizeLamports,
+        randomnessSource,
+        closeAuthority,
+        drawDelaySecs,
+        managerCannotEnter,
+        autoDraw,
+        feeBps,
+        acceptedMints,
+        listInManagerIndex
+      )
       .accounts({
         raffleOwner: raffleOwner.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: pda,
+        ...(paymentMint && {
+          paymentMint,
+          escrowTokenAccount: associatedTokenAddress(pda, paymentMint),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        }),
+        ...(recordPurchases && { purchaseLog: this.purchaseLogPda(pda) }),
+        managerIndex,
       })
+      // Each accepted mint, followed by the escrow created for it
+      .remainingAccounts(
+        acceptedMints.flatMap(({ mint }) => [
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: associatedTokenAddress(pda, mint), isSigner: false, isWritable: true },
+        ])
+      )
       .signers([raffleOwner])
       .rpc({ commitment: "confirmed" });
 
     await printLogs("createRaffle", this.connection, sig);
 
+    const event = await this.getEvent<RaffleCreatedEvent>(sig, "raffleCreatedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(pda));
+    assert.isTrue(event.raffleManager.equals(raffleOwner.publicKey));
+    assert.isTrue(event.ticketPrice.eq(ticketPrice));
+    assert.deepEqual(event.paymentMint, paymentMint);
+    assert.strictEqual(event.maxTickets, maxTickets);
+    assert.isTrue(event.endTime.eq(endTime));
+    assert.deepEqual(event.termsHash, termsHash);
+    assert.strictEqual(event.name, name);
+    assert.strictEqual(event.uri, uri);
+
     const state = await this.getState(pda);
     assert.isTrue(state.raffleManager.equals(raffleOwner.publicKey));
+    assert.isTrue(state.creator.equals(raffleOwner.publicKey));
+    assert.isNull(state.pendingManager);
+    assert.deepEqual(state.closeAuthority, closeAuthority);
     assert.isTrue(state.ticketPrice.eq(ticketPrice));
+    if (priceCurve.linearStep) {
+      assert.isTrue(state.priceCurve.linearStep.increment.eq(priceCurve.linearStep.increment));
+    } else {
+      assert.isDefined(state.priceCurve.flat);
+    }
+    assert.deepEqual(state.paymentMint, paymentMint);
+    assert.strictEqual(state.acceptedMints.length, acceptedMints.length);
+    for (let i = 0; i < acceptedMints.length; i++) {
+      assert.isTrue(state.acceptedMints[i].mint.equals(acceptedMints[i].mint));
+      assert.isTrue(state.acceptedMints[i].ticketPrice.eq(acceptedMints[i].ticketPrice));
+    }
+    assert.isNull(state.prizeNftMint);
+    assert.isNull(state.prizeTokenMint);
+    assert.isTrue(state.prizeTokenAmount.isZero());
     assert.strictEqual(state.maxTickets, maxTickets);
+    assert.strictEqual(state.minTickets, minTickets);
+    // A free raffle admits one entry per wallet whatever the limit asked for
+    assert.strictEqual(state.maxTicketsPerWallet, freeEntry ? 1 : maxTicketsPerWallet);
+    assert.deepEqual(state.allowlistRoot, allowlistRoot);
     assert.isTrue(state.endTime.eq(endTime));
+    assert.isTrue(state.originalEndTime.eq(endTime));
     assert.isNull(state.winnerIndex);
+    assert.isNull(state.winner);
+    assert.isNull(state.runnerUpIndex);
+    assert.isNull(state.consolationWinnerIndex);
+    assert.strictEqual(state.claimWindowSecs, claimWindowSecs);
+    assert.strictEqual(state.drawDelaySecs, drawDelaySecs);
+    assert.strictEqual(state.managerCannotEnter, managerCannotEnter);
+    assert.strictEqual(state.autoDraw, autoDraw);
+    assert.isNull(state.claimDeadline);
     assert.isFalse(state.drawWinnerStarted);
+    assert.deepEqual(Object.keys(state.randomnessSource), Object.keys(randomnessSource));
+    assert.isNull(state.commitSlot);
     assert.isFalse(state.claimed);
+    assert.isFalse(state.consolationClaimed);
+    assert.isFalse(state.refundsOpen);
+    assert.isFalse(state.cancelled);
+    assert.isFalse(state.paused);
+    assert.strictEqual(state.freeEntry, freeEntry);
+    assert.strictEqual(state.recordsPurchases, recordPurchases);
+    if (recordPurchases) {
+      const purchaseLog = await this.getPurchaseLog(pda);
+      assert.isTrue(purchaseLog.raffleState.equals(pda));
+      assert.strictEqual(purchaseLog.purchases.length, 0);
+    }
+    assert.strictEqual(state.listedInManagerIndex, listInManagerIndex);
+    const { raffles, lastCreated } = await this.getManagerIndex(raffleOwner.publicKey);
+    assert.strictEqual(raffles.some((raffle) => raffle.equals(pda)), listInManagerIndex);
+    assert.isTrue(lastCreated.eq(state.createdAt));
+    assert.strictEqual(state.loyaltyBonusBps, loyaltyBonusBps);
+    assert.strictEqual(state.secondChancePrizeBps, secondChancePrizeBps);
+    assert.strictEqual(state.feeBps, feeBps);
+    assert.isTrue(state.totalCollected.isZero());
+    assert.isTrue(state.keeperBounty.eq(keeperBounty));
+    assert.strictEqual(state.operatorWithdrawalCapBps, operatorWithdrawalCapBps);
+    assert.isTrue(state.operatorWithdrawn.isZero());
+    assert.isTrue(state.refundBond.eq(refundBond));
+    assert.deepEqual(Object.keys(state.revenueMode), Object.keys(revenueMode));
+    assert.isTrue(state.fixedPrizeLamports.eq(fixedPrizeLamports));
+    assert.isFalse(state.revenueWithdrawn);
+    // Slots are converted to seconds at the nominal 400ms slot time
+    const cutoffSecs = Math.floor((salesCutoffSlots * 400) / 1000);
+    assert.isTrue(state.salesCutoffTime.eq(endTime.subn(cutoffSecs)));
+    assert.deepEqual(state.termsHash, termsHash);
+    assert.strictEqual(state.name, name);
+    assert.strictEqual(state.uri, uri);
+    assert.strictEqual(state.vestingSchedule.length, vestingSchedule.length);
+    for (let i = 0; i < vestingSchedule.length; i++) {
+      assert.isTrue(state.vestingSchedule[i].unlockTime.eq(vestingSchedule[i].unlockTime));
+      assert.strictEqual(state.vestingSchedule[i].bps, vestingSchedule[i].bps);
+    }
+    assert.strictEqual(state.claimedBps, 0);
+    assert.strictEqual(state.ticketsSold, 0);
     assert.strictEqual(state.entrants.length, 0);
 
     return state;
@@ -96,15 +627,38 @@ export class RaffleTestHelper {
    * @param raffleState The PDA of the raffle state account.
    * @param buyer The keypair buying the tickets.
    * @param numTickets Number of tickets to buy.
+   * @param mintIndex Index of the accepted mint to pay in, or null for the
+   *        payment mint or lamports.
    * @returns The updated raffle state.
    */
-  async buyTickets(raffleState: PublicKey, buyer: Keypair, numTickets = 1): Promise<RaffleState> {
+  async buyTickets(
+    raffleState: PublicKey,
+    buyer: Keypair,
+    numTickets = 1,
+    recipient: PublicKey | null = null,
+    proof: number[][] = [],
+    mintIndex: number | null = null
+  ): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
+    // An index past the accepted mints falls back to the payment mint's
+    // accounts, for the program to reject the index itself
+    const acceptedMint =
+      mintIndex === null ? null : (before.acceptedMints[mintIndex]?.mint ?? null);
+
     const sig = await this.program.methods
-      .buyTickets(numTickets)
+      .buyTickets(numTickets, recipient, proof, mintIndex)
       .accounts({
         buyer: buyer.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: raffleState,
+        ...(acceptedMint
+          ? {
+              escrowTokenAccount: associatedTokenAddress(raffleState, acceptedMint),
+              buyerTokenAccount: associatedTokenAddress(buyer.publicKey, acceptedMint),
+              tokenProgram: TOKEN_PROGRAM_ID,
+            }
+          : await this.tokenAccounts(raffleState, "buyerTokenAccount", buyer.publicKey)),
+        ...(await this.purchaseLogAccount(raffleState)),
       })
       .signers([buyer])
       .rpc({ commitment: "confirmed" });
@@ -112,203 +666,1030 @@ export class RaffleTestHelper {
     await printLogs("buyTickets", this.connection, sig);
 
     const state = await this.getState(raffleState);
-    assert.isAtLeast(state.entrants.length, numTickets);
-    const start = state.entrants.length - numTickets;
-    const end = state.entrants.length;
-    for (let i = start; i < end; i++) {
-      assert.isTrue(state.entrants[i].equals(buyer.publicKey));
+    assert.strictEqual(state.ticketsSold, before.ticketsSold + numTickets);
+    // A wallet buying again adds to its entry rather than a new one
+    const entrant = recipient ?? buyer.publicKey;
+    const isNewEntrant = ticketsOf(before, entrant) === 0;
+    assert.strictEqual(state.entrants.length, before.entrants.length + (isNewEntrant ? 1 : 0));
+    assert.strictEqual(ticketsOf(state, entrant), ticketsOf(before, entrant) + numTickets);
+    // Payments in an accepted mint stay out of the payment mint's pool
+    const paid =
+      mintIndex === null ? curvePrice(before, before.ticketsSold, numTickets) : new BN(0);
+    assert.isTrue(state.totalCollected.eq(before.totalCollected.add(paid)));
+
+    // Selling out an auto-draw raffle starts its draw
+    const readyEvent = await this.getEvent<RaffleReadyToDrawEvent>(sig, "raffleReadyToDrawEvent");
+    if (state.autoDraw && state.ticketsSold === state.maxTickets) {
+      assert.isNotNull(readyEvent);
+      assert.isTrue(readyEvent.raffleState.equals(raffleState));
+      assert.deepEqual(readyEvent.commitSlot, state.commitSlot);
+    } else {
+      assert.isNull(readyEvent);
     }
 
     return state;
   }
 
   /**
-   * Draws a winner for a raffle using VRF.
-   * Waits for the callback to be executed by the VRF program.
+   * Withdraws operator funds from a raffle's pool to its manager.
    * @param raffleState The PDA of the raffle state account.
-   * @returns The updated raffle state with winner selected.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @param amount Lamports, or base units of the payment mint, to withdraw.
+   * @returns The updated raffle state.
    */
-  async drawWinner(raffleState: PublicKey): Promise<RaffleState> {
-    console.log("drawWinner starting");
-
-    // Start listening for the callback before calling drawWinner to avoid
-    // missing the event.
-    const callbackPromise = this.waitForDrawWinnerCallback(raffleState);
+  async withdrawOperatorFunds(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    amount: BN
+  ): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
 
     const sig: TransactionSignature = await this.program.methods
-      .drawWinner()
+      .withdrawOperatorFunds(amount)
       .accounts({
-        oraclePayer: this.program.provider.publicKey,
+        raffleManager: raffleManager.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: raffleState,
+        ...(await this.tokenAccounts(
+          raffleState,
+          "managerTokenAccount",
+          raffleManager.publicKey
+        )),
       })
+      .signers([raffleManager])
       .rpc({ commitment: "confirmed" });
 
-    await printLogs("drawWinner", this.connection, sig);
-
-    let event: WinnerDrawnEvent | null = null;
-    try {
-      let callbackSig: TransactionSignature;
-      [event, callbackSig] = await callbackPromise;
-      await printLogs("drawWinnerCallback", this.connection, callbackSig);
-    } catch (e) {
-      // If a timeout happens, we may have just missed the winnerDrawnEvent, so
-      // we'll check the state directly before failing.
-      console.warn(e);
-    }
+    await printLogs("withdrawOperatorFunds", this.connection, sig);
 
     const state = await this.getState(raffleState);
-    assert.isTrue(state.drawWinnerStarted);
-    assert.isNotNull(state.winnerIndex);
-    assert.isTrue(state.winnerIndex >= 0 && state.winnerIndex < state.entrants.length);
-    if (event) {
-      const calcIndex = vrf_random_u64(event.randomness).modn(state.entrants.length);
-      assert.strictEqual(calcIndex, state.winnerIndex);
-    }
+    assert.isTrue(state.operatorWithdrawn.eq(before.operatorWithdrawn.add(amount)));
 
     return state;
   }
 
   /**
-   * Creates a drawWinner instruction (for testing purposes).
+   * Withdraws the ticket revenue of a drawn raffle whose manager keeps it.
    * @param raffleState The PDA of the raffle state account.
-   * @returns The instruction to be used in a transaction.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @returns The updated raffle state.
    */
-  async drawWinnerIX(raffleState: PublicKey): Promise<TransactionInstruction> {
-    return this.program.methods
-      .drawWinner()
+  async withdrawRevenue(raffleState: PublicKey, raffleManager: Keypair): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
+
+    const sig: TransactionSignature = await this.program.methods
+      .withdrawRevenue()
       .accounts({
-        oraclePayer: this.program.provider.publicKey,
+        raffleManager: raffleManager.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: raffleState,
       })
-      .instruction();
+      .signers([raffleManager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("withdrawRevenue", this.connection, sig);
+
+    // The keeper bounty and operator withdrawals were already paid out
+    const revenue = before.totalCollected.sub(before.keeperBounty).sub(before.operatorWithdrawn);
+    const event = await this.getEvent<RevenueWithdrawnEvent>(sig, "revenueWithdrawnEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.raffleManager.equals(raffleManager.publicKey));
+    assert.isTrue(event.amount.eq(revenue));
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.revenueWithdrawn);
+
+    return state;
   }
 
   /**
-   * Calls drawWinnerCallback directly (for negative testing only).
-   * This function is only for negative testing purposes. drawWinnerCallback can
-   * only be executed by CPI from the VRF program. The address of the
-   * vrf_program_identity is tested last in the Rust code, so all the
-   * constraints are testable.
+   * Cancels a raffle, opening refunds.
    * @param raffleState The PDA of the raffle state account.
-   * @param prepend_ixs Optional instructions to prepend to the transaction.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @returns The updated raffle state.
    */
-  async drawWinnerCallback(
-    raffleState: PublicKey,
-    prepend_ixs: TransactionInstruction[] = []
-  ): Promise<void> {
-    const invalid_vrf_program_identity = this.program.provider.publicKey;
-
-    await this.program.methods
-      .drawWinnerCallback(Array.from(new Uint8Array(32)))
+  async cancel(raffleState: PublicKey, raffleManager: Keypair): Promise<RaffleState> {
+    const sig: TransactionSignature = await this.program.methods
+      .cancelRaffle()
       .accounts({
-        vrfProgramIdentity: invalid_vrf_program_identity,
+        raffleManager: raffleManager.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: raffleState,
       })
-      .preInstructions(prepend_ixs)
+      .signers([raffleManager])
       .rpc({ commitment: "confirmed" });
 
-    assert.fail("drawWinnerCallback should have failed");
+    await printLogs("cancelRaffle", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.cancelled);
+
+    const event = await this.getEvent<RaffleCancelledEvent>(sig, "raffleCancelledEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.raffleManager.equals(raffleManager.publicKey));
+    assert.strictEqual(event.ticketsSold, state.ticketsSold);
+
+    return state;
   }
 
   /**
-   * Listens for the winnerDrawnEvent to detect when drawWinnerCallback has been executed.
+   * Pauses or resumes a raffle's ticket sales.
    * @param raffleState The PDA of the raffle state account.
-   * @returns Promise that resolves with the callback transaction signature.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @param paused True to pause with pauseRaffle, false to resume with
+   *        unpauseRaffle.
+   * @returns The updated raffle state.
    */
-  private async waitForDrawWinnerCallback(
-    raffleState: PublicKey
-  ): Promise<[WinnerDrawnEvent, TransactionSignature]> {
-    const timeoutMs = 8000;
-    let listenerId: number | null = null;
-    let cancelled = false;
+  async setPaused(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    paused: boolean
+  ): Promise<RaffleState> {
+    const method = paused
+      ? this.program.methods.pauseRaffle()
+      : this.program.methods.unpauseRaffle();
+    const sig: TransactionSignature = await method
+      .accounts({
+        raffleManager: raffleManager.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .signers([raffleManager])
+      .rpc({ commitment: "confirmed" });
 
-    async function cancelListener(connection: Connection): Promise<void> {
-      cancelled = true;
-      if (listenerId !== null) {
-        await connection.removeOnLogsListener(listenerId);
-        listenerId = null;
-      }
-    }
+    const name = paused ? "pauseRaffle" : "unpauseRaffle";
+    await printLogs(name, this.connection, sig);
 
-    return new Promise((resolve, reject) => {
-      const timeout = setTimeout(() => {
-        void cancelListener(this.connection);
-        reject(new Error("Timeout waiting for winnerDrawnEvent"));
-      }, timeoutMs);
+    const state = await this.getState(raffleState);
+    assert.strictEqual(state.paused, paused);
 
-      // We have to use the more complicated connection.onLogs listener instead
-      // of program.addEventListener, because the drawWinnerCallback instruction
-      // is called via CPI from the VRF program. I'm not clear if the limitation
-      // is due to CPI use or if it's because the transaction is not sent by us.
-      listenerId = this.connection.onLogs(
-        this.program.programId,
-        (logsResult) => {
-          if (cancelled || logsResult.err) {
-            return;
-          }
-          const events = this.eventParser.parseLogs(logsResult.logs, false);
-          for (const event of events) {
-            if (event.name === "winnerDrawnEvent") {
-              const e = event.data as WinnerDrawnEvent;
-              if (!raffleState.equals(e.raffleState)) {
-                continue;
-              }
-              clearTimeout(timeout);
-              void cancelListener(this.connection);
-              console.log(
-                `winnerDrawnEvent:\n  tx: ${
-                  logsResult.signature
-                }\n  pda: ${e.raffleState.toBase58()}\n  winner: ${e.winner.toBase58()}\n  randomness: 0x${Buffer.from(
-                  e.randomness
-                ).toString("hex")}`
-              );
-              return resolve([e, logsResult.signature]);
-            }
-          }
-        },
-        "confirmed"
-      );
-    });
+    const eventName = paused ? "rafflePausedEvent" : "raffleUnpausedEvent";
+    const event = await this.getEvent<RafflePausedEvent>(sig, eventName);
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.raffleManager.equals(raffleManager.publicKey));
+
+    return state;
   }
 
   /**
-   * Claims the prize for a winning raffle ticket.
+   * Extends the end time of a raffle that is still on sale.
    * @param raffleState The PDA of the raffle state account.
-   * @param winner The public key of the winner.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @param newEndTime The new end time as a Unix timestamp (seconds).
    * @returns The updated raffle state.
    */
-  async claimPrize(raffleState: PublicKey, winner: PublicKey): Promise<RaffleState> {
-    console.log("claimPrize starting");
+  async extendEndTime(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    newEndTime: BN
+  ): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
 
     const sig: TransactionSignature = await this.program.methods
-      .claimPrize()
+      .extendEndTime(newEndTime)
       .accounts({
-        winner: winner,
+        raffleManager: raffleManager.publicKey,
         // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
         raffleState: raffleState,
       })
+      .signers([raffleManager])
       .rpc({ commitment: "confirmed" });
 
-    await printLogs("claimPrize", this.connection, sig);
+    await printLogs("extendEndTime", this.connection, sig);
 
     const state = await this.getState(raffleState);
-    assert.isTrue(state.claimed);
-    assert.isNotNull(state.winnerIndex);
-    assert.isTrue(winner.equals(state.entrants[state.winnerIndex]));
+    assert.isTrue(state.endTime.eq(newEndTime));
+    assert.isTrue(state.originalEndTime.eq(before.originalEndTime));
+    const extension = newEndTime.sub(before.endTime);
+    assert.isTrue(state.salesCutoffTime.eq(before.salesCutoffTime.add(extension)));
+
+    const event = await this.getEvent<EndTimeExtendedEvent>(sig, "endTimeExtendedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.oldEndTime.eq(before.endTime));
+    assert.isTrue(event.newEndTime.eq(newEndTime));
 
     return state;
   }
 
   /**
-   * Closes a raffle and returns the rent to the manager.
+   * Deposits an NFT held by the raffle manager as the raffle's prize.
+   * @param raffleState The PDA of the raffle state account.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @param prizeNftMint The NFT's mint; the manager's associated token account
+   *   for it must hold the NFT.
+   * @returns The updated raffle state.
+   */
+  async depositPrizeNft(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    prizeNftMint: PublicKey
+  ): Promise<RaffleState> {
+    const prizeNftEscrow = associatedTokenAddress(raffleState, prizeNftMint);
+
+    const sig: TransactionSignature = await this.program.methods
+      .depositPrizeNft()
+      .accounts({
+        raffleManager: raffleManager.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+        prizeNftMint,
+        managerNftAccount: associatedTokenAddress(raffleManager.publicKey, prizeNftMint),
+        prizeNftEscrow,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([raffleManager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("depositPrizeNft", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.prizeNftMint.equals(prizeNftMint));
+    assert.strictEqual((await tokenBalance(this.connection, prizeNftEscrow)).toNumber(), 1);
+
+    const event = await this.getEvent<PrizeNftDepositedEvent>(sig, "prizeNftDepositedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.prizeNftMint.equals(prizeNftMint));
+
+    return state;
+  }
+
+  /**
+   * Deposits SPL tokens held by the raffle manager as part of the raffle's
+   * prize, paid to the winner along with the lamport prize.
+   * @param raffleState The PDA of the raffle state account.
+   * @param raffleManager The raffle manager's keypair, which must sign.
+   * @param prizeTokenMint The tokens' mint; the manager's associated token
+   *   account for it must hold at least `amount`.
+   * @param amount Base units of the mint to deposit.
+   * @returns The updated raffle state.
+   */
+  async depositPrizeTokens(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    prizeTokenMint: PublicKey,
+    amount: BN
+  ): Promise<RaffleState> {
+    const prizeTokenEscrow = associatedTokenAddress(raffleState, prizeTokenMint);
+
+    const sig: TransactionSignature = await this.program.methods
+      .depositPrizeTokens(amount)
+      .accounts({
+        raffleManager: raffleManager.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+        prizeTokenMint,
+        managerTokenAccount: associatedTokenAddress(raffleManager.publicKey, prizeTokenMint),
+        prizeTokenEscrow,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([raffleManager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("depositPrizeTokens", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.prizeTokenMint.equals(prizeTokenMint));
+    assert.isTrue(state.prizeTokenAmount.eq(amount));
+    assert.isTrue((await tokenBalance(this.connection, prizeTokenEscrow)).eq(amount));
+
+    const event = await this.getEvent<PrizeTokensDepositedEvent>(sig, "prizeTokensDepositedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.prizeTokenMint.equals(prizeTokenMint));
+    assert.isTrue(event.amount.eq(amount));
+
+    return state;
+  }
+
+  /**
+   * Names a new manager for a raffle, who must then accept.
+   * @param raffleState The PDA of the raffle state account.
+   * @param raffleManager The current raffle manager's keypair, which must sign.
+   * @param newManager The wallet to hand the raffle to.
+   * @returns The updated raffle state.
+   */
+  async transferManager(
+    raffleState: PublicKey,
+    raffleManager: Keypair,
+    newManager: PublicKey
+  ): Promise<RaffleState> {
+    const sig: TransactionSignature = await this.program.methods
+      .transferManager(newManager)
+      .accounts({
+        raffleManager: raffleManager.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .signers([raffleManager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("transferManager", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.pendingManager.equals(newManager));
+    assert.isTrue(state.raffleManager.equals(raffleManager.publicKey));
+
+    return state;
+  }
+
+  /**
+   * Takes over a raffle as its pending manager.
+   * @param raffleState The PDA of the raffle state account.
+   * @param newManager The pending manager's keypair, which must sign.
+   * @returns The updated raffle state.
+   */
+  async acceptManager(raffleState: PublicKey, newManager: Keypair): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
+
+    const sig: TransactionSignature = await this.program.methods
+      .acceptManager()
+      .accounts({
+        newManager: newManager.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .signers([newManager])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("acceptManager", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.raffleManager.equals(newManager.publicKey));
+    assert.isNull(state.pendingManager);
+    assert.isTrue(state.creator.equals(before.creator));
+
+    const event = await this.getEvent<ManagerTransferredEvent>(sig, "managerTransferredEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.oldManager.equals(before.raffleManager));
+    assert.isTrue(event.newManager.equals(newManager.publicKey));
+
+    return state;
+  }
+
+  /**
+   * Draws a winner for a raffle using VRF.
+   * Waits for the callback to be executed by the VRF program.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The updated raffle state with winner selected.
+   */
+  async drawWinner(raffleState: PublicKey): Promise<RaffleState> {
+    console.log("drawWinner starting");
+
+    // Start listening for the callback before calling drawWinner to avoid
+    // missing the event.
+    const callbackPromise = this.waitForDrawWinnerCallback(raffleState);
+
+    const sig: TransactionSignature = await this.program.methods
+      .drawWinner()
+      .accounts({
+        oraclePayer: this.program.provider.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("drawWinner", this.connection, sig);
+
+    let event: WinnerDrawnEvent | null = null;
+    try {
+      let callbackSig: TransactionSignature;
+      [event, callbackSig] = await callbackPromise;
+      await printLogs("drawWinnerCallback", this.connection, callbackSig);
+    } catch (e) {
+      // If a timeout happens, we may have just missed the winnerDrawnEvent, so
+      // we'll check the state directly before failing.
+      console.warn(e);
+    }
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.drawWinnerStarted);
+    assert.isNotNull(state.winnerIndex);
+    assert.isTrue(state.winnerIndex >= 0 && state.winnerIndex < state.entrants.length);
+    assert.isTrue(state.winner.equals(state.entrants[state.winnerIndex].buyer));
+    if (event) {
+      assert.deepEqual(event.drawSeed, drawSeed(event.randomness, raffleState));
+      const calcTicket = select_winner_index(event.drawSeed, state.ticketsSold);
+      assert.strictEqual(entryOfTicket(state.entrants, calcTicket), state.winnerIndex);
+      assert.deepEqual(state.randomness, event.drawSeed);
+      const runnerUp =
+        state.runnerUpIndex === null ? null : state.entrants[state.runnerUpIndex].buyer;
+      assert.deepEqual(event.runnerUp, runnerUp);
+      const consolationWinner =
+        state.consolationWinnerIndex === null
+          ? null
+          : state.entrants[state.consolationWinnerIndex].buyer;
+      assert.deepEqual(event.consolationWinner, consolationWinner);
+      // The winner's odds before the draw
+      assert.strictEqual(event.winnerTicketCount, state.entrants[state.winnerIndex].count);
+      assert.strictEqual(event.totalTickets, state.ticketsSold);
+    }
+    // A runner-up, if any, is drawn from another wallet's tickets
+    if (state.claimWindowSecs === 0) {
+      assert.isNull(state.runnerUpIndex);
+      assert.isNull(state.claimDeadline);
+    } else {
+      assert.isNotNull(state.claimDeadline);
+      if (state.runnerUpIndex !== null) {
+        const winner = state.entrants[state.winnerIndex].buyer;
+        assert.isFalse(winner.equals(state.entrants[state.runnerUpIndex].buyer));
+      }
+    }
+    // So is a consolation winner, when the raffle has a second-chance prize,
+    // who can't be the runner-up either
+    if (state.secondChancePrizeBps === 0) {
+      assert.isNull(state.consolationWinnerIndex);
+    } else if (state.consolationWinnerIndex !== null) {
+      const winner = state.entrants[state.winnerIndex].buyer;
+      const consolationWinner = state.entrants[state.consolationWinnerIndex].buyer;
+      assert.isFalse(winner.equals(consolationWinner));
+      if (state.runnerUpIndex !== null) {
+        assert.isFalse(state.entrants[state.runnerUpIndex].buyer.equals(consolationWinner));
+      }
+    }
+
+    return state;
+  }
+
+  /**
+   * Passes over a winner whose claim window lapsed and draws a new one using
+   * VRF. Waits for the callback to be executed by the VRF program.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The updated raffle state with the new winner selected.
+   */
+  async redrawWinner(raffleState: PublicKey): Promise<RaffleState> {
+    console.log("redrawWinner starting");
+
+    const before = await this.getState(raffleState);
+    const callbackPromise = this.waitForDrawWinnerCallback(raffleState);
+
+    const sig: TransactionSignature = await this.program.methods
+      .redrawWinner()
+      .accounts({
+        oraclePayer: this.program.provider.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("redrawWinner", this.connection, sig);
+
+    const event = await this.getEvent<WinnerRedrawRequestedEvent>(
+      sig,
+      "winnerRedrawRequestedEvent"
+    );
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.previousWinner.equals(before.winner));
+
+    try {
+      const [, callbackSig] = await callbackPromise;
+      await printLogs("drawWinnerCallback", this.connection, callbackSig);
+    } catch (e) {
+      // As in drawWinner, the event may have been missed; check the state
+      console.warn(e);
+    }
+
+    // The new winner holds neither the passed-over winner's tickets nor the
+    // consolation winner's, which is kept from the first draw
+    const state = await this.getState(raffleState);
+    assert.isNotNull(state.winnerIndex);
+    assert.isTrue(state.winner.equals(state.entrants[state.winnerIndex].buyer));
+    assert.isTrue(state.previousWinner.equals(before.winner));
+    assert.isFalse(state.winner.equals(before.winner));
+    assert.strictEqual(state.consolationWinnerIndex, before.consolationWinnerIndex);
+    if (state.consolationWinnerIndex !== null) {
+      assert.isFalse(state.winner.equals(state.entrants[state.consolationWinnerIndex].buyer));
+    }
+    assert.isNotNull(state.claimDeadline);
+
+    return state;
+  }
+
+  /**
+   * Creates a drawWinner instruction (for testing purposes).
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The instruction to be used in a transaction.
+   */
+  async drawWinnerIX(raffleState: PublicKey): Promise<TransactionInstruction> {
+    return this.program.methods
+      .drawWinner()
+      .accounts({
+        oraclePayer: this.program.provider.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .instruction();
+  }
+
+  /**
+   * Calls drawWinnerFromSlotHashes once. The first call commits to a slot,
+   * a later call once that slot is produced draws the winner, and a call
+   * after the slot expired from the SlotHashes sysvar commits to a new one.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The updated raffle state.
+   */
+  async drawWinnerFromSlotHashes(raffleState: PublicKey): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
+    const sig: TransactionSignature = await this.program.methods
+      .drawWinnerFromSlotHashes()
+      .accounts({
+        caller: this.program.provider.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("drawWinnerFromSlotHashes", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.drawWinnerStarted);
+    assert.isNotNull(state.commitSlot);
+    if (before.commitSlot === null || !state.commitSlot.eq(before.commitSlot)) {
+      assert.isNull(state.winnerIndex);
+      return state;
+    }
+    const event = await this.getEvent<WinnerDrawnEvent>(sig, "winnerDrawnEvent");
+    assert.isNotNull(event);
+    assert.isTrue(state.winnerIndex >= 0 && state.winnerIndex < state.entrants.length);
+    assert.isTrue(state.winner.equals(event.winner));
+    assert.deepEqual(event.drawSeed, drawSeed(event.randomness, raffleState));
+    const calcTicket = select_winner_index(event.drawSeed, state.ticketsSold);
+    assert.strictEqual(entryOfTicket(state.entrants, calcTicket), state.winnerIndex);
+    assert.deepEqual(state.randomness, event.drawSeed);
+
+    return state;
+  }
+
+  /**
+   * Calls drawWinnerCallback directly (for negative testing only).
+   * This function is only for negative testing purposes. drawWinnerCallback can
+   * only be executed by CPI from the VRF program. The address of the
+   * vrf_program_identity is tested last in the Rust code, so all the
+   * constraints are testable.
+   * @param raffleState The PDA of the raffle state account.
+   * @param prepend_ixs Optional instructions to prepend to the transaction.
+   */
+  async drawWinnerCallback(
+    raffleState: PublicKey,
+    prepend_ixs: TransactionInstruction[] = []
+  ): Promise<void> {
+    const invalid_vrf_program_identity = this.program.provider.publicKey;
+
+    await this.program.methods
+      .drawWinnerCallback(Array.from(new Uint8Array(32)))
+      .accounts({
+        vrfProgramIdentity: invalid_vrf_program_identity,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .preInstructions(prepend_ixs)
+      .rpc({ commitment: "confirmed" });
+
+    assert.fail("drawWinnerCallback should have failed");
+  }
+
+  /**
+   * Listens for the winnerDrawnEvent to detect when drawWinnerCallback has been executed.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns Promise that resolves with the callback transaction signature.
+   */
+  private async waitForDrawWinnerCallback(
+    raffleState: PublicKey
+  ): Promise<[WinnerDrawnEvent, TransactionSignature]> {
+    const timeoutMs = 8000;
+    let listenerId: number | null = null;
+    let cancelled = false;
+
+    async function cancelListener(connection: Connection): Promise<void> {
+      cancelled = true;
+      if (listenerId !== null) {
+        await connection.removeOnLogsListener(listenerId);
+        listenerId = null;
+      }
+    }
+
+    return new Promise((resolve, reject) => {
+      const timeout = setTimeout(() => {
+        void cancelListener(this.connection);
+        reject(new Error("Timeout waiting for winnerDrawnEvent"));
+      }, timeoutMs);
+
+      // We have to use the more complicated connection.onLogs listener instead
+      // of program.addEventListener, because the drawWinnerCallback instruction
+      // is called via CPI from the VRF program. I'm not clear if the limitation
+      // is due to CPI use or if it's because the transaction is not sent by us.
+      listenerId = this.connection.onLogs(
+        this.program.programId,
+        (logsResult) => {
+          if (cancelled || logsResult.err) {
+            return;
+          }
+          const events = this.eventParser.parseLogs(logsResult.logs, false);
+          for (const event of events) {
+            if (event.name === "winnerDrawnEvent") {
+              const e = event.data as WinnerDrawnEvent;
+              if (!raffleState.equals(e.raffleState)) {
+                continue;
+              }
+              clearTimeout(timeout);
+              void cancelListener(this.connection);
+              console.log(
+                `winnerDrawnEvent:\n  tx: ${
+                  logsResult.signature
+                }\n  pda: ${e.raffleState.toBase58()}\n  winner: ${e.winner.toBase58()}\n  randomness: 0x${Buffer.from(
+                  e.randomness
+                ).toString("hex")}`
+              );
+              return resolve([e, logsResult.signature]);
+            }
+          }
+        },
+        "confirmed"
+      );
+    });
+  }
+
+  /**
+   * Claims the prize for a winning raffle ticket, or the unlocked part of it
+   * when the prize vests. When the raffle has a
+   * loyalty bonus, the top ticket holders are passed as remaining accounts.
+   * @param raffleState The PDA of the raffle state account.
+   * @param winner The public key of the winner, or of the runner-up once the
+   *        winner's claim window lapsed.
+   * @param expectedPrize If given, the amount PrizeClaimedEvent must report.
+   * @returns The updated raffle state.
+   */
+  async claimPrize(
+    raffleState: PublicKey,
+    winner: PublicKey,
+    expectedPrize: BN | null = null
+  ): Promise<RaffleState> {
+    console.log("claimPrize starting");
+
+    const sig: TransactionSignature = await this.program.methods
+      .claimPrize()
+      .accounts({
+        winner: winner,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+        ...(await this.tokenAccounts(raffleState, "winnerTokenAccount", winner)),
+        ...(await this.winnerTokenAccountCreation(raffleState)),
+        ...(await this.prizeNftAccounts(raffleState, "winnerNftAccount", winner)),
+        ...(await this.prizeTokenAccounts(raffleState, "winnerPrizeTokenAccount", winner)),
+        ...(await this.feeAccount(raffleState)),
+      })
+      .remainingAccounts([
+        ...(await this.loyaltyHolderMetas(raffleState)),
+        ...(await this.acceptedMintMetas(raffleState, winner)),
+      ])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("claimPrize", this.connection, sig);
+
+    const event = await this.getEvent<PrizeClaimedEvent>(sig, "prizeClaimedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.winner.equals(winner));
+    if (expectedPrize !== null) {
+      assert.isTrue(event.prizeAmount.eq(expectedPrize));
+    }
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(event.prizeTokenAmount.eq(state.prizeTokenAmount));
+    // A vested prize is only marked claimed once the last tranche is paid
+    assert.isAbove(state.claimedBps, 0);
+    assert.strictEqual(state.claimed, state.claimedBps === FULL_BPS);
+    assert.isNotNull(state.winnerIndex);
+    assert.isTrue(winner.equals(state.entrants[state.winnerIndex].buyer));
+
+    return state;
+  }
+
+  /**
+   * Claims the prize on the winner's behalf and sends it to another account.
+   * @param raffleState The PDA of the raffle state account.
+   * @param winner The winner's keypair, which must sign.
+   * @param destination The account that receives the prize; a token account
+   *        for the payment mint in token raffles.
+   * @returns The updated raffle state.
+   */
+  async claimPrizeTo(
+    raffleState: PublicKey,
+    winner: Keypair,
+    destination: PublicKey
+  ): Promise<RaffleState> {
+    console.log("claimPrizeTo starting");
+
+    const sig: TransactionSignature = await this.program.methods
+      .claimPrizeTo(destination)
+      .accounts({
+        winner: winner.publicKey,
+        recipient: destination,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+        ...(await this.tokenAccounts(raffleState)),
+        ...(await this.feeAccount(raffleState)),
+      })
+      .remainingAccounts([
+        ...(await this.loyaltyHolderMetas(raffleState)),
+        ...(await this.acceptedMintMetas(raffleState, winner.publicKey)),
+      ])
+      .signers([winner])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("claimPrizeTo", this.connection, sig);
+
+    const event = await this.getEvent<PrizeClaimedEvent>(sig, "prizeClaimedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.winner.equals(winner.publicKey));
+
+    const state = await this.getState(raffleState);
+    // A vested prize is only marked claimed once the last tranche is paid
+    assert.isAbove(state.claimedBps, 0);
+    assert.strictEqual(state.claimed, state.claimedBps === FULL_BPS);
+    assert.isNotNull(state.winnerIndex);
+    assert.isTrue(winner.publicKey.equals(state.entrants[state.winnerIndex].buyer));
+
+    return state;
+  }
+
+  /**
+   * Pays the second-chance prize to the consolation winner.
+   * @param raffleState The PDA of the raffle state account.
+   * @param consolationWinner The public key of the consolation winner.
+   * @returns The updated raffle state.
+   */
+  async claimConsolation(
+    raffleState: PublicKey,
+    consolationWinner: PublicKey
+  ): Promise<RaffleState> {
+    console.log("claimConsolation starting");
+
+    const sig: TransactionSignature = await this.program.methods
+      .claimConsolation()
+      .accounts({
+        consolationWinner,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("claimConsolation", this.connection, sig);
+
+    const event = await this.getEvent<ConsolationClaimedEvent>(sig, "consolationClaimedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.consolationWinner.equals(consolationWinner));
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.consolationClaimed);
+    assert.isNotNull(state.consolationWinnerIndex);
+    assert.isTrue(consolationWinner.equals(state.entrants[state.consolationWinnerIndex].buyer));
+
+    return state;
+  }
+
+  /**
+   * Builds the remaining accounts a claim needs for the loyalty bonus: the
+   * top ticket holders in rank order, or nothing if the raffle has no bonus
+   * or no longer exists.
+   */
+  private async loyaltyHolderMetas(raffleState: PublicKey): Promise<AccountMeta[]> {
+    // A closed raffle has no state; let the program report it
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null || state.loyaltyBonusBps === 0) {
+      return [];
+    }
+    return topTicketHolders(state.entrants).map(([pubkey]) => ({
+      pubkey,
+      isSigner: false,
+      isWritable: true,
+    }));
+  }
+
+  /**
+   * Builds the extra accounts an instruction moving a token raffle's pool
+   * needs: the escrow, the token program and, if `userAccount` is given, the
+   * user's associated token account under that name. Nothing for SOL raffles
+   * or a raffle that no longer exists.
+   */
+  /**
+   * Remaining accounts for a raffle's accepted mints: each mint, followed by
+   * its escrow and `owner`'s associated token account for the mint.
+   */
+  private async acceptedMintMetas(
+    raffleState: PublicKey,
+    owner: PublicKey
+  ): Promise<AccountMeta[]> {
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null) {
+      return [];
+    }
+    return state.acceptedMints.flatMap(({ mint }) => [
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: associatedTokenAddress(raffleState, mint), isSigner: false, isWritable: true },
+      { pubkey: associatedTokenAddress(owner, mint), isSigner: false, isWritable: true },
+    ]);
+  }
+
+  private async tokenAccounts(
+    raffleState: PublicKey,
+    userAccount?: string,
+    user?: PublicKey
+  ): Promise<Record<string, PublicKey>> {
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null || state.paymentMint === null) {
+      return {};
+    }
+    return {
+      escrowTokenAccount: associatedTokenAddress(raffleState, state.paymentMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      ...(userAccount && { [userAccount]: associatedTokenAddress(user, state.paymentMint) }),
+    };
+  }
+
+  /**
+   * Builds the extra accounts an instruction moving a raffle's prize NFT
+   * needs: the NFT escrow, the token program and `userAccount`, the user's
+   * associated token account for the NFT. Claiming also gets the accounts to
+   * create the winner's one, paid for by the provider wallet. Nothing for
+   * raffles without a prize NFT.
+   */
+  private async prizeNftAccounts(
+    raffleState: PublicKey,
+    userAccount: "winnerNftAccount" | "managerTokenAccount",
+    user: PublicKey
+  ): Promise<Record<string, PublicKey>> {
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null || state.prizeNftMint === null) {
+      return {};
+    }
+    return {
+      prizeNftEscrow: associatedTokenAddress(raffleState, state.prizeNftMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      [userAccount]: associatedTokenAddress(user, state.prizeNftMint),
+      ...(userAccount === "winnerNftAccount" && {
+        payer: this.program.provider.publicKey,
+        prizeNftMint: state.prizeNftMint,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }),
+    };
+  }
+
+  /**
+   * Builds the extra accounts an instruction moving a raffle's prize tokens
+   * needs, like `prizeNftAccounts` does for a prize NFT. Nothing for raffles
+   * without a token prize.
+   */
+  private async prizeTokenAccounts(
+    raffleState: PublicKey,
+    userAccount: "winnerPrizeTokenAccount" | "managerTokenAccount",
+    user: PublicKey
+  ): Promise<Record<string, PublicKey>> {
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null || state.prizeTokenMint === null) {
+      return {};
+    }
+    return {
+      prizeTokenEscrow: associatedTokenAddress(raffleState, state.prizeTokenMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      [userAccount]: associatedTokenAddress(user, state.prizeTokenMint),
+      ...(userAccount === "winnerPrizeTokenAccount" && {
+        payer: this.program.provider.publicKey,
+        prizeTokenMint: state.prizeTokenMint,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }),
+    };
+  }
+
+  /**
+   * Builds the accounts claim_prize needs to create the winner's token account
+   * in a token raffle, paid for by the provider wallet. Nothing for SOL raffles.
+   */
+  private async winnerTokenAccountCreation(
+    raffleState: PublicKey
+  ): Promise<Record<string, PublicKey>> {
+    const state = await this.program.account.raffleState.fetchNullable(raffleState, "confirmed");
+    if (state === null || state.paymentMint === null) {
+      return {};
+    }
+    return {
+      payer: this.program.provider.publicKey,
+      paymentMint: state.paymentMint,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+  }
+
+  /**
+   * Builds the raffle manager account receiving a raffle's fee, or nothing
+   * for a raffle without one.
+   */
+  private async feeAccount(raffleState: PublicKey): Promise<{ raffleManager?: PublicKey }> {
+    const state = await this.getState(raffleState);
+    return state.feeBps > 0 ? { raffleManager: state.raffleManager } : {};
+  }
+
+  /**
+   * Turns an expired, undrawn raffle into a refund pool.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The updated raffle state.
+   */
+  async openRefundsAfterExpiry(raffleState: PublicKey): Promise<RaffleState> {
+    const sig: TransactionSignature = await this.program.methods
+      .openRefundsAfterExpiry()
+      .accounts({
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("openRefundsAfterExpiry", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isTrue(state.refundsOpen);
+
+    return state;
+  }
+
+  /**
+   * Refunds every ticket an entrant holds in a raffle whose refunds are open.
+   * Tickets paid in an accepted mint are refunded in that mint, creating the
+   * entrant's token account for it if needed.
+   * @param raffleState The PDA of the raffle state account.
+   * @param entrant The entrant's keypair, which must sign.
+   * @returns The updated raffle state.
+   */
+  async claimRefund(raffleState: PublicKey, entrant: Keypair): Promise<RaffleState> {
+    const before = await this.getState(raffleState);
+    const tickets = ticketsOf(before, entrant.publicKey);
+    const acceptedTickets = acceptedMintTicketsOf(before, entrant.publicKey);
+    const paymentTickets = tickets - acceptedTickets.reduce((sum, count) => sum + count, 0);
+
+    const sig: TransactionSignature = await this.program.methods
+      .claimRefund()
+      .accounts({
+        entrant: entrant.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+        // An entrant who paid only in accepted mints needs no payment mint account
+        ...(await this.tokenAccounts(
+          raffleState,
+          paymentTickets > 0 ? "entrantTokenAccount" : undefined,
+          entrant.publicKey
+        )),
+        ...(before.acceptedMints.length > 0 && {
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }),
+      })
+      .remainingAccounts(await this.acceptedMintMetas(raffleState, entrant.publicKey))
+      .signers([entrant])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("claimRefund", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    assert.isFalse(state.entrants.some((entry) => entry.buyer.equals(entrant.publicKey)));
+    assert.isFalse(
+      state.acceptedMintTickets.some((entry) => entry.buyer.equals(entrant.publicKey))
+    );
+    assert.strictEqual(state.ticketsSold, before.ticketsSold - tickets);
+    // Every ticket paid in the payment mint is refunded at the average price
+    // paid in it
+    const paymentTicketsBefore = before.acceptedMintTickets.reduce(
+      (sold, entry) => entry.counts.reduce((rest, count) => rest - count, sold),
+      before.ticketsSold
+    );
+    const paid =
+      paymentTickets === 0
+        ? new BN(0)
+        : before.totalCollected.muln(paymentTickets).divn(paymentTicketsBefore);
+    assert.isTrue(state.totalCollected.eq(before.totalCollected.sub(paid)));
+
+    return state;
+  }
+
+  /**
+   * Closes a raffle and returns the rent to the manager.
    * @param raffleState The PDA of the raffle state account.
    * @param signer The keypair signing the close transaction
    *        (raffle manager or program owner).
+   * @returns The emitted RaffleClosedEvent.
    */
-  async close(raffleState: PublicKey, signer: Keypair): Promise<void> {
+  async close(raffleState: PublicKey, signer: Keypair): Promise<RaffleClosedEvent> {
     console.log("closeRaffle starting");
 
     // Fetch the raffle state to get the raffle manager
@@ -321,11 +1702,250 @@ export class RaffleTestHelper {
         // @ts-expect-error - TypeScript doesn't recognize raffleManager in accounts
         raffleManager: state.raffleManager,
         raffleState: raffleState,
+        ...(await this.tokenAccounts(raffleState)),
+        ...(await this.purchaseLogAccount(raffleState)),
+        ...(state.listedInManagerIndex && { managerIndex: this.managerIndexPda(state.creator) }),
+        ...(await this.prizeNftAccounts(raffleState, "managerTokenAccount", state.raffleManager)),
+        ...(await this.prizeTokenAccounts(raffleState, "managerTokenAccount", state.raffleManager)),
       })
+      .remainingAccounts(await this.acceptedMintMetas(raffleState, state.raffleManager))
       .signers([signer])
       .rpc({ commitment: "confirmed" });
 
     await printLogs("closeRaffle", this.connection, sig);
+
+    const event = await this.getEvent<RaffleClosedEvent>(sig, "raffleClosedEvent");
+    assert.isNotNull(event);
+    assert.isTrue(event.raffleState.equals(raffleState));
+    assert.isTrue(event.raffleManager.equals(state.raffleManager));
+
+    return event;
+  }
+
+  /**
+   * Re-selects an out-of-range winner index. Only the program upgrade
+   * authority can do this.
+   * @param raffleState The PDA of the raffle state account.
+   * @param upgradeAuthority The program upgrade authority keypair.
+   * @returns The updated raffle state.
+   */
+  async repairWinnerIndex(raffleState: PublicKey, upgradeAuthority: Keypair): Promise<RaffleState> {
+    const sig: TransactionSignature = await this.program.methods
+      .repairWinnerIndex()
+      .accounts({
+        upgradeAuthority: upgradeAuthority.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .signers([upgradeAuthority])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("repairWinnerIndex", this.connection, sig);
+
+    const state = await this.getState(raffleState);
+    if (state.winnerIndex !== null) {
+      assert.isBelow(state.winnerIndex, state.entrants.length);
+    }
+
+    return state;
+  }
+
+  /**
+   * Snapshots the raffle's ticket distribution into its Distribution PDA.
+   * @param raffleState The PDA of the raffle state account.
+   * @param payer The keypair paying rent for the distribution account.
+   * @returns The created distribution account.
+   */
+  async snapshotDistribution(raffleState: PublicKey, payer: Keypair): Promise<Distribution> {
+    const sig: TransactionSignature = await this.program.methods
+      .snapshotDistribution()
+      .accounts({
+        payer: payer.publicKey,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+
+    await printLogs("snapshotDistribution", this.connection, sig);
+
+    const distribution = await this.program.account.distribution.fetch(
+      this.distributionPda(raffleState),
+      "confirmed"
+    );
+    assert.isTrue(distribution.raffleState.equals(raffleState));
+    assert.isAtMost(distribution.topHolders.length, DISTRIBUTION_TOP_N);
+
+    return distribution;
+  }
+
+  /**
+   * Reads the status of several raffles in one simulated call.
+   * @param raffleStates The PDAs of the raffle state accounts.
+   * @returns One status per raffle, in the order given.
+   */
+  async getStatusesBatch(raffleStates: PublicKey[]): Promise<RaffleStatus[]> {
+    const statuses: Buffer = await this.program.methods
+      .getStatusesBatch()
+      .remainingAccounts(
+        raffleStates.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+      )
+      .view({ commitment: "confirmed" });
+    return Array.from(statuses);
+  }
+
+  /**
+   * Reads how far a raffle's ticket sales have got in a simulated call.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns Tickets and time left, and whether the raffle is over or drawn.
+   */
+  async getRaffleProgress(raffleState: PublicKey): Promise<RaffleProgress> {
+    return this.program.methods
+      .getRaffleProgress()
+      .accounts({
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .view({ commitment: "confirmed" });
+  }
+
+  /**
+   * Reads whether a wallet can claim a raffle's prize in a simulated call.
+   * @param raffleState The PDA of the raffle state account.
+   * @param claimant The wallet to check.
+   * @returns Whether the raffle was drawn, won by claimant, already claimed,
+   *          and the prize pool.
+   */
+  async checkClaimEligibility(
+    raffleState: PublicKey,
+    claimant: PublicKey
+  ): Promise<ClaimEligibility> {
+    return this.program.methods
+      .checkClaimEligibility()
+      .accounts({
+        claimant,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .view({ commitment: "confirmed" });
+  }
+
+  /**
+   * Reads how many tickets a wallet holds in a simulated call.
+   * @param raffleState The PDA of the raffle state account.
+   * @param wallet The wallet whose tickets to count.
+   * @returns The wallet's tickets and the tickets sold in total.
+   */
+  async getMyTickets(raffleState: PublicKey, wallet: PublicKey): Promise<WalletTickets> {
+    return this.program.methods
+      .getMyTickets()
+      .accounts({
+        wallet,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .view({ commitment: "confirmed" });
+  }
+
+  /**
+   * Reads a wallet's chance of winning in a simulated call.
+   * @param raffleState The PDA of the raffle state account.
+   * @param wallet The wallet whose odds to read.
+   * @returns The wallet's chance of winning in basis points.
+   */
+  async getMyOdds(raffleState: PublicKey, wallet: PublicKey): Promise<number> {
+    return this.program.methods
+      .getMyOdds()
+      .accounts({
+        wallet,
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .view({ commitment: "confirmed" });
+  }
+
+  /**
+   * Reads a page of a raffle's entrants in a simulated call.
+   * @param raffleState The PDA of the raffle state account.
+   * @param start Index of the first entrant to return.
+   * @param limit Most entrants to return.
+   * @returns The page of entrants and the number of entrants in total.
+   */
+  async getEntrantsPage(
+    raffleState: PublicKey,
+    start: number,
+    limit: number
+  ): Promise<EntrantsPage> {
+    return this.program.methods
+      .getEntrantsPage(start, limit)
+      .accounts({
+        // @ts-expect-error - raffleState is in the IDL type, but the linter isn't recognizing it
+        raffleState: raffleState,
+      })
+      .view({ commitment: "confirmed" });
+  }
+
+  /**
+   * Builds the purchase log account of a raffle that records its purchases,
+   * or nothing for one that doesn't.
+   */
+  private async purchaseLogAccount(raffleState: PublicKey): Promise<{ purchaseLog?: PublicKey }> {
+    const state = await this.getState(raffleState);
+    return state.recordsPurchases ? { purchaseLog: this.purchaseLogPda(raffleState) } : {};
+  }
+
+  /**
+   * Reads a manager's raffle index.
+   * @param manager The manager the index belongs to.
+   * @returns The manager raffle index.
+   */
+  async getManagerIndex(manager: PublicKey): Promise<ManagerRaffleIndex> {
+    return this.program.account.managerRaffleIndex.fetch(
+      this.managerIndexPda(manager),
+      "confirmed"
+    );
+  }
+
+  /**
+   * Reads a raffle's purchase log.
+   * @param raffleState The PDA of the raffle state account.
+   * @returns The purchase log.
+   */
+  async getPurchaseLog(raffleState: PublicKey): Promise<PurchaseLog> {
+    return this.program.account.purchaseLog.fetch(this.purchaseLogPda(raffleState), "confirmed");
+  }
+
+  /**
+   * Derives the PDA for a raffle's purchase log account.
+   */
+  purchaseLogPda(raffleState: PublicKey): PublicKey {
+    const [pda, _bump] = PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("PurchaseLogSeed"), raffleState.toBuffer()],
+      this.program.programId
+    );
+    return pda;
+  }
+
+  /**
+   * Derives the PDA for a manager's raffle index.
+   */
+  managerIndexPda(manager: PublicKey): PublicKey {
+    const [pda, _bump] = PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("ManagerIndexSeed"), manager.toBuffer()],
+      this.program.programId
+    );
+    return pda;
+  }
+
+  /**
+   * Derives the PDA for a raffle's distribution account.
+   */
+  distributionPda(raffleState: PublicKey): PublicKey {
+    const [pda, _bump] = PublicKey.findProgramAddressSync(
+      [anchor.utils.bytes.utf8.encode("DistributionSeed"), raffleState.toBuffer()],
+      this.program.programId
+    );
+    return pda;
   }
 
   /**
@@ -348,10 +1968,35 @@ export class RaffleTestHelper {
    * Converts a RaffleState to its PDA address.
    */
   state2Pda(state: RaffleState): PublicKey {
-    const [pda, _bump] = this.pda(state.raffleManager, state.ticketPrice, state.maxTickets, state.endTime);
+    const [pda, _bump] = this.pda(
+      state.creator,
+      state.ticketPrice,
+      state.maxTickets,
+      state.originalEndTime
+    );
     return pda;
   }
 
+  /**
+   * Returns the data of the first event with the given name that the program
+   * emitted in a confirmed transaction, or null if there is none.
+   * @param sig The transaction signature.
+   * @param eventName The camelCase event name (e.g. "raffleCreatedEvent").
+   */
+  async getEvent<T>(sig: TransactionSignature, eventName: string): Promise<T | null> {
+    const txDetails = await this.connection.getTransaction(sig, {
+      maxSupportedTransactionVersion: 0,
+      commitment: "confirmed",
+    });
+    const logs = txDetails?.meta?.logMessages ?? [];
+    for (const event of this.eventParser.parseLogs(logs, false)) {
+      if (event.name === eventName) {
+        return event.data as T;
+      }
+    }
+    return null;
+  }
+
   /**
    * Fetches the current state of a raffle account.
    */
@@ -398,3 +2043,86 @@ export class RaffleTestHelper {
     return new PublicKey(authorityBytes);
   }
 }
+
+/**
+ * Mirrors RaffleState::top_ticket_holders in the program: the entrants holding
+ * the most tickets, largest first, with ties going to the earliest buyer.
+ * @param entrants The raffle's entrants, one entry per wallet.
+ * @param limit Maximum number of holders to return.
+ * @returns [holder, ticketCount] pairs in rank order.
+ */
+export function topTicketHolders(
+  entrants: EntrantEntry[],
+  limit = LOYALTY_BONUS_HOLDERS
+): [PublicKey, number][] {
+  const counts: [PublicKey, number][] = entrants.map((entry) => [entry.buyer, entry.count]);
+  // Array.prototype.sort is stable, so ties keep their first purchase order
+  counts.sort((a, b) => b[1] - a[1]);
+  return counts.slice(0, limit);
+}
+
+/**
+ * Mirrors RaffleState::tickets_of in the program.
+ * @returns The number of tickets `wallet` holds in the raffle.
+ */
+export function ticketsOf(state: RaffleState, wallet: PublicKey): number {
+  return state.entrants.find((entry) => entry.buyer.equals(wallet))?.count ?? 0;
+}
+
+/**
+ * Tickets a wallet paid in each of a raffle's accepted mints, by index.
+ */
+export function acceptedMintTicketsOf(state: RaffleState, wallet: PublicKey): number[] {
+  return (
+    state.acceptedMintTickets.find((entry) => entry.buyer.equals(wallet))?.counts ??
+    state.acceptedMints.map(() => 0)
+  );
+}
+
+/**
+ * Mirrors state::entry_index_of_ticket in the program: the index of the entry
+ * holding ticket number `ticket`, counting tickets entry by entry in order.
+ * @returns The entry index, or null past the last ticket.
+ */
+export function entryOfTicket(entrants: EntrantEntry[], ticket: number): number | null {
+  let ticketsThrough = 0;
+  for (let i = 0; i < entrants.length; i++) {
+    ticketsThrough += entrants[i].count;
+    if (ticket < ticketsThrough) {
+      return i;
+    }
+  }
+  return null;
+}
+
+/**
+ * Mirrors state::raffle_randomness in the program: the seed a raffle's winner
+ * is selected from, the draw randomness bound to the raffle's address.
+ */
+export function drawSeed(randomness: number[], raffleState: PublicKey): number[] {
+  return [
+    ...createHash("sha256").update(Buffer.from(randomness)).update(raffleState.toBuffer()).digest(),
+  ];
+}
+
+/**
+ * Mirrors merkle::leaf_hash in the program: the allowlist leaf of a wallet.
+ */
+export function allowlistLeaf(wallet: PublicKey): number[] {
+  return [...createHash("sha256").update(Buffer.from([0])).update(wallet.toBuffer()).digest()];
+}
+
+/**
+ * Mirrors merkle::node_hash in the program: the parent of two nodes, hashed
+ * in sorted order.
+ */
+export function allowlistNode(a: number[], b: number[]): number[] {
+  const [left, right] = Buffer.compare(Buffer.from(a), Buffer.from(b)) <= 0 ? [a, b] : [b, a];
+  return [
+    ...createHash("sha256")
+      .update(Buffer.from([1]))
+      .update(Buffer.from(left))
+      .update(Buffer.from(right))
+      .digest(),
+  ];
+}
@@ -0,0 +1,180 @@
+import {
+  Connection,
+  Keypair,
+  PublicKey,
+  sendAndConfirmTransaction,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { printLogs } from "./test_utils";
+
+// Minimal SPL token client, just enough to set up token raffles in tests
+// without depending on @solana/spl-token.
+
+export const TOKEN_PROGRAM_ID = new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+export const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+);
+
+/** Size of an SPL token mint account. */
+const MINT_SIZE = 82;
+/** SPL token instruction tags. */
+const MINT_TO = 7;
+const CLOSE_ACCOUNT = 9;
+const INITIALIZE_MINT_2 = 20;
+/** Associated token account instruction tag. */
+const CREATE_IDEMPOTENT = 1;
+
+/**
+ * Derives the associated token account of `owner` for `mint`.
+ * @param owner The token account owner; may be a PDA.
+ * @param mint The token mint.
+ * @returns The associated token account address.
+ */
+export function associatedTokenAddress(owner: PublicKey, mint: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [owner.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  )[0];
+}
+
+/**
+ * Creates a new mint with `payer` as its mint authority and no freeze authority.
+ * @param connection The Solana Connection object.
+ * @param payer Pays for the mint account and becomes its mint authority.
+ * @param decimals Number of decimals of the token.
+ * @returns The mint address.
+ */
+export async function createMint(
+  connection: Connection,
+  payer: Keypair,
+  decimals = 6
+): Promise<PublicKey> {
+  const mint = Keypair.generate();
+  const lamports = await connection.getMinimumBalanceForRentExemption(MINT_SIZE);
+
+  // InitializeMint2: tag, decimals, mint authority, COption<Pubkey> freeze authority
+  const data = Buffer.alloc(35);
+  data.writeUInt8(INITIALIZE_MINT_2, 0);
+  data.writeUInt8(decimals, 1);
+  payer.publicKey.toBuffer().copy(data, 2);
+  data.writeUInt8(0, 34);
+
+  const sig = await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        lamports,
+        space: MINT_SIZE,
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+        data,
+      })
+    ),
+    [payer, mint],
+    { commitment: "confirmed" }
+  );
+  await printLogs("createMint", connection, sig);
+
+  return mint.publicKey;
+}
+
+/**
+ * Creates the associated token account of `owner` for `mint`, if it doesn't
+ * exist yet, and mints `amount` base units into it.
+ * @param connection The Solana Connection object.
+ * @param mintAuthority Mint authority of `mint`; also pays for the account.
+ * @param mint The token mint.
+ * @param owner Owner of the token account.
+ * @param amount Base units to mint.
+ * @returns The token account address.
+ */
+export async function mintToOwner(
+  connection: Connection,
+  mintAuthority: Keypair,
+  mint: PublicKey,
+  owner: PublicKey,
+  amount: BN
+): Promise<PublicKey> {
+  const tokenAccount = associatedTokenAddress(owner, mint);
+
+  const sig = await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      new TransactionInstruction({
+        programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: mintAuthority.publicKey, isSigner: true, isWritable: true },
+          { pubkey: tokenAccount, isSigner: false, isWritable: true },
+          { pubkey: owner, isSigner: false, isWritable: false },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        ],
+        data: Buffer.from([CREATE_IDEMPOTENT]),
+      }),
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: tokenAccount, isSigner: false, isWritable: true },
+          { pubkey: mintAuthority.publicKey, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([MINT_TO]), amount.toArrayLike(Buffer, "le", 8)]),
+      })
+    ),
+    [mintAuthority],
+    { commitment: "confirmed" }
+  );
+  await printLogs("mintToOwner", connection, sig);
+
+  return tokenAccount;
+}
+
+/**
+ * Closes an empty token account, returning its rent to the owner.
+ * @param connection The Solana Connection object.
+ * @param owner Owner of the token account; must sign.
+ * @param tokenAccount The token account.
+ */
+export async function closeTokenAccount(
+  connection: Connection,
+  owner: Keypair,
+  tokenAccount: PublicKey
+): Promise<void> {
+  const sig = await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: tokenAccount, isSigner: false, isWritable: true },
+          { pubkey: owner.publicKey, isSigner: false, isWritable: true },
+          { pubkey: owner.publicKey, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.from([CLOSE_ACCOUNT]),
+      })
+    ),
+    [owner],
+    { commitment: "confirmed" }
+  );
+  await printLogs("closeTokenAccount", connection, sig);
+}
+
+/**
+ * Reads a token account's balance in base units.
+ * @param connection The Solana Connection object.
+ * @param tokenAccount The token account.
+ * @returns The balance.
+ */
+export async function tokenBalance(connection: Connection, tokenAccount: PublicKey): Promise<BN> {
+  const balance = await connection.getTokenAccountBalance(tokenAccount, "confirmed");
+  return new BN(balance.value.amount);
+}
@@ -220,6 +220,14 @@ export async function sweepSol(
   return sig;
 }
 
+/**
+ * Waits for the given number of milliseconds.
+ * @param ms Time to wait in milliseconds.
+ */
+export async function sleep(ms: number): Promise<void> {
+  return new Promise((resolve) => setTimeout(resolve, ms));
+}
+
 /**
  * Converts SOL to lamports.
  * @param sol Amount in SOL (can be a decimal value).
@@ -239,28 +247,16 @@ export function lamportsToSol(lamports: number): number {
 }
 
 /**
- * Generates a random u64 value from a 32-byte random seed using the same
- * algorithm as the Rust ephemeral_vrf_sdk::rnd::random_u64 function.
+ * Maps VRF randomness to a winner index using the same algorithm as the Rust
+ * state::select_winner_index function.
  *
- * The function extracts bytes at indices [0, 4, 8, 12, 16, 20, 24, 28]
- * and interprets them as a little-endian u64.
+ * The 32 bytes are read as a little-endian 256-bit number and scaled to
+ * `randomness * numEntrants / 2^256`.
  *
  * @param randomness - A 32-byte array containing random data from the VRF
- * @returns A BN representing the random u64 value
+ * @param numEntrants - Number of tickets sold, at least 1
+ * @returns The index of the winning ticket
  */
-export function vrf_random_u64(randomness: number[]): BN {
-  // Extract bytes at specific indices as done in the Rust implementation
-  const bytes = Buffer.from([
-    randomness[0],
-    randomness[4],
-    randomness[8],
-    randomness[12],
-    randomness[16],
-    randomness[20],
-    randomness[24],
-    randomness[28],
-  ]);
-
-  // Interpret as little-endian u64
-  return new BN(bytes, "le");
+export function select_winner_index(randomness: number[], numEntrants: number): number {
+  return new BN(randomness, "le").muln(numEntrants).shrn(256).toNumber();
 }